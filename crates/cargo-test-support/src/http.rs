@@ -0,0 +1,220 @@
+//! A tiny HTTP server for tests that need to talk to a registry web API.
+//!
+//! The `file://` stand-in used by most registry tests can only answer `GET`
+//! requests with a fixed body. Tests that need to check the method, headers,
+//! or body of a request, or that need to answer with a specific status code,
+//! can use `HttpServer` instead.
+//!
+//! # Example
+//! ```
+//! let server = HttpServer::new(|req| {
+//!     assert_eq!(req.method, "GET");
+//!     Response::json(200, r#"{"ok": true}"#)
+//! });
+//! // Point something at `server.url()` ...
+//! ```
+
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// A request received by an `HttpServer`.
+#[derive(Clone, Debug)]
+pub struct Request {
+    /// The request method, such as `GET` or `PUT`.
+    pub method: String,
+    /// The path and query of the request, such as `/api/v1/crates`.
+    pub path: String,
+    /// Headers as `(name, value)` pairs. Names are lowercased.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Returns the value of the first header named `name`, if any.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.headers
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the body as a string.
+    pub fn body_str(&self) -> &str {
+        std::str::from_utf8(&self.body).expect("request body is not utf-8")
+    }
+}
+
+/// A response to send from an `HttpServer` handler.
+#[derive(Clone, Debug)]
+pub struct Response {
+    pub code: u32,
+    /// Extra headers as full `Name: value` lines.
+    pub headers: Vec<String>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// A response with the given status and JSON body.
+    pub fn json(code: u32, body: &str) -> Response {
+        Response {
+            code,
+            headers: vec!["Content-Type: application/json".to_string()],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    /// An empty response with the given status.
+    pub fn status(code: u32) -> Response {
+        Response {
+            code,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+}
+
+type Handler = dyn Fn(&Request) -> Response + Send + Sync;
+
+/// A running HTTP server. The server is shut down when this is dropped.
+pub struct HttpServer {
+    addr: SocketAddr,
+    done: Arc<AtomicBool>,
+    requests: Arc<Mutex<Vec<Request>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HttpServer {
+    /// Starts a server on a random local port which answers every request
+    /// with `handler`.
+    pub fn new<F>(handler: F) -> HttpServer
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        let listener = t!(TcpListener::bind("127.0.0.1:0"));
+        let addr = t!(listener.local_addr());
+        let done = Arc::new(AtomicBool::new(false));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+        let thread = {
+            let done = done.clone();
+            let requests = requests.clone();
+            thread::spawn(move || {
+                for conn in listener.incoming() {
+                    if done.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let conn = match conn {
+                        Ok(conn) => conn,
+                        Err(_) => continue,
+                    };
+                    let handler = handler.clone();
+                    let requests = requests.clone();
+                    thread::spawn(move || serve(conn, &*handler, &requests));
+                }
+            })
+        };
+        HttpServer {
+            addr,
+            done,
+            requests,
+            thread: Some(thread),
+        }
+    }
+
+    /// The base URL of the server, such as `http://127.0.0.1:1234`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// All requests received so far, in the order they were answered.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::SeqCst);
+        // Wake up the accept loop so it notices the shutdown.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(conn: TcpStream, handler: &Handler, requests: &Mutex<Vec<Request>>) {
+    let mut writer = t!(conn.try_clone());
+    let mut reader = BufReader::new(conn);
+    let mut line = String::new();
+    if reader.read_line(&mut line).unwrap_or(0) == 0 {
+        return;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let path = parts.next().unwrap_or("").to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        t!(reader.read_line(&mut line));
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let mut kv = header.splitn(2, ':');
+        let name = kv.next().unwrap().trim().to_lowercase();
+        let value = kv.next().unwrap_or("").trim().to_string();
+        headers.push((name, value));
+    }
+
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+    };
+    if header("expect").map_or(false, |v| v.eq_ignore_ascii_case("100-continue")) {
+        t!(writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n"));
+    }
+    let len = header("content-length").map_or(0, |v| t!(v.parse::<usize>()));
+    let mut body = vec![0; len];
+    t!(reader.read_exact(&mut body));
+
+    let req = Request {
+        method,
+        path,
+        headers,
+        body,
+    };
+    let res = handler(&req);
+    requests.lock().unwrap().push(req);
+
+    let mut out = format!("HTTP/1.1 {} {}\r\n", res.code, reason(res.code));
+    for header in &res.headers {
+        out.push_str(header);
+        out.push_str("\r\n");
+    }
+    out.push_str(&format!("Content-Length: {}\r\n", res.body.len()));
+    out.push_str("Connection: close\r\n\r\n");
+    t!(writer.write_all(out.as_bytes()));
+    t!(writer.write_all(&res.body));
+}
+
+fn reason(code: u32) -> &'static str {
+    match code {
+        200 => "OK",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        _ => "",
+    }
+}
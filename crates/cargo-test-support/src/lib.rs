@@ -36,6 +36,7 @@ pub use cargo_test_macro::cargo_test;
 
 pub mod cross_compile;
 pub mod git;
+pub mod http;
 pub mod paths;
 pub mod publish;
 pub mod registry;
@@ -1546,6 +1547,8 @@ fn substitute_macros(input: &str) -> String {
         ("[HELP]", "help:"),
         ("[DOCUMENTING]", " Documenting"),
         ("[FRESH]", "       Fresh"),
+        ("[DIRTY]", "       Dirty"),
+        ("[TOUCHED]", "     Touched"),
         ("[UPDATING]", "    Updating"),
         ("[ADDING]", "      Adding"),
         ("[REMOVING]", "    Removing"),
@@ -1565,11 +1568,16 @@ fn substitute_macros(input: &str) -> String {
         ("[IGNORED]", "     Ignored"),
         ("[INSTALLED]", "   Installed"),
         ("[REPLACED]", "    Replaced"),
+        ("[RESTORED]", "    Restored"),
+        ("[REBUILT]", "     Rebuilt"),
         ("[BUILDING]", "    Building"),
         ("[LOGIN]", "       Login"),
         ("[LOGOUT]", "      Logout"),
         ("[YANK]", "        Yank"),
         ("[OWNER]", "       Owner"),
+        ("[INDEXING]", "    Indexing"),
+        ("[MOVING]", "      Moving"),
+        ("[VERIFIED]", "    Verified"),
     ];
     let mut result = input.to_owned();
     for &(pat, subst) in &macros {
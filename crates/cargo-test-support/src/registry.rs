@@ -1,6 +1,7 @@
-use crate::git::repo;
+use crate::git::{self, repo};
+use crate::http::{HttpServer, Request, Response};
 use crate::paths;
-use cargo::core::manifest::SUBCRATE_DELIMETER;
+use cargo::core::manifest::{SUBCRATE_DELIMETER, SUBCRATE_DELIMETER_REGISTRY_FILENAME_REPLACEMENT};
 use cargo::sources::CRATES_IO_INDEX;
 use cargo::util::Sha256;
 use flate2::write::GzEncoder;
@@ -147,6 +148,7 @@ pub struct Package {
     invalid_json: bool,
     proc_macro: bool,
     links: Option<String>,
+    pubtime: Option<String>,
 }
 
 #[derive(Clone)]
@@ -230,6 +232,58 @@ pub fn init_registry(registry_path: PathBuf, dl_url: String, api_url: Url, api_p
     fs::create_dir_all(api_path.join("api/v1/crates")).unwrap();
 }
 
+/// Replaces the `config.json` of the index at `registry_path` with `config`
+/// and commits the change, for example to point `api` at an `HttpServer`.
+pub fn write_index_config(registry_path: &Path, config: &str) {
+    let repo = t!(git2::Repository::open(registry_path));
+    t!(fs::write(registry_path.join("config.json"), config));
+    git::add(&repo);
+    git::commit(&repo);
+}
+
+/// Starts an `HttpServer` that serves the index at `registry_path()` as a
+/// sparse index, at `sparse+<server.url()>`.
+///
+/// Files are sent with an `ETag` of their checksum, and requests whose
+/// `If-None-Match` matches it get a `304 Not Modified` response.
+pub fn sparse_index_server() -> HttpServer {
+    sparse_index_server_with(|_| None)
+}
+
+/// Like `sparse_index_server`, but requests for which `answer` returns a
+/// response get that response instead, for example to make some files fail.
+pub fn sparse_index_server_with<F>(answer: F) -> HttpServer
+where
+    F: Fn(&Request) -> Option<Response> + Send + Sync + 'static,
+{
+    init();
+    let root = registry_path();
+    HttpServer::new(move |req| {
+        if let Some(response) = answer(req) {
+            return response;
+        }
+        let path = root.join(req.path.trim_start_matches('/'));
+        let contents = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(_) => return Response::status(404),
+        };
+        let etag = format!("\"{}\"", cksum(&contents));
+        let headers = vec![format!("ETag: {}", etag)];
+        if req.header("if-none-match") == Some(&etag) {
+            return Response {
+                code: 304,
+                headers,
+                body: Vec::new(),
+            };
+        }
+        Response {
+            code: 200,
+            headers,
+            body: contents,
+        }
+    })
+}
+
 impl Package {
     /// Creates a new package builder.
     /// Call `publish()` to finalize and build the package.
@@ -248,6 +302,7 @@ impl Package {
             invalid_json: false,
             proc_macro: false,
             links: None,
+            pubtime: None,
         }
     }
 
@@ -376,6 +431,12 @@ impl Package {
         self
     }
 
+    /// Sets the RFC 3339 publish time recorded in the index entry.
+    pub fn pubtime(&mut self, pubtime: &str) -> &mut Package {
+        self.pubtime = Some(pubtime.to_string());
+        self
+    }
+
     /// Creates the package and place it in the registry.
     ///
     /// This does not actually use Cargo's publishing system, but instead
@@ -429,6 +490,7 @@ impl Package {
             "features": self.features,
             "yanked": self.yanked,
             "links": self.links,
+            "pubtime": self.pubtime,
         })
         .to_string();
 
@@ -531,7 +593,7 @@ impl Package {
             };
             manifest.push_str(&format!(
                 r#"
-                [{}{}dependencies.{}]
+                [{}{}dependencies."{}"]
                 version = "{}"
             "#,
                 target, kind, dep.name, dep.vers
@@ -551,7 +613,7 @@ impl Package {
     fn append<W: Write>(&self, ar: &mut Builder<W>, file: &str, contents: &str) {
         self.append_extra(
             ar,
-            &format!("{}-{}/{}", self.name, self.vers, file),
+            &format!("{}-{}/{}", self.registry_safe_name(), self.vers, file),
             contents,
         );
     }
@@ -564,17 +626,27 @@ impl Package {
         t!(ar.append(&header, contents.as_bytes()));
     }
 
+    /// Returns the name used for this package in filenames and download
+    /// URLs, with any namespace delimiters replaced.
+    fn registry_safe_name(&self) -> String {
+        self.name.replace(
+            SUBCRATE_DELIMETER,
+            SUBCRATE_DELIMETER_REGISTRY_FILENAME_REPLACEMENT,
+        )
+    }
+
     /// Returns the path to the compressed package file.
     pub fn archive_dst(&self) -> PathBuf {
+        let name = self.registry_safe_name();
         if self.local {
-            registry_path().join(format!("{}-{}.crate", self.name, self.vers))
+            registry_path().join(format!("{}-{}.crate", name, self.vers))
         } else if self.alternative {
             alt_dl_path()
-                .join(&self.name)
+                .join(&name)
                 .join(&self.vers)
-                .join(&format!("{}-{}.crate", self.name, self.vers))
+                .join(&format!("{}-{}.crate", name, self.vers))
         } else {
-            dl_path().join(&self.name).join(&self.vers).join("download")
+            dl_path().join(&name).join(&self.vers).join("download")
         }
     }
 }
@@ -2,6 +2,7 @@
 #![allow(clippy::identity_op)] // used for vertical alignment
 
 use std::collections::BTreeMap;
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::{Cursor, SeekFrom};
@@ -81,12 +82,81 @@ pub struct User {
     pub name: Option<String>,
 }
 
+/// Ownership information about a namespace such as `foo/`.
+#[derive(Deserialize)]
+pub struct Namespace {
+    /// The top-level namespace, without the trailing `/`.
+    pub name: String,
+    pub owners: Vec<User>,
+    /// Whether the user making the request owns the namespace. This is
+    /// `None` when the request was made without a token, or when the
+    /// registry doesn't say.
+    #[serde(default)]
+    pub is_owner: Option<bool>,
+}
+
 pub struct Warnings {
     pub invalid_categories: Vec<String>,
     pub invalid_badges: Vec<String>,
     pub other: Vec<String>,
 }
 
+/// An unsuccessful response from the registry's web API.
+///
+/// This is the error returned by `Registry` methods when the server responds
+/// with an error, so callers can `downcast_ref` to it to inspect the status.
+#[derive(Debug)]
+pub enum ResponseError {
+    /// The server sent a list of errors in the response body.
+    Api { code: u32, errors: Vec<String> },
+    /// The server sent a non-200 response without any error details.
+    Code {
+        code: u32,
+        headers: Vec<String>,
+        body: String,
+    },
+}
+
+impl ResponseError {
+    /// The HTTP status code of the response.
+    pub fn code(&self) -> u32 {
+        match *self {
+            ResponseError::Api { code, .. } | ResponseError::Code { code, .. } => code,
+        }
+    }
+}
+
+impl fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseError::Api { code, errors } => write!(
+                f,
+                "api errors (status {} {}): {}",
+                code,
+                reason(*code),
+                errors.join(", ")
+            ),
+            ResponseError::Code {
+                code,
+                headers,
+                body,
+            } => write!(
+                f,
+                "failed to get a 200 OK response, got {}\n\
+                 headers:\n\
+                 \t{}\n\
+                 body:\n\
+                 {}",
+                code,
+                headers.join("\n\t"),
+                body,
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResponseError {}
+
 #[derive(Deserialize)]
 struct R {
     ok: bool,
@@ -142,6 +212,11 @@ impl Registry {
         is_url_crates_io(&self.host)
     }
 
+    /// Whether requests that can be authorized are sent with a token.
+    pub fn has_token(&self) -> bool {
+        self.token.is_some()
+    }
+
     pub fn add_owners(&mut self, krate: &str, owners: &[&str]) -> Result<String> {
         let body = serde_json::to_string(&OwnersReq { users: owners })?;
         let body = self.put(&format!("/crates/{}/owners", krate), body.as_bytes())?;
@@ -161,6 +236,58 @@ impl Registry {
         Ok(serde_json::from_str::<Users>(&body)?.users)
     }
 
+    /// Invites users or teams as owners of every crate in `namespace`.
+    pub fn add_namespace_owners(&mut self, namespace: &str, owners: &[&str]) -> Result<String> {
+        let body = serde_json::to_string(&OwnersReq { users: owners })?;
+        let body = self
+            .put(&namespace_owners_path(namespace), body.as_bytes())
+            .map_err(|e| namespace_error(e, namespace))?;
+        assert!(serde_json::from_str::<OwnerResponse>(&body)?.ok);
+        Ok(serde_json::from_str::<OwnerResponse>(&body)?.msg)
+    }
+
+    /// Removes users or teams as owners of `namespace`.
+    pub fn remove_namespace_owners(&mut self, namespace: &str, owners: &[&str]) -> Result<()> {
+        let body = serde_json::to_string(&OwnersReq { users: owners })?;
+        let body = self
+            .delete(&namespace_owners_path(namespace), Some(body.as_bytes()))
+            .map_err(|e| namespace_error(e, namespace))?;
+        assert!(serde_json::from_str::<OwnerResponse>(&body)?.ok);
+        Ok(())
+    }
+
+    /// Looks up who owns `namespace`.
+    ///
+    /// Returns `None` if nobody has claimed the namespace yet. The token is
+    /// sent if there is one so that the registry can fill in `is_owner`.
+    pub fn namespace(&mut self, namespace: &str) -> Result<Option<Namespace>> {
+        let path = format!(
+            "/namespaces/{}",
+            percent_encode(namespace.as_bytes(), NON_ALPHANUMERIC)
+        );
+        let auth = if self.token.is_some() {
+            Auth::Authorized
+        } else {
+            Auth::Unauthorized
+        };
+        self.handle.get(true)?;
+        match self.req(&path, None, auth) {
+            Ok(body) => Ok(Some(serde_json::from_str::<Namespace>(&body)?)),
+            Err(e) => match e.downcast_ref::<ResponseError>() {
+                Some(r) if r.code() == 404 => Ok(None),
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// Lists the owners of `namespace`.
+    pub fn list_namespace_owners(&mut self, namespace: &str) -> Result<Vec<User>> {
+        let body = self
+            .get(&namespace_owners_path(namespace))
+            .map_err(|e| namespace_error(e, namespace))?;
+        Ok(serde_json::from_str::<Users>(&body)?.users)
+    }
+
     pub fn publish(&mut self, krate: &NewCrate, mut tarball: &File) -> Result<Warnings> {
         let json = serde_json::to_string(krate)?;
         // Prepare the body. The format of the upload request is:
@@ -253,6 +380,28 @@ impl Registry {
         Ok((crates.crates, crates.meta.total))
     }
 
+    /// Lists one page of the crates in `namespace`, along with the total
+    /// number of crates in it. Pages are numbered from 1.
+    pub fn search_namespace(
+        &mut self,
+        namespace: &str,
+        per_page: u32,
+        page: u32,
+    ) -> Result<(Vec<Crate>, u32)> {
+        let formatted_namespace = percent_encode(namespace.as_bytes(), NON_ALPHANUMERIC);
+        let body = self.req(
+            &format!(
+                "/crates?namespace={}&per_page={}&page={}",
+                formatted_namespace, per_page, page
+            ),
+            None,
+            Auth::Unauthorized,
+        )?;
+
+        let crates = serde_json::from_str::<Crates>(&body)?;
+        Ok((crates.crates, crates.meta.total))
+    }
+
     pub fn yank(&mut self, krate: &str, version: &str) -> Result<()> {
         let body = self.delete(&format!("/crates/{}/{}/yank", krate, version), None)?;
         assert!(serde_json::from_str::<R>(&body)?.ok);
@@ -338,31 +487,42 @@ impl Registry {
                  upload a crate it may be too large. If the crate is under \
                  10MB in size, you can email help@crates.io for assistance."
             ),
-            (code, Some(errors)) => {
-                let reason = reason(code);
-                bail!(
-                    "api errors (status {} {}): {}",
-                    code,
-                    reason,
-                    errors.join(", ")
-                )
-            }
-            (code, None) => bail!(
-                "failed to get a 200 OK response, got {}\n\
-                 headers:\n\
-                 \t{}\n\
-                 body:\n\
-                 {}",
+            (code, Some(errors)) => bail!(ResponseError::Api { code, errors }),
+            (code, None) => bail!(ResponseError::Code {
                 code,
-                headers.join("\n\t"),
+                headers,
                 body,
-            ),
+            }),
         }
 
         Ok(body)
     }
 }
 
+fn namespace_owners_path(namespace: &str) -> String {
+    format!(
+        "/namespaces/{}/owners",
+        percent_encode(namespace.as_bytes(), NON_ALPHANUMERIC)
+    )
+}
+
+/// Adds a description of common failures to an error from one of the
+/// namespace endpoints.
+fn namespace_error(e: anyhow::Error, namespace: &str) -> anyhow::Error {
+    let code = e.downcast_ref::<ResponseError>().map(|e| e.code());
+    match code {
+        Some(401) | Some(403) => e.context(format!(
+            "you are not an owner of the namespace `{}`",
+            namespace
+        )),
+        Some(404) => e.context(format!(
+            "the namespace `{}` does not exist on this registry",
+            namespace
+        )),
+        _ => e,
+    }
+}
+
 fn reason(code: u32) -> &'static str {
     // Taken from https://developer.mozilla.org/en-US/docs/Web/HTTP/Status
     match code {
@@ -45,6 +45,7 @@ pub fn cli() -> App {
             "Run all benchmarks regardless of failure",
         ))
         .arg_unit_graph()
+        .arg_explain_rebuild()
         .after_help("Run `cargo help bench` for more detailed information.\n")
 }
 
@@ -0,0 +1,96 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, IndexBuildOptions, IndexInput, IndexVerifyOptions};
+
+pub fn cli() -> App {
+    subcommand("index")
+        .about("Build or check a registry index for namespaced crates (unstable)")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("build")
+                .about("Write a namespaced index for a directory of packages or an index")
+                .arg(
+                    Arg::with_name("output")
+                        .help("Directory to write the index to")
+                        .required(true),
+                )
+                .arg(
+                    opt("from-crates", "Directory of `.crate` files to index")
+                        .value_name("DIR")
+                        .required_unless("from-index")
+                        .conflicts_with("from-index"),
+                )
+                .arg(
+                    opt(
+                        "from-index",
+                        "Existing index to move to the namespaced layout",
+                    )
+                    .value_name("DIR"),
+                )
+                .arg(
+                    opt(
+                        "index-url",
+                        "URL the index will be served from (crates.io by default)",
+                    )
+                    .value_name("URL")
+                    .requires("from-crates"),
+                ),
+        )
+        .subcommand(
+            subcommand("verify")
+                .about("Check that an index is laid out for namespaced crates")
+                .arg(
+                    Arg::with_name("index")
+                        .help("Directory of the index to check")
+                        .required(true),
+                )
+                .arg(
+                    opt(
+                        "crates",
+                        "Directory of `.crate` files to check checksums against",
+                    )
+                    .value_name("DIR"),
+                ),
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    config.cli_unstable().fail_if_stable_command("index")?;
+    match args.subcommand() {
+        ("build", Some(args)) => {
+            let input = match args.value_of_path("from-crates", config) {
+                Some(dir) => IndexInput::Crates(dir),
+                None => IndexInput::Index(args.value_of_path("from-index", config).unwrap()),
+            };
+            let opts = IndexBuildOptions {
+                input,
+                output: args.value_of_path("output", config).unwrap(),
+                index_url: args.value_of("index-url").map(String::from),
+            };
+            ops::build_index(config, &opts)?;
+        }
+        ("verify", Some(args)) => {
+            let opts = IndexVerifyOptions {
+                index: args.value_of_path("index", config).unwrap(),
+                crates: args.value_of_path("crates", config),
+            };
+            let problems = ops::verify_index(&opts)?;
+            if !problems.is_empty() {
+                for problem in &problems {
+                    config.shell().error(problem)?;
+                }
+                return Err(anyhow::format_err!(
+                    "found {} problem(s) in the index at `{}`",
+                    problems.len(),
+                    opts.index.display()
+                )
+                .into());
+            }
+            config
+                .shell()
+                .status("Verified", format!("`{}`", opts.index.display()))?;
+        }
+        _ => unreachable!("clap should ensure we have a subcommand"),
+    }
+    Ok(())
+}
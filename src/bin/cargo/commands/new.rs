@@ -9,6 +9,16 @@ pub fn cli() -> App {
         .arg(Arg::with_name("path").required(true))
         .arg(opt("registry", "Registry to use").value_name("REGISTRY"))
         .arg_new_opts()
+        .arg(
+            opt(
+                "namespace-layout",
+                "Directory layout for a namespaced package: \
+                 `foo/bar/` (nested) or `foo-bar/` (flat)",
+            )
+            .value_name("LAYOUT")
+            .possible_values(&["nested", "flat"])
+            .default_value("nested"),
+        )
         .after_help("Run `cargo help new` for more detailed information.\n")
 }
 
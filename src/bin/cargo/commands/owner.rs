@@ -7,6 +7,14 @@ pub fn cli() -> App {
         .about("Manage the owners of a crate on the registry")
         .arg(opt("quiet", "No output printed to stdout").short("q"))
         .arg(Arg::with_name("crate"))
+        .arg(
+            opt(
+                "namespace",
+                "Manage the owners of a namespace instead of a crate",
+            )
+            .value_name("NAMESPACE")
+            .conflicts_with("crate"),
+        )
         .arg(
             multi_opt(
                 "add",
@@ -36,6 +44,7 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let registry = args.registry(config)?;
     let opts = OwnersOptions {
         krate: args.value_of("crate").map(|s| s.to_string()),
+        namespace: args.value_of("namespace").map(|s| s.to_string()),
         token: args.value_of("token").map(|s| s.to_string()),
         index: args.value_of("index").map(|s| s.to_string()),
         to_add: args
@@ -0,0 +1,35 @@
+use crate::command_prelude::*;
+
+use cargo::ops;
+
+pub fn cli() -> App {
+    subcommand("report")
+        .about("Generate reports about previous builds (unstable)")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("timings")
+                .about("Compare the timings of two builds saved by `-Z timings=json`")
+                .arg(
+                    opt("compare", "Timings reports of an old and a new build")
+                        .value_names(&["OLD", "NEW"])
+                        .number_of_values(2)
+                        .required(true),
+                ),
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    config.cli_unstable().fail_if_stable_command("report")?;
+    match args.subcommand() {
+        ("timings", Some(args)) => {
+            let reports: Vec<_> = args
+                .values_of_os("compare")
+                .unwrap()
+                .map(|path| config.cwd().join(path))
+                .collect();
+            ops::report_timings_comparison(config, &reports[0], &reports[1])?;
+        }
+        _ => unreachable!("clap should ensure we have a subcommand"),
+    }
+    Ok(())
+}
@@ -26,6 +26,7 @@ pub fn cli() -> App {
         .arg_manifest_path()
         .arg_message_format()
         .arg_unit_graph()
+        .arg_explain_rebuild()
         .after_help("Run `cargo help run` for more detailed information.\n")
 }
 
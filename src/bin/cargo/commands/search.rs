@@ -1,7 +1,5 @@
 use crate::command_prelude::*;
 
-use std::cmp::min;
-
 use cargo::ops;
 
 pub fn cli() -> App {
@@ -13,7 +11,8 @@ pub fn cli() -> App {
         .arg(
             opt(
                 "limit",
-                "Limit the number of results (default: 10, max: 100)",
+                "Limit the number of results (default: 10, max: 100, \
+                 or all crates when listing a namespace)",
             )
             .value_name("LIMIT"),
         )
@@ -25,7 +24,6 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let registry = args.registry(config)?;
     let index = args.index(config)?;
     let limit = args.value_of_u32("limit")?;
-    let limit = min(100, limit.unwrap_or(10));
     let query: Vec<&str> = args.values_of("query").unwrap_or_default().collect();
     let query: String = query.join("+");
     ops::search(&query, config, index, limit, registry)?;
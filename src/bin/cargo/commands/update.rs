@@ -1,7 +1,8 @@
 use crate::command_prelude::*;
 
-use cargo::ops::{self, UpdateOptions};
+use cargo::ops::{self, UpdateMessageFormat, UpdateOptions};
 use cargo::util::print_available_packages;
+use std::str::FromStr;
 
 pub fn cli() -> App {
     subcommand("update")
@@ -14,6 +15,12 @@ pub fn cli() -> App {
             "Force updating all dependencies of SPEC as well when used with -p",
         ))
         .arg_dry_run("Don't actually write the lockfile")
+        .arg(
+            opt("message-format", "Output representation: human, json")
+                .value_name("FMT")
+                .possible_values(&["human", "json"])
+                .default_value("human"),
+        )
         .arg(
             opt(
                 "precise",
@@ -21,23 +28,39 @@ pub fn cli() -> App {
             )
             .value_name("PRECISE"),
         )
+        .arg(
+            opt(
+                "max-age",
+                "Only update to versions published at least DURATION ago",
+            )
+            .value_name("DURATION"),
+        )
         .arg_manifest_path()
         .after_help("Run `cargo help update` for more detailed information.\n")
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if let Some(max_age) = args.value_of("max-age") {
+        let max_age = humantime::parse_duration(max_age)
+            .map_err(|e| anyhow::format_err!("invalid value `{}` for --max-age: {}", max_age, e))?;
+        config.set_registry_max_age(max_age);
+    }
+
     let ws = args.workspace(config)?;
 
     if args.is_present_with_zero_values("package") {
         print_available_packages(&ws)?;
     }
 
+    let message_format = UpdateMessageFormat::from_str(args.value_of("message-format").unwrap())
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
     let update_opts = UpdateOptions {
         aggressive: args.is_present("aggressive"),
         precise: args.value_of("precise"),
         to_update: values(args, "package"),
         dry_run: args.is_present("dry-run"),
         workspace: args.is_present("workspace"),
+        message_format,
         config,
     };
     ops::update_lockfile(&ws, &update_opts)?;
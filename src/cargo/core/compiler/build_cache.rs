@@ -0,0 +1,249 @@
+//! A build cache of compiled units that is shared between workspaces.
+//!
+//! With `-Z build-cache`, libraries of non-path packages are stored in a
+//! content-addressed cache, `$CARGO_HOME/build-cache` by default. Each entry
+//! is a directory named after the unit's crate name, its `Metadata` hash and
+//! a hash of the full rustc version and the unit's rustflags. It holds the
+//! unit's outputs (`.rlib`, `.rmeta`, ...) and Cargo's dep-info file for it.
+//!
+//! `fingerprint::prepare_target` restores an entry when a cacheable unit is
+//! dirty, instead of running rustc, and stores the outputs after rustc ran
+//! for a unit that wasn't in the cache. At the end of a build the least
+//! recently used entries are removed until the cache fits in
+//! `build-cache.max-size`.
+//!
+//! Packages with a build script are never cached, because the script's
+//! output may depend on the machine or the environment rather than on what
+//! is hashed in the key.
+//!
+//! The handling of entries and their eviction is shared with the cache of
+//! build script runs, see `build_script_cache`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::format_err;
+use filetime::FileTime;
+use log::debug;
+use serde::Deserialize;
+
+use crate::core::compiler::{CompileMode, Context, FileFlavor, Unit};
+use crate::util::config::ConfigRelativePath;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{self, memory, paths, Config};
+
+/// The name of the dep-info file in an entry.
+const DEP_INFO: &str = "dep-info";
+
+/// The default for `build-cache.max-size`, 10 GiB.
+const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024 * 1024;
+
+/// The `[build-cache]` and `[build-script-cache]` config tables.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+struct BuildCacheConfig {
+    dir: Option<ConfigRelativePath>,
+    max_size: Option<String>,
+}
+
+pub struct BuildCache {
+    /// Directory holding one directory per entry.
+    dir: PathBuf,
+    /// The size, in bytes, the cache is trimmed to after a build.
+    max_size: u64,
+}
+
+impl BuildCache {
+    /// Returns the build cache configured for this session, or `None` if
+    /// `-Z build-cache` isn't enabled.
+    pub fn new(config: &Config) -> CargoResult<Option<BuildCache>> {
+        if !config.cli_unstable().build_cache {
+            return Ok(None);
+        }
+        BuildCache::open(config, "build-cache").map(Some)
+    }
+
+    /// Opens the cache configured by the `table` config table, which lives
+    /// in `$CARGO_HOME/<table>` unless `<table>.dir` is set.
+    pub(super) fn open(config: &Config, table: &str) -> CargoResult<BuildCache> {
+        let cache_config = config
+            .get::<Option<BuildCacheConfig>>(table)?
+            .unwrap_or_default();
+        let dir = match cache_config.dir {
+            Some(dir) => dir.resolve_path(config),
+            None => config.home().join(table).into_path_unlocked(),
+        };
+        let max_size = match cache_config.max_size {
+            Some(size) => memory::parse_size(&size).ok_or_else(|| {
+                format_err!(
+                    "`{}.max-size` must be a size such as `10GiB`, found `{}`",
+                    table,
+                    size
+                )
+            })?,
+            None => DEFAULT_MAX_SIZE,
+        };
+        Ok(BuildCache { dir, max_size })
+    }
+
+    pub(super) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the name of the entry for `unit`, or `None` if `unit` can't be
+    /// cached.
+    ///
+    /// `rustflags` are the extra flags the unit is compiled with, which
+    /// aren't part of its `Metadata`.
+    pub fn key(cx: &Context<'_, '_>, unit: &Unit, rustflags: &[String]) -> Option<String> {
+        let cacheable = !unit.is_local()
+            && !unit.pkg.has_custom_build()
+            && unit.target.is_lib()
+            && (unit.mode == CompileMode::Build || unit.mode.is_check());
+        if !cacheable {
+            return None;
+        }
+        let metadata = cx.files().metadata(unit)?;
+        let rustc = util::short_hash(&(&cx.bcx.rustc().verbose_version, rustflags));
+        Some(format!(
+            "{}-{}-{}",
+            unit.target.crate_name(),
+            metadata,
+            rustc
+        ))
+    }
+
+    /// Copies the files of entry `key` to `outputs` and `dep_info`.
+    ///
+    /// Returns `false` without changing anything if there is no entry for
+    /// `key` or if it lacks one of `outputs`.
+    pub fn restore(&self, key: &str, outputs: &[PathBuf], dep_info: &Path) -> CargoResult<bool> {
+        let entry = self.dir.join(key);
+        let mut files = vec![(entry.join(DEP_INFO), dep_info)];
+        for output in outputs {
+            files.push((entry.join(output.file_name().unwrap()), output.as_path()));
+        }
+        if files.iter().any(|(cached, _)| !cached.is_file()) {
+            return Ok(false);
+        }
+        // The dep-info goes last, as the fingerprint treats it as the
+        // reference that the other files are compared to.
+        for (cached, dst) in files.iter().skip(1).chain(files.iter().take(1)) {
+            paths::copy(cached, dst)?;
+        }
+        self.touch(Path::new(key));
+        debug!("restored {} from the build cache", key);
+        Ok(true)
+    }
+
+    /// Adds `outputs` and `dep_info` to the cache as entry `key`, replacing
+    /// an existing entry that lacked some of them.
+    pub fn store(&self, key: &str, outputs: &[PathBuf], dep_info: &Path) -> CargoResult<()> {
+        self.store_entry(Path::new(key), |tmp| {
+            for output in outputs {
+                paths::copy(output, tmp.join(output.file_name().unwrap()))?;
+            }
+            paths::copy(dep_info, tmp.join(DEP_INFO))?;
+            Ok(())
+        })
+    }
+
+    /// Creates or replaces the entry at `name`, relative to the cache
+    /// directory, with the files `fill` writes to the directory it's given.
+    pub(super) fn store_entry(
+        &self,
+        name: &Path,
+        fill: impl FnOnce(&Path) -> CargoResult<()>,
+    ) -> CargoResult<()> {
+        let entry = self.dir.join(name);
+        let parent = entry.parent().unwrap();
+        paths::create_dir_all(parent)?;
+        // Fill a temporary directory first so other Cargo processes never
+        // see a partial entry.
+        let tmp = tempfile::Builder::new()
+            .prefix(".tmp-")
+            .tempdir_in(parent)?;
+        fill(tmp.path())?;
+
+        if entry.exists() {
+            paths::remove_dir_all(&entry)?;
+        }
+        // Another process may have stored the same entry in the meantime,
+        // which is as good as ours.
+        if let Err(e) = fs::rename(tmp.path(), &entry) {
+            debug!(
+                "failed to add {} to {}: {}",
+                name.display(),
+                self.dir.display(),
+                e
+            );
+        }
+        Ok(())
+    }
+
+    /// Marks the entry at `name` as just used. Eviction works on the
+    /// top-level directories of the cache, so that's the one updated.
+    pub(super) fn touch(&self, name: &Path) {
+        if let Some(top) = name.components().next() {
+            let top = self.dir.join(top);
+            paths::set_file_time_no_err(&top, FileTime::from_system_time(SystemTime::now()));
+        }
+    }
+
+    /// Removes the least recently used entries until the cache is no larger
+    /// than its `max-size`.
+    pub fn evict(&self) -> CargoResult<()> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(_) => return Ok(()),
+        };
+        let mut entries = Vec::new();
+        let mut total = 0;
+        for entry in read_dir {
+            let entry = entry
+                .chain_err(|| format!("failed to read the cache at `{}`", self.dir.display()))?;
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') || !path.is_dir() {
+                continue;
+            }
+            let size = dir_size(&path)?;
+            total += size;
+            entries.push((paths::mtime(&path)?, size, path));
+        }
+        if total <= self.max_size {
+            return Ok(());
+        }
+        entries.sort();
+        for (_, size, path) in entries {
+            if total <= self.max_size {
+                break;
+            }
+            debug!("removing {} from the cache", path.display());
+            paths::remove_dir_all(&path)?;
+            total -= size;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the outputs of `unit` that are stored in the build cache.
+pub fn cached_outputs(cx: &Context<'_, '_>, unit: &Unit) -> CargoResult<Vec<PathBuf>> {
+    Ok(cx
+        .outputs(unit)?
+        .iter()
+        .filter(|output| output.flavor != FileFlavor::DebugInfo)
+        .map(|output| output.path.clone())
+        .collect())
+}
+
+fn dir_size(dir: &Path) -> CargoResult<u64> {
+    let mut size = 0;
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = entry.chain_err(|| format!("failed to read `{}`", dir.display()))?;
+        if entry.file_type().is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
@@ -0,0 +1,255 @@
+//! A cache of build script runs that survives `cargo clean` and is shared
+//! between profiles.
+//!
+//! With `-Z build-script-cache`, the output of a build script run and the
+//! contents of its `OUT_DIR` are stored in `$CARGO_HOME/build-script-cache`
+//! by default. A run is only cached if the script declared what it depends
+//! on with `rerun-if-changed` or `rerun-if-env-changed`, since otherwise
+//! Cargo can't tell when its output would be different.
+//!
+//! Entries live in two levels of directories:
+//!
+//! * `<name>-<hash>` is specific to the compiled build script. `hash` is the
+//!   metadata hash of the unit compiling the script, which covers the
+//!   package, its build-dependencies and the settings the script is compiled
+//!   with, together with the rustc version. It holds `inputs.json`, the rerun
+//!   inputs the script declared the last time it ran.
+//! * `<name>-<hash>/<key>` is one run of the script. `key` hashes the
+//!   contents of the script's source files, as listed in its dep-info, and
+//!   the current contents of those inputs together with the environment
+//!   describing the host, target and features (`HOST`, `TARGET`,
+//!   `CARGO_CFG_*`, `CARGO_FEATURE_*` and the `DEP_*` metadata of `links`
+//!   dependencies). It holds the script's stdout and stderr, the `OUT_DIR` it
+//!   ran with and a copy of `OUT_DIR`.
+//!
+//! Variables that only depend on the profile, such as `OPT_LEVEL`, `DEBUG`
+//! and `PROFILE`, are not part of the key, which is what allows a run made
+//! for one profile to be reused by another, as long as both compile the
+//! build script the same way (for example with matching `build-override`
+//! settings).
+//!
+//! Entries are stored and evicted like those of the `build_cache`, with
+//! `build-script-cache.max-size` bounding the cache. A package's directory
+//! is evicted as a whole.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::core::compiler::build_cache::BuildCache;
+use crate::core::compiler::fingerprint;
+use crate::core::compiler::{CompileMode, Context, Unit};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{self, paths, Config, ProcessBuilder, Sha256};
+
+/// Environment variables set for build scripts that are part of the key.
+const KEY_ENV: &[&str] = &["HOST", "TARGET"];
+
+/// Prefixes of environment variables set for build scripts that are part of
+/// the key.
+const KEY_ENV_PREFIXES: &[&str] = &["CARGO_CFG_", "CARGO_FEATURE_", "DEP_"];
+
+/// The inputs a build script declared with `rerun-if-changed` and
+/// `rerun-if-env-changed`.
+#[derive(Serialize, Deserialize)]
+struct Inputs {
+    paths: Vec<PathBuf>,
+    env: Vec<String>,
+}
+
+/// The output of a build script run restored from the cache.
+pub struct CachedRun {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// The `OUT_DIR` the script ran with, which its output may refer to.
+    pub out_dir: PathBuf,
+}
+
+pub struct BuildScriptCache {
+    cache: Arc<BuildCache>,
+    /// Name of the directory holding the entries for one package's build
+    /// script.
+    name: PathBuf,
+    /// The package's root, which relative `rerun-if-changed` paths are
+    /// relative to.
+    pkg_root: PathBuf,
+    /// Cargo's dep-info of the compiled build script, listing its source
+    /// files.
+    dep_info: PathBuf,
+    /// The target directory, which some paths in `dep_info` are relative to.
+    target_root: PathBuf,
+}
+
+impl BuildScriptCache {
+    /// Opens the cache of build script runs, or returns `None` if
+    /// `-Z build-script-cache` isn't enabled.
+    pub fn open(config: &Config) -> CargoResult<Option<BuildCache>> {
+        if !config.cli_unstable().build_script_cache {
+            return Ok(None);
+        }
+        BuildCache::open(config, "build-script-cache").map(Some)
+    }
+
+    /// Returns the cache for the build script run by `unit`, or `None` if
+    /// `-Z build-script-cache` isn't enabled or the script can't be cached.
+    pub fn new(cx: &Context<'_, '_>, unit: &Unit) -> CargoResult<Option<BuildScriptCache>> {
+        let cache = match &cx.build_script_cache {
+            Some(cache) => Arc::clone(cache),
+            None => return Ok(None),
+        };
+        let script_unit = cx
+            .unit_deps(unit)
+            .iter()
+            .find(|d| d.unit.mode == CompileMode::Build && d.unit.target.is_custom_build())
+            .map(|d| &d.unit)
+            .expect("running a script not depending on an actual script");
+        let metadata = match cx.files().metadata(script_unit) {
+            Some(metadata) => metadata,
+            None => return Ok(None),
+        };
+        let hash = util::short_hash(&(metadata, &cx.bcx.rustc().verbose_version));
+        Ok(Some(BuildScriptCache {
+            cache,
+            name: PathBuf::from(format!("{}-{}", unit.pkg.name(), hash)),
+            pkg_root: unit.pkg.root().to_path_buf(),
+            dep_info: cx.files().fingerprint_file_path(script_unit, "dep-"),
+            target_root: cx.bcx.ws.target_dir().into_path_unlocked(),
+        }))
+    }
+
+    /// Directory holding the entries for this build script.
+    fn dir(&self) -> PathBuf {
+        self.cache.dir().join(&self.name)
+    }
+
+    /// Copies the cached `OUT_DIR` of a run of `cmd` to `out_dir` and returns
+    /// its output, or returns `None` if there is no such run in the cache.
+    pub fn restore(&self, cmd: &ProcessBuilder, out_dir: &Path) -> CargoResult<Option<CachedRun>> {
+        let inputs = match paths::read_bytes(&self.dir().join("inputs.json")) {
+            Ok(inputs) => serde_json::from_slice::<Inputs>(&inputs)?,
+            Err(_) => return Ok(None),
+        };
+        let entry = self.dir().join(self.key(cmd, &inputs)?);
+        if !entry.join("output").is_file() {
+            return Ok(None);
+        }
+        let run = CachedRun {
+            stdout: paths::read_bytes(&entry.join("output"))?,
+            stderr: paths::read_bytes(&entry.join("stderr"))?,
+            out_dir: util::bytes2path(&paths::read_bytes(&entry.join("root-output"))?)?,
+        };
+        if out_dir.exists() {
+            paths::remove_dir_all(out_dir)?;
+        }
+        copy_dir(&entry.join("out"), out_dir)?;
+        self.cache.touch(&self.name);
+        debug!("restored {} from the build script cache", entry.display());
+        Ok(Some(run))
+    }
+
+    /// Adds a run of `cmd` to the cache. Runs that didn't declare any rerun
+    /// inputs are skipped.
+    pub fn store(
+        &self,
+        cmd: &ProcessBuilder,
+        rerun_if_changed: &[PathBuf],
+        rerun_if_env_changed: &[String],
+        stdout: &[u8],
+        stderr: &[u8],
+        out_dir: &Path,
+    ) -> CargoResult<()> {
+        if rerun_if_changed.is_empty() && rerun_if_env_changed.is_empty() {
+            return Ok(());
+        }
+        let inputs = Inputs {
+            paths: rerun_if_changed.to_vec(),
+            env: rerun_if_env_changed.to_vec(),
+        };
+        let key = self.key(cmd, &inputs)?;
+        self.cache.store_entry(&self.name.join(key), |tmp| {
+            paths::write(tmp.join("output"), stdout)?;
+            paths::write(tmp.join("stderr"), stderr)?;
+            paths::write(tmp.join("root-output"), util::path2bytes(out_dir)?)?;
+            copy_dir(out_dir, &tmp.join("out"))
+        })?;
+        paths::write(self.dir().join("inputs.json"), serde_json::to_vec(&inputs)?)
+    }
+
+    /// Hashes the script's source files, the current state of `inputs` and
+    /// the environment of `cmd`.
+    fn key(&self, cmd: &ProcessBuilder, inputs: &Inputs) -> CargoResult<String> {
+        let mut hasher = Sha256::new();
+        let sources =
+            fingerprint::parse_dep_info(&self.pkg_root, &self.target_root, &self.dep_info)?
+                .ok_or_else(|| {
+                    anyhow::format_err!("failed to read dep-info at `{}`", self.dep_info.display())
+                })?;
+        for path in &sources.files {
+            hash_path(&mut hasher, path)?;
+        }
+        let env = cmd.get_envs().iter().filter(|(name, _)| {
+            KEY_ENV.contains(&name.as_str())
+                || KEY_ENV_PREFIXES
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+        });
+        for (name, value) in env {
+            hasher.update(name.as_bytes());
+            if let Some(value) = value {
+                hasher
+                    .update(b"=")
+                    .update(value.to_string_lossy().as_bytes());
+            }
+            hasher.update(b"\0");
+        }
+        for path in &inputs.paths {
+            hash_path(&mut hasher, &self.pkg_root.join(path))?;
+        }
+        for name in &inputs.env {
+            hasher.update(name.as_bytes());
+            if let Some(value) = std::env::var_os(name) {
+                hasher
+                    .update(b"=")
+                    .update(value.to_string_lossy().as_bytes());
+            }
+            hasher.update(b"\0");
+        }
+        Ok(hasher.finish_hex())
+    }
+}
+
+/// Hashes the contents of the file at `path`, or of every file below it if
+/// it's a directory. A missing path hashes differently from an empty file.
+fn hash_path(hasher: &mut Sha256, path: &Path) -> CargoResult<()> {
+    hasher.update(util::path2bytes(path)?).update(b"\0");
+    if !path.exists() {
+        hasher.update(b"missing\0");
+        return Ok(());
+    }
+    for entry in walkdir::WalkDir::new(path).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+        let entry = entry.chain_err(|| format!("failed to read `{}`", path.display()))?;
+        if entry.file_type().is_file() {
+            let relative = entry.path().strip_prefix(path).unwrap();
+            hasher.update(util::path2bytes(relative)?).update(b"\0");
+            hasher.update_path(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the files below `src` to `dst`, creating `dst` if needed.
+fn copy_dir(src: &Path, dst: &Path) -> CargoResult<()> {
+    paths::create_dir_all(dst)?;
+    for entry in walkdir::WalkDir::new(src).min_depth(1) {
+        let entry = entry.chain_err(|| format!("failed to read `{}`", src.display()))?;
+        let target = dst.join(entry.path().strip_prefix(src).unwrap());
+        if entry.file_type().is_dir() {
+            paths::create_dir_all(&target)?;
+        } else if entry.file_type().is_file() {
+            paths::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
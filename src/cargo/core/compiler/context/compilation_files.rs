@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fmt;
 use std::hash::{Hash, Hasher};
@@ -11,6 +11,7 @@ use log::info;
 use super::{BuildContext, CompileKind, Context, FileFlavor, Layout};
 use crate::core::compiler::{CompileMode, CompileTarget, CrateType, FileType, Unit};
 use crate::core::{Target, TargetKind, Workspace};
+use crate::util::interning::InternedString;
 use crate::util::{self, CargoResult, StableHasher};
 
 /// This is a generic version number that can be changed to make
@@ -130,8 +131,9 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
         target: HashMap<CompileTarget, Layout>,
     ) -> CompilationFiles<'a, 'cfg> {
         let mut metas = HashMap::new();
+        let ambiguous = ambiguous_file_safe_names(cx);
         for unit in &cx.bcx.roots {
-            metadata_of(unit, cx, &mut metas);
+            metadata_of(unit, cx, &mut metas, &ambiguous);
         }
         let outputs = metas
             .keys()
@@ -477,16 +479,39 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
     }
 }
 
+/// Returns the filename-safe package names that are shared by more than one
+/// distinct package name in the unit graph.
+///
+/// A namespaced package like `foo/bar` and an ordinary `foo_bar` both use
+/// `foo_bar` in filenames, so their outputs must always be distinguished by a
+/// metadata hash.
+fn ambiguous_file_safe_names(cx: &Context<'_, '_>) -> HashSet<InternedString> {
+    let mut names: HashMap<InternedString, HashSet<InternedString>> = HashMap::new();
+    for unit in cx.bcx.unit_graph.keys() {
+        let id = unit.pkg.package_id();
+        names
+            .entry(id.file_safe_name())
+            .or_default()
+            .insert(id.name());
+    }
+    names
+        .into_iter()
+        .filter(|(_, pkg_names)| pkg_names.len() > 1)
+        .map(|(file_safe_name, _)| file_safe_name)
+        .collect()
+}
+
 fn metadata_of(
     unit: &Unit,
     cx: &Context<'_, '_>,
     metas: &mut HashMap<Unit, Option<Metadata>>,
+    ambiguous: &HashSet<InternedString>,
 ) -> Option<Metadata> {
     if !metas.contains_key(unit) {
-        let meta = compute_metadata(unit, cx, metas);
+        let meta = compute_metadata(unit, cx, metas, ambiguous);
         metas.insert(unit.clone(), meta);
         for dep in cx.unit_deps(unit) {
-            metadata_of(&dep.unit, cx, metas);
+            metadata_of(&dep.unit, cx, metas, ambiguous);
         }
     }
     metas[unit]
@@ -496,9 +521,12 @@ fn compute_metadata(
     unit: &Unit,
     cx: &Context<'_, '_>,
     metas: &mut HashMap<Unit, Option<Metadata>>,
+    ambiguous: &HashSet<InternedString>,
 ) -> Option<Metadata> {
     let bcx = &cx.bcx;
-    if !should_use_metadata(bcx, unit) {
+    if !should_use_metadata(bcx, unit)
+        && !ambiguous.contains(&unit.pkg.package_id().file_safe_name())
+    {
         return None;
     }
     let mut hasher = StableHasher::new();
@@ -520,7 +548,7 @@ fn compute_metadata(
     let mut deps_metadata = cx
         .unit_deps(unit)
         .iter()
-        .map(|dep| metadata_of(&dep.unit, cx, metas))
+        .map(|dep| metadata_of(&dep.unit, cx, metas, ambiguous))
         .collect::<Vec<_>>();
     deps_metadata.sort();
     deps_metadata.hash(&mut hasher);
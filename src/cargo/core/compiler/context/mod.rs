@@ -10,9 +10,11 @@ use crate::core::PackageId;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::profile;
 
+use super::build_cache::BuildCache;
 use super::build_plan::BuildPlan;
+use super::build_script_cache::BuildScriptCache;
 use super::custom_build::{self, BuildDeps, BuildScriptOutputs, BuildScripts};
-use super::fingerprint::Fingerprint;
+use super::fingerprint::{DirtyReason, Fingerprint};
 use super::job_queue::JobQueue;
 use super::layout::Layout;
 use super::lto::Lto;
@@ -77,6 +79,19 @@ pub struct Context<'a, 'cfg> {
     /// compilation is happening (only object, only bitcode, both, etc), and is
     /// precalculated early on.
     pub lto: HashMap<Unit, Lto>,
+
+    /// The shared cache of compiled units, if `-Z build-cache` is enabled.
+    pub build_cache: Option<Arc<BuildCache>>,
+
+    /// The cache of build script runs, if `-Z build-script-cache` is enabled.
+    pub build_script_cache: Option<Arc<BuildCache>>,
+
+    /// Units that could use the build cache, mapped to whether they were
+    /// restored from it (`true`) or need to be compiled (`false`).
+    pub build_cache_hits: HashMap<Unit, bool>,
+
+    /// Why each dirty unit is rebuilt, with `--explain-rebuild`.
+    pub rebuild_reasons: HashMap<Unit, DirtyReason>,
 }
 
 impl<'a, 'cfg> Context<'a, 'cfg> {
@@ -117,6 +132,10 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             rustc_clients: HashMap::new(),
             pipelining,
             lto: HashMap::new(),
+            build_cache: BuildCache::new(bcx.config)?.map(Arc::new),
+            build_script_cache: BuildScriptCache::open(bcx.config)?.map(Arc::new),
+            build_cache_hits: HashMap::new(),
+            rebuild_reasons: HashMap::new(),
         })
     }
 
@@ -155,6 +174,23 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         // Now that we've figured out everything that we're going to do, do it!
         queue.execute(&mut self, &mut plan)?;
 
+        if let Some(cache) = &self.build_cache {
+            if let Err(e) = cache.evict() {
+                self.bcx
+                    .config
+                    .shell()
+                    .warn(format!("failed to trim the build cache: {}", e))?;
+            }
+        }
+        if let Some(cache) = &self.build_script_cache {
+            if let Err(e) = cache.evict() {
+                self.bcx
+                    .config
+                    .shell()
+                    .warn(format!("failed to trim the build script cache: {}", e))?;
+            }
+        }
+
         if build_plan {
             plan.set_inputs(self.build_plan_inputs()?);
             plan.output_plan(self.bcx.config);
@@ -426,6 +462,24 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                                 path: &PathBuf,
                                 suggestion: &str|
          -> CargoResult<()> {
+            let (pkg_id, other_pkg_id) = (unit.pkg.package_id(), other_unit.pkg.package_id());
+            if pkg_id.name() != other_pkg_id.name()
+                && pkg_id.file_safe_name() == other_pkg_id.file_safe_name()
+            {
+                // Namespaced packages like `foo/bar` share filenames with
+                // `foo_bar`. Their `deps` outputs are kept apart by a metadata
+                // hash, but uplifted files can't be, so this is a hard error.
+                anyhow::bail!(
+                    "output filename collision between packages `{}` and `{}`.\n\
+                     {}\
+                     The package names differ, but both use `{}` in filenames.\n\
+                     Consider renaming one of the targets or compiling them separately.",
+                    pkg_id,
+                    other_pkg_id,
+                    describe_collision(unit, other_unit, path),
+                    pkg_id.file_safe_name(),
+                );
+            }
             if unit.target.name() == other_unit.target.name() {
                 self.bcx.config.shell().warn(format!(
                     "output filename collision.\n\
@@ -1,20 +1,23 @@
+use super::build_script_cache::BuildScriptCache;
 use super::job::{Freshness, Job, Work};
 use super::{fingerprint, Context, LinkType, Unit};
 use crate::core::compiler::context::Metadata;
 use crate::core::compiler::job_queue::JobState;
-use crate::core::{profiles::ProfileRoot, PackageId};
+use crate::core::{profiles::ProfileRoot, PackageId, Target};
+use crate::util::config::BuildScriptSandbox;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::machine_message::{self, Message};
 use crate::util::{self, internal, paths, profile};
 use cargo_platform::Cfg;
+use filetime::FileTime;
+use serde_json::json;
 use std::collections::hash_map::{Entry, HashMap};
 use std::collections::{BTreeSet, HashSet};
+use std::fmt;
 use std::path::{Path, PathBuf};
 use std::str;
 use std::sync::{Arc, Mutex};
 
-const CARGO_WARNING: &str = "cargo:warning=";
-
 /// Contains the parsed output of a custom build script.
 #[derive(Clone, Debug, Hash, Default)]
 pub struct BuildOutput {
@@ -35,8 +38,137 @@ pub struct BuildOutput {
     pub rerun_if_changed: Vec<PathBuf>,
     /// Environment variables which, when changed, will cause a rebuild.
     pub rerun_if_env_changed: Vec<String>,
-    /// Warnings generated by this build.
-    pub warnings: Vec<String>,
+    /// Warnings and errors reported by this build.
+    pub diagnostics: Vec<BuildDiagnostic>,
+}
+
+/// How severe a `BuildDiagnostic` is.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum DiagnosticLevel {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for DiagnosticLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticLevel::Warning => f.write_str("warning"),
+            DiagnosticLevel::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A warning or error reported by a build script with `cargo:warning` or
+/// `cargo:error`.
+///
+/// Both directives may name the file, and optionally the line, the message
+/// is about: `cargo:warning:src/foo.c:12=message`.
+#[derive(Clone, Debug, Hash)]
+pub struct BuildDiagnostic {
+    pub level: DiagnosticLevel,
+    pub message: String,
+    pub file: Option<PathBuf>,
+    pub line: Option<u32>,
+}
+
+impl BuildDiagnostic {
+    /// A warning about a build script that isn't tied to a file.
+    pub fn warning(message: impl Into<String>) -> BuildDiagnostic {
+        BuildDiagnostic {
+            level: DiagnosticLevel::Warning,
+            message: message.into(),
+            file: None,
+            line: None,
+        }
+    }
+
+    /// Parses a line of a build script's output, returning `None` if it
+    /// isn't a warning or an error.
+    fn parse_line(line: &str) -> Option<BuildDiagnostic> {
+        let mut iter = line.strip_prefix("cargo:")?.splitn(2, '=');
+        let key = iter.next()?;
+        let value = iter.next()?;
+        BuildDiagnostic::parse(key, value.trim_end())
+    }
+
+    /// Parses the `key` and `value` of a `cargo:key=value` line, returning
+    /// `None` if it isn't a warning or an error.
+    fn parse(key: &str, value: &str) -> Option<BuildDiagnostic> {
+        let (level, location) = if let Some(location) = key.strip_prefix("warning") {
+            (DiagnosticLevel::Warning, location)
+        } else if let Some(location) = key.strip_prefix("error") {
+            (DiagnosticLevel::Error, location)
+        } else {
+            return None;
+        };
+        let (file, line) = match location {
+            "" => (None, None),
+            location => {
+                let location = location.strip_prefix(':')?;
+                // Only split off a line number, so that paths such as
+                // `C:\foo.c` are kept whole.
+                let line = location
+                    .rfind(':')
+                    .and_then(|i| Some((&location[..i], location[i + 1..].parse().ok()?)));
+                match line {
+                    Some((file, line)) => (Some(PathBuf::from(file)), Some(line)),
+                    None => (Some(PathBuf::from(location)), None),
+                }
+            }
+        };
+        Some(BuildDiagnostic {
+            level,
+            message: value.to_string(),
+            file,
+            line,
+        })
+    }
+
+    /// Renders the diagnostic like a rustc diagnostic, for
+    /// `--message-format=json`.
+    fn to_json(&self) -> serde_json::Value {
+        let spans = match &self.file {
+            Some(file) => {
+                // Without a line the span covers the start of the file.
+                let line = self.line.unwrap_or(1);
+                vec![json!({
+                    "file_name": file,
+                    "byte_start": 0,
+                    "byte_end": 0,
+                    "line_start": line,
+                    "line_end": line,
+                    "column_start": 1,
+                    "column_end": 1,
+                    "is_primary": true,
+                    "text": [],
+                    "label": null,
+                    "suggested_replacement": null,
+                    "suggestion_applicability": null,
+                    "expansion": null,
+                })]
+            }
+            None => Vec::new(),
+        };
+        json!({
+            "message": self.message,
+            "code": null,
+            "level": self.level.to_string(),
+            "spans": spans,
+            "children": [],
+            "rendered": format!("{}: {}\n", self.level, self),
+        })
+    }
+}
+
+impl fmt::Display for BuildDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "\n --> {}:{}", file.display(), line),
+            (Some(file), None) => write!(f, "\n --> {}", file.display()),
+            (None, _) => Ok(()),
+        }
+    }
 }
 
 /// Map of packages to build script output.
@@ -128,7 +260,17 @@ fn emit_build_output(
     output: &BuildOutput,
     out_dir: &Path,
     package_id: PackageId,
+    target: &Target,
+    show_warnings: bool,
 ) -> CargoResult<()> {
+    emit_diagnostics(
+        state,
+        &output.diagnostics,
+        package_id,
+        target,
+        show_warnings,
+    )?;
+
     let library_paths = output
         .library_paths
         .iter()
@@ -148,6 +290,30 @@ fn emit_build_output(
     Ok(())
 }
 
+/// Emits a build script's warnings and errors as `compiler-message`s.
+fn emit_diagnostics(
+    state: &JobState<'_>,
+    diagnostics: &[BuildDiagnostic],
+    package_id: PackageId,
+    target: &Target,
+    show_warnings: bool,
+) -> CargoResult<()> {
+    for diagnostic in diagnostics {
+        if diagnostic.level == DiagnosticLevel::Warning && !show_warnings {
+            continue;
+        }
+        let message = serde_json::value::to_raw_value(&diagnostic.to_json())?;
+        let msg = machine_message::FromCompiler {
+            package_id,
+            target,
+            message,
+        }
+        .to_json_string();
+        state.stdout(msg)?;
+    }
+    Ok(())
+}
+
 fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
     assert!(unit.mode.is_run_custom_build());
     let bcx = &cx.bcx;
@@ -280,9 +446,12 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
         Arc::clone(&build_script_outputs),
         output_file.clone(),
         script_out_dir.clone(),
+        unit.target.clone(),
     );
+    let target = unit.target.clone();
     let build_scripts = cx.build_scripts.get(unit).cloned();
     let json_messages = bcx.build_config.emit_json();
+    let show_warnings = unit.show_warnings(bcx.config);
     let extra_verbose = bcx.config.extra_verbose();
     let (prev_output, prev_script_out_dir) = prev_build_output(cx, unit);
     let metadata_hash = cx.get_run_build_script_metadata(unit);
@@ -292,6 +461,26 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
 
     let extra_link_arg = cx.bcx.config.cli_unstable().extra_link_arg;
 
+    // With `-Z build-script-sandbox` the package's directory is snapshotted
+    // around the run so changes outside of `OUT_DIR` can be reported.
+    let sandbox = if cx.bcx.config.cli_unstable().build_script_sandbox {
+        let mode = cx
+            .bcx
+            .config
+            .build_config()?
+            .build_script_sandbox
+            .unwrap_or(BuildScriptSandbox::Warn);
+        let excluded = vec![
+            cx.bcx.ws.target_dir().into_path_unlocked(),
+            script_out_dir.clone(),
+        ];
+        Some((mode, unit.pkg.root().to_path_buf(), excluded))
+    } else {
+        None
+    };
+
+    let script_cache = BuildScriptCache::new(cx, unit)?;
+
     // Prepare the unit of "dirty work" which will actually run the custom build
     // command.
     //
@@ -344,43 +533,75 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
             return Ok(());
         }
 
-        // And now finally, run the build command itself!
-        state.running(&cmd);
+        // With `-Z build-script-cache`, a previous run with the same inputs
+        // is restored instead of running the script again.
+        let restored = match &script_cache {
+            Some(cache) => cache.restore(&cmd, &script_out_dir).unwrap_or_else(|e| {
+                log::debug!("failed to restore build script output of {}: {:?}", id, e);
+                None
+            }),
+            None => None,
+        };
         let timestamp = paths::set_invocation_time(&script_run_dir)?;
-        let prefix = format!("[{} {}] ", id.name(), id.version());
-        let mut warnings_in_case_of_panic = Vec::new();
-        let output = cmd
-            .exec_with_streaming(
-                &mut |stdout| {
-                    if let Some(warning) = stdout.strip_prefix(CARGO_WARNING) {
-                        warnings_in_case_of_panic.push(warning.to_owned());
-                    }
-                    if extra_verbose {
-                        state.stdout(format!("{}{}", prefix, stdout))?;
-                    }
-                    Ok(())
-                },
-                &mut |stderr| {
-                    if extra_verbose {
-                        state.stderr(format!("{}{}", prefix, stderr))?;
-                    }
-                    Ok(())
-                },
-                true,
-            )
-            .chain_err(|| format!("failed to run custom build command for `{}`", pkg_name));
-
-        if let Err(error) = output {
-            insert_warnings_in_build_outputs(
-                build_script_outputs,
-                id,
-                metadata_hash,
-                warnings_in_case_of_panic,
-            );
-            return Err(error);
-        }
+        let mut snapshot = None;
+        let from_cache = restored.is_some();
+        let (stdout, stderr, out_dir_when_generated) = if let Some(run) = restored {
+            state.status(
+                "Restored",
+                format!("build script output of {} from the cache", pkg_name),
+            )?;
+            (run.stdout, run.stderr, run.out_dir)
+        } else {
+            // And now finally, run the build command itself!
+            snapshot = sandbox
+                .as_ref()
+                .map(|(_, root, excluded)| SourceSnapshot::take(root, excluded));
+            state.running(&cmd);
+            let prefix = format!("[{} {}] ", id.name(), id.version());
+            let mut diagnostics_in_case_of_panic = Vec::new();
+            let output = cmd
+                .exec_with_streaming(
+                    &mut |stdout| {
+                        if let Some(diagnostic) = BuildDiagnostic::parse_line(stdout) {
+                            diagnostics_in_case_of_panic.push(diagnostic);
+                        }
+                        if extra_verbose {
+                            state.stdout(format!("{}{}", prefix, stdout))?;
+                        }
+                        Ok(())
+                    },
+                    &mut |stderr| {
+                        if extra_verbose {
+                            state.stderr(format!("{}{}", prefix, stderr))?;
+                        }
+                        Ok(())
+                    },
+                    true,
+                )
+                .chain_err(|| format!("failed to run custom build command for `{}`", pkg_name));
+
+            if let Err(error) = output {
+                if json_messages {
+                    emit_diagnostics(
+                        state,
+                        &diagnostics_in_case_of_panic,
+                        id,
+                        &target,
+                        show_warnings,
+                    )?;
+                }
+                insert_warnings_in_build_outputs(
+                    build_script_outputs,
+                    id,
+                    metadata_hash,
+                    diagnostics_in_case_of_panic,
+                );
+                return Err(error);
+            }
 
-        let output = output.unwrap();
+            let output = output.unwrap();
+            (output.stdout, output.stderr, script_out_dir.clone())
+        };
 
         // After the build command has finished running, we need to be sure to
         // remember all of its output so we can later discover precisely what it
@@ -389,22 +610,112 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
         // This is also the location where we provide feedback into the build
         // state informing what variables were discovered via our script as
         // well.
-        paths::write(&output_file, &output.stdout)?;
+        paths::write(&output_file, &stdout)?;
         // This mtime shift allows Cargo to detect if a source file was
         // modified in the middle of the build.
         paths::set_file_time_no_err(output_file, timestamp);
-        paths::write(&err_file, &output.stderr)?;
-        paths::write(&root_output_file, util::path2bytes(&script_out_dir)?)?;
-        let parsed_output = BuildOutput::parse(
-            &output.stdout,
+        paths::write(&err_file, &stderr)?;
+        paths::write(
+            &root_output_file,
+            util::path2bytes(&out_dir_when_generated)?,
+        )?;
+        let mut parsed_output = BuildOutput::parse(
+            &stdout,
             &pkg_name,
-            &script_out_dir,
+            &out_dir_when_generated,
             &script_out_dir,
             extra_link_arg,
         )?;
 
+        if let (Some((mode, root, excluded)), Some(before)) = (&sandbox, snapshot) {
+            let after = SourceSnapshot::take(root, excluded);
+            let changes = before.changes(&after);
+            if !changes.is_empty() {
+                let describe = |(kind, path): &(&str, PathBuf)| {
+                    let path = path.strip_prefix(root).unwrap_or(path);
+                    format!("{} `{}`", kind, path.display())
+                };
+                match mode {
+                    BuildScriptSandbox::Warn => {
+                        parsed_output
+                            .diagnostics
+                            .extend(changes.iter().map(|change| {
+                                BuildDiagnostic::warning(format!(
+                                    "build script {} outside of OUT_DIR",
+                                    describe(change)
+                                ))
+                            }))
+                    }
+                    BuildScriptSandbox::Deny => {
+                        let list = changes
+                            .iter()
+                            .map(|change| format!("\n  {}", describe(change)))
+                            .collect::<String>();
+                        anyhow::bail!(
+                            "build script of `{}` changed files outside of OUT_DIR:{}",
+                            pkg_name,
+                            list
+                        );
+                    }
+                }
+            }
+        }
+
+        // A script that reported an error with `cargo:error` fails the
+        // unit, but its diagnostics are still shown like the warnings of a
+        // script that panicked.
+        if parsed_output.has_errors() {
+            if json_messages {
+                emit_diagnostics(
+                    state,
+                    &parsed_output.diagnostics,
+                    id,
+                    &target,
+                    show_warnings,
+                )?;
+            }
+            let errors = parsed_output
+                .diagnostics
+                .iter()
+                .filter(|d| d.level == DiagnosticLevel::Error)
+                .count();
+            build_script_outputs
+                .lock()
+                .unwrap()
+                .insert(id, metadata_hash, parsed_output);
+            if errors == 1 {
+                anyhow::bail!("build script of `{}` reported an error", pkg_name);
+            }
+            anyhow::bail!("build script of `{}` reported {} errors", pkg_name, errors);
+        }
+
+        if let (Some(cache), false) = (&script_cache, from_cache) {
+            let stored = cache.store(
+                &cmd,
+                &parsed_output.rerun_if_changed,
+                &parsed_output.rerun_if_env_changed,
+                &stdout,
+                &stderr,
+                &script_out_dir,
+            );
+            if let Err(e) = stored {
+                log::warn!(
+                    "failed to add build script output of {} to the cache: {:?}",
+                    id,
+                    e
+                );
+            }
+        }
+
         if json_messages {
-            emit_build_output(state, &parsed_output, script_out_dir.as_path(), id)?;
+            emit_build_output(
+                state,
+                &parsed_output,
+                script_out_dir.as_path(),
+                id,
+                &target,
+                show_warnings,
+            )?;
         }
         build_script_outputs
             .lock()
@@ -417,7 +728,7 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
     // itself to run when we actually end up just discarding what we calculated
     // above.
     let fresh = Work::new(move |state| {
-        let (id, pkg_name, build_script_outputs, output_file, script_out_dir) = all;
+        let (id, pkg_name, build_script_outputs, output_file, script_out_dir, target) = all;
         let output = match prev_output {
             Some(output) => output,
             None => BuildOutput::parse_file(
@@ -430,7 +741,14 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
         };
 
         if json_messages {
-            emit_build_output(state, &output, script_out_dir.as_path(), id)?;
+            emit_build_output(
+                state,
+                &output,
+                script_out_dir.as_path(),
+                id,
+                &target,
+                show_warnings,
+            )?;
         }
 
         build_script_outputs
@@ -457,10 +775,10 @@ fn insert_warnings_in_build_outputs(
     build_script_outputs: Arc<Mutex<BuildScriptOutputs>>,
     id: PackageId,
     metadata_hash: Metadata,
-    warnings: Vec<String>,
+    diagnostics: Vec<BuildDiagnostic>,
 ) {
     let build_output_with_only_warnings = BuildOutput {
-        warnings,
+        diagnostics,
         ..BuildOutput::default()
     };
     build_script_outputs
@@ -469,7 +787,76 @@ fn insert_warnings_in_build_outputs(
         .insert(id, metadata_hash, build_output_with_only_warnings);
 }
 
+/// The files found in a package's directory, used by
+/// `-Z build-script-sandbox` to find what a build script changed.
+struct SourceSnapshot {
+    files: HashMap<PathBuf, (FileTime, u64)>,
+}
+
+impl SourceSnapshot {
+    /// Records the mtime and size of every file under `root`, skipping the
+    /// `excluded` directories, VCS directories and nested packages.
+    ///
+    /// Nested packages, such as the members of a workspace root, have build
+    /// scripts of their own that may run at the same time as this one.
+    /// Entries that can't be read are skipped, as other jobs may be removing
+    /// files while this runs.
+    fn take(root: &Path, excluded: &[PathBuf]) -> SourceSnapshot {
+        let files = walkdir::WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|entry| {
+                if excluded.iter().any(|dir| entry.path() == dir) {
+                    return false;
+                }
+                if entry.depth() == 0 || !entry.file_type().is_dir() {
+                    return true;
+                }
+                let is_vcs = match entry.file_name().to_str() {
+                    Some(name) => [".git", ".hg", ".pijul", ".svn"].contains(&name),
+                    None => false,
+                };
+                !is_vcs && !entry.path().join("Cargo.toml").exists()
+            })
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| {
+                let meta = entry.metadata().ok()?;
+                let stamp = (FileTime::from_last_modification_time(&meta), meta.len());
+                Some((entry.into_path(), stamp))
+            })
+            .collect();
+        SourceSnapshot { files }
+    }
+
+    /// Returns the files that were added, modified or removed between this
+    /// snapshot and `after`, sorted by path.
+    fn changes(&self, after: &SourceSnapshot) -> Vec<(&'static str, PathBuf)> {
+        let mut changes = Vec::new();
+        for (path, stamp) in &after.files {
+            match self.files.get(path) {
+                None => changes.push(("added", path.clone())),
+                Some(old) if old != stamp => changes.push(("modified", path.clone())),
+                Some(_) => {}
+            }
+        }
+        for path in self.files.keys() {
+            if !after.files.contains_key(path) {
+                changes.push(("removed", path.clone()));
+            }
+        }
+        changes.sort_by(|a, b| a.1.cmp(&b.1));
+        changes
+    }
+}
+
 impl BuildOutput {
+    /// Returns whether the build script reported an error with `cargo:error`.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.level == DiagnosticLevel::Error)
+    }
+
     pub fn parse_file(
         path: &Path,
         pkg_name: &str,
@@ -504,7 +891,7 @@ impl BuildOutput {
         let mut metadata = Vec::new();
         let mut rerun_if_changed = Vec::new();
         let mut rerun_if_env_changed = Vec::new();
-        let mut warnings = Vec::new();
+        let mut diagnostics = Vec::new();
         let whence = format!("build script of `{}`", pkg_name);
 
         for line in input.split(|b| *b == b'\n') {
@@ -538,6 +925,11 @@ impl BuildOutput {
                 script_out_dir.to_str().unwrap(),
             );
 
+            if let Some(diagnostic) = BuildDiagnostic::parse(key, &value) {
+                diagnostics.push(diagnostic);
+                continue;
+            }
+
             // Keep in sync with TargetConfig::new.
             match key {
                 "rustc-flags" => {
@@ -554,19 +946,24 @@ impl BuildOutput {
                     if extra_link_arg {
                         linker_args.push((Some(LinkType::Bin), value));
                     } else {
-                        warnings.push(format!("cargo:{} requires -Zextra-link-arg flag", key));
+                        diagnostics.push(BuildDiagnostic::warning(format!(
+                            "cargo:{} requires -Zextra-link-arg flag",
+                            key
+                        )));
                     }
                 }
                 "rustc-link-arg" => {
                     if extra_link_arg {
                         linker_args.push((None, value));
                     } else {
-                        warnings.push(format!("cargo:{} requires -Zextra-link-arg flag", key));
+                        diagnostics.push(BuildDiagnostic::warning(format!(
+                            "cargo:{} requires -Zextra-link-arg flag",
+                            key
+                        )));
                     }
                 }
                 "rustc-cfg" => cfgs.push(value.to_string()),
                 "rustc-env" => env.push(BuildOutput::parse_rustc_env(&value, &whence)?),
-                "warning" => warnings.push(value.to_string()),
                 "rerun-if-changed" => rerun_if_changed.push(PathBuf::from(value)),
                 "rerun-if-env-changed" => rerun_if_env_changed.push(value.to_string()),
                 _ => metadata.push((key.to_string(), value.to_string())),
@@ -582,7 +979,7 @@ impl BuildOutput {
             metadata,
             rerun_if_changed,
             rerun_if_env_changed,
-            warnings,
+            diagnostics,
         })
     }
 
@@ -207,6 +207,18 @@
 //! build, so it takes a conservative approach of assuming the file was *not*
 //! included, and it should be rebuilt during the next build.
 //!
+//! #### Checksum freshness
+//!
+//! With `-Z checksum-freshness`, `translate_dep_info` also records the size
+//! and SHA-256 of every file in the dep-info. A file whose mtime is newer
+//! than the dep-info file is then hashed again, and the unit only becomes
+//! stale if the content differs (`StaleItem::ChangedContent`). Files that
+//! were only touched, for example by a `git checkout` or by restoring a
+//! cache, are listed in `Fingerprint.touched` instead, and both cases are
+//! shown in `-v` output. A file modified after the build started is recorded
+//! without a checksum so it is still rebuilt by its mtime. The mtime
+//! comparison of outputs against dependency outputs is unchanged.
+//!
 //! #### Rustdoc mtime handling
 //!
 //! Rustdoc does not emit a dep-info file, so Cargo currently has a relatively
@@ -315,13 +327,15 @@
 use std::collections::hash_map::{Entry, HashMap};
 use std::convert::TryInto;
 use std::env;
+use std::fmt;
+use std::fs;
 use std::hash::{self, Hasher};
 use std::path::{Path, PathBuf};
 use std::str;
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
-use anyhow::{bail, format_err};
+use anyhow::bail;
 use filetime::FileTime;
 use log::{debug, info};
 use serde::de;
@@ -333,11 +347,14 @@ use crate::core::Package;
 use crate::util;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::interning::InternedString;
+use crate::util::machine_message::{self, Message};
 use crate::util::paths;
-use crate::util::{internal, profile, ProcessBuilder};
+use crate::util::{internal, profile, Config, ProcessBuilder, Sha256};
 
+use super::build_cache::{self, BuildCache};
 use super::custom_build::BuildDeps;
 use super::job::{Job, Work};
+use super::timings;
 use super::{BuildContext, Context, FileFlavor, Unit};
 
 /// Determines if a `unit` is up-to-date, and if not prepares necessary work to
@@ -372,6 +389,9 @@ pub fn prepare_target(cx: &mut Context<'_, '_>, unit: &Unit, force: bool) -> Car
     let mtime_on_use = cx.bcx.config.cli_unstable().mtime_on_use;
     let compare = compare_old_fingerprint(&loc, &*fingerprint, mtime_on_use);
     log_compare(unit, &compare);
+    if cx.bcx.config.cli_unstable().checksum_freshness {
+        report_fs_status(cx.bcx.config, unit, &fingerprint, compare.is_ok())?;
+    }
 
     // If our comparison failed (e.g., we're going to trigger a rebuild of this
     // crate), then we also ensure the source of the crate passes all
@@ -395,6 +415,41 @@ pub fn prepare_target(cx: &mut Context<'_, '_>, unit: &Unit, force: bool) -> Car
     if compare.is_ok() && !force {
         return Ok(Job::new_fresh());
     }
+    let dirty_reason = if bcx.build_config.explain_rebuild {
+        Some(dirty_reason(unit, &loc, &fingerprint, &compare))
+    } else {
+        None
+    };
+
+    // A unit of a non-path package may be in the build cache, in which case
+    // its outputs are restored instead of compiling it. Otherwise they are
+    // added to the cache once it's compiled.
+    let cache = match (&cx.build_cache, force) {
+        (Some(cache), false) => match BuildCache::key(cx, unit, &fingerprint.rustflags) {
+            Some(key) => Some((
+                Arc::clone(cache),
+                key,
+                build_cache::cached_outputs(cx, unit)?,
+                dep_info_loc(cx, unit),
+            )),
+            None => None,
+        },
+        _ => None,
+    };
+    if let Some((cache, key, outputs, dep_info)) = &cache {
+        let hit = cache.restore(key, outputs, dep_info).unwrap_or_else(|e| {
+            debug!("failed to restore {} from the build cache: {:?}", key, e);
+            false
+        });
+        cx.build_cache_hits.insert(unit.clone(), hit);
+        if hit {
+            write_fingerprint(&loc, &fingerprint)?;
+            bcx.config
+                .shell()
+                .status("Restored", format!("{} from the build cache", unit.pkg))?;
+            return Ok(Job::new_fresh());
+        }
+    }
 
     // Clear out the old fingerprint file if it exists. This protects when
     // compilation is interrupted leaving a corrupt file. For example, a
@@ -463,9 +518,20 @@ pub fn prepare_target(cx: &mut Context<'_, '_>, unit: &Unit, force: bool) -> Car
             write_fingerprint(&loc, &fingerprint)
         })
     } else {
-        Work::new(move |_| write_fingerprint(&loc, &fingerprint))
+        Work::new(move |_| {
+            write_fingerprint(&loc, &fingerprint)?;
+            if let Some((cache, key, outputs, dep_info)) = cache {
+                if let Err(e) = cache.store(&key, &outputs, &dep_info) {
+                    log::warn!("failed to add {} to the build cache: {:?}", key, e);
+                }
+            }
+            Ok(())
+        })
     };
 
+    if let Some(reason) = dirty_reason {
+        cx.rebuild_reasons.insert(unit.clone(), reason);
+    }
     Ok(Job::new_dirty(write_fingerprint))
 }
 
@@ -553,6 +619,13 @@ pub struct Fingerprint {
     /// fingerprints output files are regenerated and look newer than this one.
     #[serde(skip)]
     outputs: Vec<PathBuf>,
+    /// The reason `check_filesystem` left this unit stale, if it found one.
+    #[serde(skip)]
+    stale_item: Option<StaleItem>,
+    /// Input files that are newer than the last build but whose content is
+    /// unchanged, found with `-Z checksum-freshness`.
+    #[serde(skip)]
+    touched: Vec<PathBuf>,
 }
 
 /// Indication of the status on the filesystem for a particular unit.
@@ -686,13 +759,81 @@ enum StaleItem {
         stale: PathBuf,
         stale_mtime: FileTime,
     },
+    /// A file is newer than `reference` and its size or SHA-256 no longer
+    /// matches the one recorded with `-Z checksum-freshness`.
+    ChangedContent {
+        reference: PathBuf,
+        stale: PathBuf,
+    },
     ChangedEnv {
         var: String,
         previous: Option<String>,
         current: Option<String>,
     },
+    /// The dependency `name` is stale, so this unit is as well.
+    StaleDependency {
+        name: InternedString,
+    },
+    /// The outputs of dependency `name` are newer than this unit's, so it was
+    /// rebuilt after this unit was.
+    NewerDependency {
+        name: InternedString,
+        dep_mtime: FileTime,
+        max_mtime: FileTime,
+    },
+}
+
+/// The first difference found between a unit's `Fingerprint` and the one
+/// from its previous build, which is why the unit is rebuilt.
+///
+/// This is logged for every dirty unit and collected for `--explain-rebuild`.
+#[derive(Clone, Debug)]
+pub struct DirtyReason {
+    /// The part of the fingerprint that changed, such as `features`, `deps`
+    /// or `fs` for the files and dependencies checked on the filesystem.
+    pub field: &'static str,
+    /// What changed, such as "features have changed".
+    pub message: String,
+    /// The previous value of `field`, if it's worth showing.
+    pub old: Option<String>,
+    /// The current value of `field`, if it's worth showing.
+    pub new: Option<String>,
+    /// The crate name of the dependency that made this unit dirty.
+    pub dependency: Option<InternedString>,
 }
 
+impl DirtyReason {
+    fn new(field: &'static str, message: impl Into<String>) -> DirtyReason {
+        DirtyReason {
+            field,
+            message: message.into(),
+            old: None,
+            new: None,
+            dependency: None,
+        }
+    }
+
+    fn values(mut self, old: impl fmt::Display, new: impl fmt::Display) -> DirtyReason {
+        self.old = Some(old.to_string());
+        self.new = Some(new.to_string());
+        self
+    }
+}
+
+impl fmt::Display for DirtyReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        // The hashes of a dependency's fingerprint aren't of any help to
+        // someone reading this, its name in the message is.
+        if let (Some(old), Some(new), None) = (&self.old, &self.new, self.dependency) {
+            write!(f, ": previously {}, now {}", old, new)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DirtyReason {}
+
 impl LocalFingerprint {
     /// Checks dynamically at runtime if this `LocalFingerprint` has a stale
     /// item inside of it.
@@ -705,11 +846,17 @@ impl LocalFingerprint {
     ///
     /// * Another is the `rerun-if-changed` directive from build scripts. This
     ///   is where we'll find whether files have actually changed
+    ///
+    /// If `checksum_freshness` is set, files with a checksum in the dep-info
+    /// are only stale if their content changed. Those that were only touched
+    /// are pushed onto `touched`.
     fn find_stale_item(
         &self,
         mtime_cache: &mut HashMap<PathBuf, FileTime>,
         pkg_root: &Path,
         target_root: &Path,
+        checksum_freshness: bool,
+        touched: &mut Vec<PathBuf>,
     ) -> CargoResult<Option<StaleItem>> {
         match self {
             // We need to parse `dep_info`, learn about the crate's dependencies.
@@ -720,10 +867,13 @@ impl LocalFingerprint {
             // rustc.
             LocalFingerprint::CheckDepInfo { dep_info } => {
                 let dep_info = target_root.join(dep_info);
-                let info = match parse_dep_info(pkg_root, target_root, &dep_info)? {
+                let mut info = match parse_dep_info(pkg_root, target_root, &dep_info)? {
                     Some(info) => info,
                     None => return Ok(Some(StaleItem::MissingFile(dep_info))),
                 };
+                if !checksum_freshness {
+                    info.checksums.clear();
+                }
                 for (key, previous) in info.env.iter() {
                     let current = env::var(key).ok();
                     if current == *previous {
@@ -735,7 +885,13 @@ impl LocalFingerprint {
                         current,
                     }));
                 }
-                Ok(find_stale_file(mtime_cache, &dep_info, info.files.iter()))
+                Ok(find_stale_file(
+                    mtime_cache,
+                    &dep_info,
+                    info.files.iter(),
+                    &info.checksums,
+                    touched,
+                ))
             }
 
             // We need to verify that no paths listed in `paths` are newer than
@@ -744,6 +900,8 @@ impl LocalFingerprint {
                 mtime_cache,
                 &target_root.join(output),
                 paths.iter().map(|p| pkg_root.join(p)),
+                &HashMap::new(),
+                touched,
             )),
 
             // These have no dependencies on the filesystem, and their values
@@ -783,6 +941,8 @@ impl Fingerprint {
             config: 0,
             fs_status: FsStatus::Stale,
             outputs: Vec::new(),
+            stale_item: None,
+            touched: Vec::new(),
         }
     }
 
@@ -809,55 +969,67 @@ impl Fingerprint {
     /// serialized to filesystem.
     ///
     /// The purpose of this is exclusively to produce a diagnostic message
-    /// indicating why we're recompiling something. This function always returns
-    /// an error, it will never return success.
-    fn compare(&self, old: &Fingerprint) -> CargoResult<()> {
+    /// indicating why we're recompiling something, which is logged and shown
+    /// by `--explain-rebuild`. This function always returns an error, it will
+    /// never return success.
+    fn compare(&self, old: &Fingerprint) -> Result<(), DirtyReason> {
         if self.rustc != old.rustc {
-            bail!("rust compiler has changed")
+            return Err(DirtyReason::new("rustc", "rust compiler has changed")
+                .values(util::to_hex(old.rustc), util::to_hex(self.rustc)));
         }
         if self.features != old.features {
-            bail!(
-                "features have changed: previously {}, now {}",
-                old.features,
-                self.features
-            )
+            return Err(DirtyReason::new("features", "features have changed")
+                .values(&old.features, &self.features));
         }
         if self.target != old.target {
-            bail!("target configuration has changed")
+            return Err(
+                DirtyReason::new("target", "target configuration has changed")
+                    .values(util::to_hex(old.target), util::to_hex(self.target)),
+            );
         }
         if self.path != old.path {
-            bail!("path to the source has changed")
+            return Err(DirtyReason::new("path", "path to the source has changed")
+                .values(util::to_hex(old.path), util::to_hex(self.path)));
         }
         if self.profile != old.profile {
-            bail!("profile configuration has changed")
+            return Err(
+                DirtyReason::new("profile", "profile configuration has changed")
+                    .values(util::to_hex(old.profile), util::to_hex(self.profile)),
+            );
         }
         if self.rustflags != old.rustflags {
-            bail!(
-                "RUSTFLAGS has changed: previously {:?}, now {:?}",
-                old.rustflags,
-                self.rustflags
-            )
+            return Err(
+                DirtyReason::new("rustflags", "RUSTFLAGS has changed").values(
+                    format!("{:?}", old.rustflags),
+                    format!("{:?}", self.rustflags),
+                ),
+            );
         }
         if self.metadata != old.metadata {
-            bail!("metadata changed")
+            return Err(DirtyReason::new("metadata", "metadata changed")
+                .values(util::to_hex(old.metadata), util::to_hex(self.metadata)));
         }
         if self.config != old.config {
-            bail!("configuration settings have changed")
+            return Err(
+                DirtyReason::new("config", "configuration settings have changed")
+                    .values(util::to_hex(old.config), util::to_hex(self.config)),
+            );
         }
         let my_local = self.local.lock().unwrap();
         let old_local = old.local.lock().unwrap();
         if my_local.len() != old_local.len() {
-            bail!("local lens changed");
+            return Err(DirtyReason::new("local", "local lens changed")
+                .values(old_local.len(), my_local.len()));
         }
         for (new, old) in my_local.iter().zip(old_local.iter()) {
             match (new, old) {
                 (LocalFingerprint::Precalculated(a), LocalFingerprint::Precalculated(b)) => {
                     if a != b {
-                        bail!(
-                            "precalculated components have changed: previously {}, now {}",
-                            b,
-                            a
+                        return Err(DirtyReason::new(
+                            "local",
+                            "precalculated components have changed",
                         )
+                        .values(b, a));
                     }
                 }
                 (
@@ -865,11 +1037,8 @@ impl Fingerprint {
                     LocalFingerprint::CheckDepInfo { dep_info: bdep },
                 ) => {
                     if adep != bdep {
-                        bail!(
-                            "dep info output changed: previously {:?}, now {:?}",
-                            bdep,
-                            adep
-                        )
+                        return Err(DirtyReason::new("local", "dep info output changed")
+                            .values(bdep.display(), adep.display()));
                     }
                 }
                 (
@@ -883,18 +1052,12 @@ impl Fingerprint {
                     },
                 ) => {
                     if aout != bout {
-                        bail!(
-                            "rerun-if-changed output changed: previously {:?}, now {:?}",
-                            bout,
-                            aout
-                        )
+                        return Err(DirtyReason::new("local", "rerun-if-changed output changed")
+                            .values(bout.display(), aout.display()));
                     }
                     if apaths != bpaths {
-                        bail!(
-                            "rerun-if-changed output changed: previously {:?}, now {:?}",
-                            bpaths,
-                            apaths,
-                        )
+                        return Err(DirtyReason::new("local", "rerun-if-changed paths changed")
+                            .values(format!("{:?}", bpaths), format!("{:?}", apaths)));
                     }
                 }
                 (
@@ -908,57 +1071,67 @@ impl Fingerprint {
                     },
                 ) => {
                     if *akey != *bkey {
-                        bail!("env vars changed: previously {}, now {}", bkey, akey);
+                        return Err(
+                            DirtyReason::new("local", "env vars changed").values(bkey, akey)
+                        );
                     }
                     if *avalue != *bvalue {
-                        bail!(
-                            "env var `{}` changed: previously {:?}, now {:?}",
-                            akey,
-                            bvalue,
-                            avalue
+                        return Err(DirtyReason::new(
+                            "local",
+                            format!("env var `{}` changed", akey),
                         )
+                        .values(format!("{:?}", bvalue), format!("{:?}", avalue)));
                     }
                 }
-                (a, b) => bail!(
-                    "local fingerprint type has changed ({} => {})",
-                    b.kind(),
-                    a.kind()
-                ),
+                (a, b) => {
+                    return Err(
+                        DirtyReason::new("local", "local fingerprint type has changed")
+                            .values(b.kind(), a.kind()),
+                    )
+                }
             }
         }
 
         if self.deps.len() != old.deps.len() {
-            bail!("number of dependencies has changed")
+            return Err(
+                DirtyReason::new("deps", "number of dependencies has changed")
+                    .values(old.deps.len(), self.deps.len()),
+            );
         }
         for (a, b) in self.deps.iter().zip(old.deps.iter()) {
             if a.name != b.name {
-                let e = format_err!("`{}` != `{}`", a.name, b.name)
-                    .context("unit dependency name changed");
-                return Err(e);
+                return Err(
+                    DirtyReason::new("deps", "unit dependency name changed").values(b.name, a.name)
+                );
             }
 
             if a.fingerprint.hash() != b.fingerprint.hash() {
-                let e = format_err!(
-                    "new ({}/{:x}) != old ({}/{:x})",
-                    a.name,
-                    a.fingerprint.hash(),
-                    b.name,
-                    b.fingerprint.hash()
-                )
-                .context("unit dependency information changed");
-                return Err(e);
+                let mut reason =
+                    DirtyReason::new("deps", format!("dependency `{}` has changed", a.name))
+                        .values(
+                            util::to_hex(b.fingerprint.hash()),
+                            util::to_hex(a.fingerprint.hash()),
+                        );
+                reason.dependency = Some(a.name);
+                return Err(reason);
             }
         }
 
         if !self.fs_status.up_to_date() {
-            bail!("current filesystem status shows we're outdated");
+            return Err(DirtyReason::new(
+                "fs",
+                "current filesystem status shows we're outdated",
+            ));
         }
 
         // This typically means some filesystem modifications happened or
         // something transitive was odd. In general we should strive to provide
         // a better error message than this, so if you see this message a lot it
         // likely means this method needs to be updated!
-        bail!("two fingerprint comparison turned up nothing obvious");
+        Err(DirtyReason::new(
+            "unknown",
+            "two fingerprint comparison turned up nothing obvious",
+        ))
     }
 
     /// Dynamically inspect the local filesystem to update the `fs_status` field
@@ -974,6 +1147,7 @@ impl Fingerprint {
         mtime_cache: &mut HashMap<PathBuf, FileTime>,
         pkg_root: &Path,
         target_root: &Path,
+        checksum_freshness: bool,
     ) -> CargoResult<()> {
         assert!(!self.fs_status.up_to_date());
 
@@ -991,6 +1165,7 @@ impl Fingerprint {
                 // exists, so leave ourselves as stale and bail out.
                 Err(e) => {
                     debug!("failed to get mtime of {:?}: {}", output, e);
+                    self.stale_item = Some(StaleItem::MissingFile(output.clone()));
                     return Ok(());
                 }
             };
@@ -1018,7 +1193,10 @@ impl Fingerprint {
             let dep_mtimes = match &dep.fingerprint.fs_status {
                 FsStatus::UpToDate { mtimes } => mtimes,
                 // If our dependency is stale, so are we, so bail out.
-                FsStatus::Stale => return Ok(()),
+                FsStatus::Stale => {
+                    self.stale_item = Some(StaleItem::StaleDependency { name: dep.name });
+                    return Ok(());
+                }
             };
 
             // If our dependency edge only requires the rmeta file to be present
@@ -1052,10 +1230,13 @@ impl Fingerprint {
             // for a discussion of why it's `>` see the discussion about #5918
             // below in `find_stale`.
             if dep_mtime > max_mtime {
-                info!(
-                    "dependency on `{}` is newer than we are {} > {} {:?}",
-                    dep.name, dep_mtime, max_mtime, pkg_root
-                );
+                let item = StaleItem::NewerDependency {
+                    name: dep.name,
+                    dep_mtime: *dep_mtime,
+                    max_mtime: *max_mtime,
+                };
+                item.log();
+                self.stale_item = Some(item);
                 return Ok(());
             }
         }
@@ -1064,15 +1245,24 @@ impl Fingerprint {
         // all our `LocalFingerprint` information to see if we have any stale
         // files for this package itself. If we do find something log a helpful
         // message and bail out so we stay stale.
+        let mut touched = Vec::new();
         for local in self.local.get_mut().unwrap().iter() {
-            if let Some(item) = local.find_stale_item(mtime_cache, pkg_root, target_root)? {
+            if let Some(item) = local.find_stale_item(
+                mtime_cache,
+                pkg_root,
+                target_root,
+                checksum_freshness,
+                &mut touched,
+            )? {
                 item.log();
+                self.stale_item = Some(item);
                 return Ok(());
             }
         }
 
         // Everything was up to date! Record such.
         self.fs_status = FsStatus::UpToDate { mtimes };
+        self.touched = touched;
         debug!("filesystem up-to-date {:?}", pkg_root);
 
         Ok(())
@@ -1199,6 +1389,10 @@ impl StaleItem {
                 info!("          (vs) {:?}", reference);
                 info!("               {:?} != {:?}", reference_mtime, stale_mtime);
             }
+            StaleItem::ChangedContent { reference, stale } => {
+                info!("stale: changed content {:?}", stale);
+                info!("                  (vs) {:?}", reference);
+            }
             StaleItem::ChangedEnv {
                 var,
                 previous,
@@ -1207,10 +1401,70 @@ impl StaleItem {
                 info!("stale: changed env {:?}", var);
                 info!("       {:?} != {:?}", previous, current);
             }
+            StaleItem::StaleDependency { name } => {
+                info!("stale: dependency on `{}` is stale", name);
+            }
+            StaleItem::NewerDependency {
+                name,
+                dep_mtime,
+                max_mtime,
+            } => {
+                info!(
+                    "stale: dependency on `{}` is newer than we are {} > {}",
+                    name, dep_mtime, max_mtime
+                );
+            }
+        }
+    }
+
+    /// A short explanation for `-v` output of why a unit is stale, with paths
+    /// shown relative to `pkg_root` where possible.
+    fn describe(&self, pkg_root: &Path) -> String {
+        match self {
+            StaleItem::MissingFile(path) => {
+                format!("missing: {}", relative_display(pkg_root, path))
+            }
+            StaleItem::ChangedFile { stale, .. } => {
+                format!("modified: {}", relative_display(pkg_root, stale))
+            }
+            StaleItem::ChangedContent { stale, .. } => {
+                format!("content changed: {}", relative_display(pkg_root, stale))
+            }
+            StaleItem::ChangedEnv { var, .. } => format!("env var changed: {}", var),
+            StaleItem::StaleDependency { name } => format!("dependency `{}` is stale", name),
+            StaleItem::NewerDependency { name, .. } => {
+                format!("dependency `{}` was rebuilt more recently", name)
+            }
+        }
+    }
+
+    /// The `DirtyReason` for `--explain-rebuild` of a unit made dirty by this
+    /// item.
+    fn dirty_reason(&self, pkg_root: &Path) -> DirtyReason {
+        let mut reason = DirtyReason::new("fs", self.describe(pkg_root));
+        match self {
+            StaleItem::ChangedEnv {
+                previous, current, ..
+            } => {
+                let value = |v: &Option<String>| v.clone().unwrap_or_else(|| "(unset)".into());
+                reason = reason.values(value(previous), value(current));
+            }
+            StaleItem::StaleDependency { name } | StaleItem::NewerDependency { name, .. } => {
+                reason.dependency = Some(*name);
+            }
+            _ => {}
         }
+        reason
     }
 }
 
+fn relative_display(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
 /// Calculates the fingerprint for a `unit`.
 ///
 /// This fingerprint is used by Cargo to learn about when information such as:
@@ -1240,7 +1494,13 @@ fn calculate(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Arc<Fingerpri
     // After we built the initial `Fingerprint` be sure to update the
     // `fs_status` field of it.
     let target_root = target_root(cx);
-    fingerprint.check_filesystem(&mut cx.mtime_cache, unit.pkg.root(), &target_root)?;
+    let checksum_freshness = cx.bcx.config.cli_unstable().checksum_freshness;
+    fingerprint.check_filesystem(
+        &mut cx.mtime_cache,
+        unit.pkg.root(),
+        &target_root,
+        checksum_freshness,
+    )?;
 
     let fingerprint = Arc::new(fingerprint);
     cx.fingerprints
@@ -1335,6 +1595,8 @@ fn calculate_normal(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Finger
         rustflags: extra_flags,
         fs_status: FsStatus::Stale,
         outputs,
+        stale_item: None,
+        touched: Vec::new(),
     })
 }
 
@@ -1628,7 +1890,7 @@ fn compare_old_fingerprint(
     }
     let result = new_fingerprint.compare(&old_fingerprint);
     assert!(result.is_err());
-    result
+    Ok(result?)
 }
 
 fn log_compare(unit: &Unit, compare: &CargoResult<()>) {
@@ -1643,6 +1905,175 @@ fn log_compare(unit: &Unit, compare: &CargoResult<()>) {
     info!("    err: {:?}", ce);
 }
 
+/// Returns why `unit` is rebuilt, given the result of comparing its
+/// fingerprint to the one at `loc`.
+fn dirty_reason(
+    unit: &Unit,
+    loc: &Path,
+    fingerprint: &Fingerprint,
+    compare: &CargoResult<()>,
+) -> DirtyReason {
+    let e = match compare {
+        Ok(()) => return DirtyReason::new("force", "a rebuild was requested"),
+        Err(e) => e,
+    };
+    match e.downcast_ref::<DirtyReason>() {
+        Some(reason) if reason.field == "fs" => match &fingerprint.stale_item {
+            Some(item) => item.dirty_reason(unit.pkg.root()),
+            None => reason.clone(),
+        },
+        Some(reason) => reason.clone(),
+        None if !loc.exists() => DirtyReason::new("fingerprint", "not built before"),
+        None => DirtyReason::new(
+            "fingerprint",
+            format!("the previous fingerprint couldn't be read: {}", e),
+        ),
+    }
+}
+
+/// Explains in `-v` output, for `-Z checksum-freshness`, which input file
+/// made `unit` dirty, or which ones were touched without their content
+/// changing.
+fn report_fs_status(
+    config: &Config,
+    unit: &Unit,
+    fingerprint: &Fingerprint,
+    fresh: bool,
+) -> CargoResult<()> {
+    let root = unit.pkg.root();
+    let mut shell = config.shell();
+    if !fresh {
+        if let Some(item) = &fingerprint.stale_item {
+            shell
+                .verbose(|s| s.status("Dirty", format!("{}: {}", unit.pkg, item.describe(root))))?;
+        }
+        return Ok(());
+    }
+    for path in fingerprint.touched.iter() {
+        shell.verbose(|s| {
+            s.status(
+                "Touched",
+                format!(
+                    "{}: touched only: {}",
+                    unit.pkg,
+                    relative_display(root, path)
+                ),
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Prints, for `--explain-rebuild`, why each unit was rebuilt.
+///
+/// Units are shown as a tree in which a unit that was rebuilt because of a
+/// dependency is below that dependency, so the units at the top are the root
+/// causes. With JSON messages, a `rebuild-reason` message is also emitted for
+/// each unit.
+pub fn explain_rebuilds(cx: &Context<'_, '_>) -> CargoResult<()> {
+    let config = cx.bcx.config;
+    // The dependency that made each unit dirty, if it was rebuilt as well.
+    let mut parents = HashMap::new();
+    for (unit, reason) in cx.rebuild_reasons.iter() {
+        let dep = reason.dependency.and_then(|name| {
+            cx.unit_deps(unit)
+                .iter()
+                .find(|dep| dep.extern_crate_name == name)
+        });
+        if let Some(dep) = dep {
+            parents.insert(unit, &dep.unit);
+        }
+        if cx.bcx.build_config.emit_json() {
+            let msg = machine_message::RebuildReason {
+                package_id: unit.pkg.package_id(),
+                target: &unit.target,
+                mode: unit.mode,
+                field: reason.field,
+                message: &reason.message,
+                old: reason.old.as_deref(),
+                new: reason.new.as_deref(),
+                dependency: dep.map(|dep| dep.unit.pkg.package_id()),
+            }
+            .to_json_string();
+            writeln!(config.shell().out(), "{}", msg)?;
+        }
+    }
+
+    let mut children: HashMap<Option<&Unit>, Vec<&Unit>> = HashMap::new();
+    for unit in cx.rebuild_reasons.keys() {
+        let parent = parents
+            .get(unit)
+            .copied()
+            .filter(|parent| cx.rebuild_reasons.contains_key(parent));
+        children.entry(parent).or_default().push(unit);
+    }
+    for units in children.values_mut() {
+        units.sort_by_key(|unit| unit_description(unit));
+    }
+
+    let count = cx.rebuild_reasons.len();
+    if count == 0 {
+        return Ok(());
+    }
+    let mut shell = config.shell();
+    shell.status(
+        "Rebuilt",
+        format!("{} {}:", count, if count == 1 { "unit" } else { "units" }),
+    )?;
+    let mut lines = Vec::new();
+    for root in children.get(&None).into_iter().flatten() {
+        tree_lines(cx, root, &children, "", "", &mut lines);
+    }
+    for line in lines {
+        writeln!(shell.err(), "{}", line)?;
+    }
+    Ok(())
+}
+
+fn tree_lines(
+    cx: &Context<'_, '_>,
+    unit: &Unit,
+    children: &HashMap<Option<&Unit>, Vec<&Unit>>,
+    prefix: &str,
+    child_prefix: &str,
+    lines: &mut Vec<String>,
+) {
+    lines.push(format!(
+        "{}{}: {}",
+        prefix,
+        unit_description(unit),
+        cx.rebuild_reasons[unit]
+    ));
+    let units = match children.get(&Some(unit)) {
+        Some(units) => units,
+        None => return,
+    };
+    for (i, child) in units.iter().enumerate() {
+        let (tee, down) = if i + 1 == units.len() {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        tree_lines(
+            cx,
+            child,
+            children,
+            &format!("{}{}", child_prefix, tee),
+            &format!("{}{}", child_prefix, down),
+            lines,
+        );
+    }
+}
+
+fn unit_description(unit: &Unit) -> String {
+    format!(
+        "{} v{}{}",
+        unit.pkg.name(),
+        unit.pkg.version(),
+        timings::target_description(unit)
+    )
+}
+
 /// Parses Cargo's internal `EncodedDepInfo` structure that was previously
 /// serialized to disk.
 ///
@@ -1672,12 +2103,15 @@ pub fn parse_dep_info(
     };
     let mut ret = RustcDepInfo::default();
     ret.env = info.env;
-    for (ty, path) in info.files {
+    for (ty, path, checksum) in info.files {
         let path = match ty {
             DepInfoPathType::PackageRootRelative => pkg_root.join(path),
             // N.B. path might be absolute here in which case the join will have no effect
             DepInfoPathType::TargetRootRelative => target_root.join(path),
         };
+        if let Some(checksum) = checksum {
+            ret.checksums.insert(path.clone(), checksum);
+        }
         ret.files.push(path);
     }
     Ok(Some(ret))
@@ -1697,6 +2131,8 @@ fn find_stale_file<I>(
     mtime_cache: &mut HashMap<PathBuf, FileTime>,
     reference: &Path,
     paths: I,
+    checksums: &HashMap<PathBuf, FileChecksum>,
+    touched: &mut Vec<PathBuf>,
 ) -> Option<StaleItem>
 where
     I: IntoIterator,
@@ -1742,6 +2178,20 @@ where
             continue;
         }
 
+        // A newer file with a recorded checksum is only stale if its content
+        // actually changed, so that a `git checkout` or a restored cache
+        // doesn't force a rebuild.
+        if let Some(checksum) = checksums.get(path) {
+            if checksum.matches(path) {
+                touched.push(path.to_path_buf());
+                continue;
+            }
+            return Some(StaleItem::ChangedContent {
+                reference: reference.to_path_buf(),
+                stale: path.to_path_buf(),
+            });
+        }
+
         return Some(StaleItem::ChangedFile {
             reference: reference.to_path_buf(),
             reference_mtime,
@@ -1786,6 +2236,10 @@ enum DepInfoPathType {
 /// all those files). See the module-level docs for the note about
 /// `-Zbinary-dep-depinfo` for more details on why this is done.
 ///
+/// If `checksum_start` is set (with `-Z checksum-freshness`), it is the time
+/// the compiler was started, and the size and SHA-256 of every file not
+/// modified since then is recorded as well.
+///
 /// The serialized Cargo format will contain a list of files, all of which are
 /// relative if they're under `root`. or absolute if they're elsewhere.
 pub fn translate_dep_info(
@@ -1796,6 +2250,7 @@ pub fn translate_dep_info(
     target_root: &Path,
     rustc_cmd: &ProcessBuilder,
     allow_package: bool,
+    checksum_start: Option<FileTime>,
 ) -> CargoResult<()> {
     let depinfo = parse_rustc_dep_info(rustc_dep_info)?;
 
@@ -1854,7 +2309,9 @@ pub fn translate_dep_info(
             // effect.
             (DepInfoPathType::TargetRootRelative, &*abs_file)
         };
-        on_disk_info.files.push((ty, path.to_owned()));
+        let checksum =
+            checksum_start.and_then(|start| FileChecksum::unmodified_since(&canon_file, start));
+        on_disk_info.files.push((ty, path.to_owned(), checksum));
     }
     paths::write(cargo_dep_info, on_disk_info.serialize()?)?;
     Ok(())
@@ -1872,6 +2329,54 @@ pub struct RustcDepInfo {
     /// means that the env var wasn't actually set and the compilation depends
     /// on it not being set.
     pub env: Vec<(String, Option<String>)>,
+    /// Checksums of the files in `files` recorded with
+    /// `-Z checksum-freshness`.
+    pub checksums: HashMap<PathBuf, FileChecksum>,
+}
+
+/// The size and SHA-256 of a file, used by `-Z checksum-freshness` to tell a
+/// file whose content changed from one that was only touched.
+#[derive(Clone, PartialEq)]
+pub struct FileChecksum {
+    size: u64,
+    sha256: [u8; 32],
+}
+
+impl FileChecksum {
+    fn new(path: &Path) -> CargoResult<FileChecksum> {
+        let file = paths::open(path)?;
+        let size = file.metadata()?.len();
+        let sha256 = Sha256::new()
+            .update_file(&file)
+            .chain_err(|| format!("failed to read `{}`", path.display()))?
+            .finish();
+        Ok(FileChecksum { size, sha256 })
+    }
+
+    /// Computes the checksum of `path`, unless it was modified after `start`.
+    ///
+    /// The mtime is checked after hashing, so a file edited while the
+    /// compiler ran never gets a checksum of content the compiler didn't see.
+    /// Those files are left to the mtime comparison instead.
+    fn unmodified_since(path: &Path, start: FileTime) -> Option<FileChecksum> {
+        let checksum = FileChecksum::new(path).ok()?;
+        match paths::mtime(path) {
+            Ok(mtime) if mtime <= start => Some(checksum),
+            _ => None,
+        }
+    }
+
+    /// Returns whether `path` still has this size and SHA-256.
+    fn matches(&self, path: &Path) -> bool {
+        match fs::metadata(path) {
+            Ok(meta) if meta.len() == self.size => {}
+            _ => return false,
+        }
+        match FileChecksum::new(path) {
+            Ok(checksum) => checksum == *self,
+            Err(_) => false,
+        }
+    }
 }
 
 // Same as `RustcDepInfo` except avoids absolute paths as much as possible to
@@ -1879,9 +2384,12 @@ pub struct RustcDepInfo {
 //
 // This is also stored in an optimized format to make parsing it fast because
 // Cargo will read it for crates on all future compilations.
+//
+// Checksums from `-Z checksum-freshness` are written in a trailer after the
+// env vars, so dep-info files with and without them can be read by any Cargo.
 #[derive(Default)]
 struct EncodedDepInfo {
-    files: Vec<(DepInfoPathType, PathBuf)>,
+    files: Vec<(DepInfoPathType, PathBuf, Option<FileChecksum>)>,
     env: Vec<(String, Option<String>)>,
 }
 
@@ -1897,7 +2405,7 @@ impl EncodedDepInfo {
                 _ => return None,
             };
             let bytes = read_bytes(bytes)?;
-            files.push((ty, util::bytes2path(bytes).ok()?));
+            files.push((ty, util::bytes2path(bytes).ok()?, None));
         }
 
         let nenv = read_usize(bytes)?;
@@ -1911,8 +2419,28 @@ impl EncodedDepInfo {
             };
             env.push((key, val));
         }
+
+        if !bytes.is_empty() {
+            for (_, _, checksum) in files.iter_mut() {
+                *checksum = match read_u8(bytes)? {
+                    0 => None,
+                    1 => {
+                        let size = read_u64(bytes)?;
+                        let sha256 = read_bytes(bytes)?.try_into().ok()?;
+                        Some(FileChecksum { size, sha256 })
+                    }
+                    _ => return None,
+                };
+            }
+        }
         return Some(EncodedDepInfo { files, env });
 
+        fn read_u64(bytes: &mut &[u8]) -> Option<u64> {
+            let ret = bytes.get(..8)?;
+            *bytes = &bytes[8..];
+            Some(u64::from_le_bytes(ret.try_into().unwrap()))
+        }
+
         fn read_usize(bytes: &mut &[u8]) -> Option<usize> {
             let ret = bytes.get(..4)?;
             *bytes = &bytes[4..];
@@ -1937,7 +2465,7 @@ impl EncodedDepInfo {
         let mut ret = Vec::new();
         let dst = &mut ret;
         write_usize(dst, self.files.len());
-        for (ty, file) in self.files.iter() {
+        for (ty, file, _) in self.files.iter() {
             match ty {
                 DepInfoPathType::PackageRootRelative => dst.push(0),
                 DepInfoPathType::TargetRootRelative => dst.push(1),
@@ -1956,6 +2484,19 @@ impl EncodedDepInfo {
                 }
             }
         }
+
+        if self.files.iter().any(|(_, _, checksum)| checksum.is_some()) {
+            for (_, _, checksum) in self.files.iter() {
+                match checksum {
+                    None => dst.push(0),
+                    Some(checksum) => {
+                        dst.push(1);
+                        dst.extend(&u64::to_le_bytes(checksum.size));
+                        write_bytes(dst, checksum.sha256);
+                    }
+                }
+            }
+        }
         return Ok(ret);
 
         fn write_bytes(dst: &mut Vec<u8>, val: impl AsRef<[u8]>) {
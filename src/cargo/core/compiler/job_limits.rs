@@ -0,0 +1,109 @@
+//! Limits on what the `JobQueue` runs at the same time, besides the number
+//! of jobs, enabled with `-Z job-limits`.
+//!
+//! * `build.max-concurrent-links` caps how many units that link a binary or
+//!   a dynamic library (bins, tests, benches, examples, dylibs, ...) build
+//!   at once, since linking large binaries is what tends to run out of
+//!   memory.
+//! * `build.min-free-memory` holds back new units while the memory
+//!   available on the system is below a threshold.
+//!
+//! A unit is never held back when nothing else is running, so the build
+//! always makes progress, and fresh units are never held back since they
+//! don't do any work.
+
+use std::fmt;
+
+use anyhow::format_err;
+
+use crate::core::compiler::{BuildContext, CompileMode, Unit};
+use crate::util::errors::CargoResult;
+use crate::util::memory;
+
+#[derive(Default)]
+pub struct JobLimits {
+    max_concurrent_links: Option<usize>,
+    /// In bytes.
+    min_free_memory: Option<u64>,
+}
+
+/// Why a unit was held back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Throttle {
+    /// `build.max-concurrent-links` units that link were already running.
+    Links,
+    /// Less than `build.min-free-memory` was available.
+    Memory,
+}
+
+impl fmt::Display for Throttle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Throttle::Links => f.write_str("the link limit"),
+            Throttle::Memory => f.write_str("free memory"),
+        }
+    }
+}
+
+impl JobLimits {
+    pub fn new(bcx: &BuildContext<'_, '_>) -> CargoResult<JobLimits> {
+        if !bcx.config.cli_unstable().job_limits {
+            return Ok(JobLimits::default());
+        }
+        let build_config = bcx.config.build_config()?;
+        let max_concurrent_links = match build_config.max_concurrent_links {
+            Some(0) => anyhow::bail!("`build.max-concurrent-links` must be at least 1"),
+            Some(max) => Some(max as usize),
+            None => None,
+        };
+        let min_free_memory = match &build_config.min_free_memory {
+            Some(size) => Some(memory::parse_size(size).ok_or_else(|| {
+                format_err!(
+                    "`build.min-free-memory` must be a size such as `2GiB`, found `{}`",
+                    size
+                )
+            })?),
+            None => None,
+        };
+        Ok(JobLimits {
+            max_concurrent_links,
+            min_free_memory,
+        })
+    }
+
+    /// Returns the memory available on the system, if it's needed to check
+    /// `build.min-free-memory`.
+    pub fn free_memory(&self) -> Option<u64> {
+        self.min_free_memory.and_then(|_| memory::available())
+    }
+
+    /// Returns why `unit` can't start yet, or `None` if it can.
+    ///
+    /// `active_links` is the number of running units that link, and
+    /// `free_memory` is the result of `free_memory`.
+    pub fn throttle(
+        &self,
+        unit: &Unit,
+        active_links: usize,
+        free_memory: Option<u64>,
+    ) -> Option<Throttle> {
+        if let Some(max) = self.max_concurrent_links {
+            if links(unit) && active_links >= max {
+                return Some(Throttle::Links);
+            }
+        }
+        match (self.min_free_memory, free_memory) {
+            (Some(min), Some(free)) if free < min => Some(Throttle::Memory),
+            _ => None,
+        }
+    }
+}
+
+/// Returns whether building `unit` runs the linker.
+pub fn links(unit: &Unit) -> bool {
+    let compiles = matches!(
+        unit.mode,
+        CompileMode::Build | CompileMode::Test | CompileMode::Bench
+    );
+    compiles && unit.requires_upstream_objects()
+}
@@ -54,7 +54,7 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io;
 use std::marker;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::format_err;
 use crossbeam_utils::thread::Scope;
@@ -62,11 +62,13 @@ use jobserver::{Acquired, Client, HelperThread};
 use log::{debug, info, trace};
 
 use super::context::OutputFile;
+use super::custom_build::DiagnosticLevel;
 use super::job::{
     Freshness::{self, Dirty, Fresh},
     Job,
 };
-use super::timings::Timings;
+use super::job_limits::{self, JobLimits, Throttle};
+use super::timings::{Timings, UnitDurations};
 use super::{BuildContext, BuildPlan, CompileMode, Context, Unit};
 use crate::core::{PackageId, Shell, TargetKind};
 use crate::util::diagnostic_server::{self, DiagnosticPrinter};
@@ -83,6 +85,8 @@ pub struct JobQueue<'cfg> {
     queue: DependencyQueue<Unit, Artifact, Job>,
     counts: HashMap<PackageId, usize>,
     timings: Timings<'cfg>,
+    /// Durations from previous builds, used to prioritize the queue.
+    durations: UnitDurations,
 }
 
 /// This structure is backed by the `DependencyQueue` type and manages the
@@ -149,6 +153,17 @@ struct DrainState<'cfg> {
     pending_queue: Vec<(Unit, Job)>,
     print: DiagnosticPrinter<'cfg>,
 
+    /// Limits on which jobs may run at the same time, from `-Z job-limits`.
+    limits: JobLimits,
+
+    /// Jobs in `pending_queue` that `limits` held back, with the reason and
+    /// when they were first held back.
+    throttled: HashMap<Unit, (Throttle, Instant)>,
+    /// How many tokens requested for `throttled` jobs were given back to the
+    /// jobserver while the jobs were held back, and need to be requested
+    /// again once they can start.
+    released_tokens: usize,
+
     // How many jobs we've finished
     finished: usize,
 }
@@ -225,6 +240,7 @@ enum Message {
     BuildPlanMsg(String, ProcessBuilder, Arc<Vec<OutputFile>>),
     Stdout(String),
     Stderr(String),
+    Status(String, String),
     FixDiagnostic(diagnostic_server::Message),
     Token(io::Result<Acquired>),
     Finish(JobId, Artifact, CargoResult<()>),
@@ -271,6 +287,18 @@ impl<'a> JobState<'a> {
         Ok(())
     }
 
+    /// Prints a status line such as `Restored ...` on the coordinator
+    /// thread.
+    pub fn status(&self, status: &str, message: String) -> CargoResult<()> {
+        if let Some(config) = self.output {
+            config.shell().status(status, message)?;
+        } else {
+            self.messages
+                .push_bounded(Message::Status(status.to_string(), message));
+        }
+        Ok(())
+    }
+
     /// A method used to signal to the coordinator thread that the rmeta file
     /// for an rlib has been produced. This is only called for some rmeta
     /// builds when required, and can be called at any time before a job ends.
@@ -305,10 +333,14 @@ impl<'cfg> JobQueue<'cfg> {
             queue: DependencyQueue::new(),
             counts: HashMap::new(),
             timings: Timings::new(bcx, &bcx.roots),
+            durations: UnitDurations::load(bcx),
         }
     }
 
     pub fn enqueue(&mut self, cx: &Context<'_, 'cfg>, unit: &Unit, job: Job) -> CargoResult<()> {
+        if let Some(&hit) = cx.build_cache_hits.get(unit) {
+            self.timings.add_build_cache(unit, hit);
+        }
         let dependencies = cx.unit_deps(unit);
         let mut queue_deps = dependencies
             .iter()
@@ -370,11 +402,10 @@ impl<'cfg> JobQueue<'cfg> {
             }
         }
 
-        // For now we use a fixed placeholder value for the cost of each unit, but
-        // in the future this could be used to allow users to provide hints about
-        // relative expected costs of units, or this could be automatically set in
-        // a smarter way using timing data from a previous compilation.
-        self.queue.queue(unit.clone(), job, queue_deps, 100);
+        // Units are weighted by how long they took in previous `-Z timings`
+        // builds, if any, so that the slowest chains start first.
+        let cost = self.durations.cost(unit);
+        self.queue.queue(unit.clone(), job, queue_deps, cost);
         *self.counts.entry(unit.pkg.package_id()).or_insert(0) += 1;
         Ok(())
     }
@@ -409,6 +440,9 @@ impl<'cfg> JobQueue<'cfg> {
             to_send_clients: BTreeMap::new(),
             pending_queue: Vec::new(),
             print: DiagnosticPrinter::new(cx.bcx.config),
+            limits: JobLimits::new(cx.bcx)?,
+            throttled: HashMap::new(),
+            released_tokens: 0,
             finished: 0,
         };
 
@@ -468,13 +502,54 @@ impl<'cfg> DrainState<'cfg> {
         // try to spawn it so long as we've got a jobserver token which says
         // we're able to perform some parallel work.
         while self.has_extra_tokens() && !self.pending_queue.is_empty() {
-            let (unit, job) = self.pending_queue.remove(0);
+            let index = match self.next_runnable() {
+                Some(index) => index,
+                None => break,
+            };
+            let (unit, job) = self.pending_queue.remove(index);
+            if let Some((throttle, since)) = self.throttled.remove(&unit) {
+                let waited = since.elapsed().as_secs_f64();
+                self.timings.unit_throttled(throttle, waited);
+            }
             self.run(&unit, job, cx, scope)?;
         }
 
+        // Held back jobs don't keep their tokens (see `wait_for_events`), so
+        // ask for one again once such a job can start.
+        self.released_tokens = self.released_tokens.min(self.throttled.len());
+        if self.released_tokens > 0 && !self.has_extra_tokens() && self.next_runnable().is_some() {
+            jobserver_helper.request_token();
+            self.released_tokens -= 1;
+        }
+
         Ok(())
     }
 
+    /// Returns the index of the first job in `pending_queue` that `limits`
+    /// allows to start, recording why the jobs before it were held back.
+    fn next_runnable(&mut self) -> Option<usize> {
+        let active_links = self
+            .active
+            .values()
+            .filter(|unit| job_limits::links(unit))
+            .count();
+        let free_memory = self.limits.free_memory();
+        for (index, (unit, job)) in self.pending_queue.iter().enumerate() {
+            if self.active.is_empty() || job.freshness() == Fresh {
+                return Some(index);
+            }
+            match self.limits.throttle(unit, active_links, free_memory) {
+                Some(throttle) => {
+                    self.throttled
+                        .entry(unit.clone())
+                        .or_insert_with(|| (throttle, Instant::now()));
+                }
+                None => return Some(index),
+            }
+        }
+        None
+    }
+
     fn has_extra_tokens(&self) -> bool {
         self.active.len() < self.tokens.len() + 1
     }
@@ -541,6 +616,9 @@ impl<'cfg> DrainState<'cfg> {
                 shell.print_ansi(err.as_bytes())?;
                 shell.err().write_all(b"\n")?;
             }
+            Message::Status(status, message) => {
+                cx.bcx.config.shell().status(status, message)?;
+            }
             Message::FixDiagnostic(msg) => {
                 self.print.print(&msg)?;
             }
@@ -642,6 +720,12 @@ impl<'cfg> DrainState<'cfg> {
         if events.is_empty() {
             loop {
                 self.tick_progress();
+                // Tokens beyond what the active jobs use can only be meant
+                // for throttled jobs, which may be held back for a while.
+                // Give them back so other processes sharing the jobserver
+                // can use them in the meantime.
+                let surplus = self.tokens.len().saturating_sub(self.active.len() - 1);
+                self.released_tokens = (self.released_tokens + surplus).min(self.throttled.len());
                 self.tokens.truncate(self.active.len() - 1);
                 match self.messages.pop(Duration::from_millis(500)) {
                     Some(message) => {
@@ -731,6 +815,15 @@ impl<'cfg> DrainState<'cfg> {
             opt_type += " + debuginfo";
         }
 
+        if cx.bcx.build_config.explain_rebuild {
+            if let Err(e) = super::fingerprint::explain_rebuilds(cx) {
+                if error.is_some() {
+                    crate::display_error(&e, &mut cx.bcx.config.shell());
+                } else {
+                    return Some(e);
+                }
+            }
+        }
         let time_elapsed = util::elapsed(cx.bcx.config.creation_time().elapsed());
         if let Err(e) = self.timings.finished(cx.bcx, &error) {
             if error.is_some() {
@@ -952,13 +1045,21 @@ impl<'cfg> DrainState<'cfg> {
             None => return Ok(()),
         };
         let bcx = &mut cx.bcx;
+        // With JSON messages these were emitted as `compiler-message`s.
+        if bcx.build_config.emit_json() {
+            return Ok(());
+        }
         if let Some(output) = outputs.get(unit.pkg.package_id(), metadata) {
-            if !output.warnings.is_empty() {
+            let (errors, warnings): (Vec<_>, Vec<_>) = output
+                .diagnostics
+                .iter()
+                .partition(|d| d.level == DiagnosticLevel::Error);
+            if !warnings.is_empty() {
                 if let Some(msg) = msg {
                     writeln!(bcx.config.shell().err(), "{}\n", msg)?;
                 }
 
-                for warning in output.warnings.iter() {
+                for warning in warnings {
                     bcx.config.shell().warn(warning)?;
                 }
 
@@ -967,6 +1068,9 @@ impl<'cfg> DrainState<'cfg> {
                     writeln!(bcx.config.shell().err())?;
                 }
             }
+            for error in errors {
+                bcx.config.shell().error(error)?;
+            }
         }
 
         Ok(())
@@ -1,6 +1,8 @@
+mod build_cache;
 mod build_config;
 mod build_context;
 mod build_plan;
+mod build_script_cache;
 mod compilation;
 mod compile_kind;
 mod context;
@@ -8,6 +10,7 @@ mod crate_type;
 mod custom_build;
 mod fingerprint;
 mod job;
+mod job_limits;
 mod job_queue;
 mod layout;
 mod links;
@@ -38,13 +41,16 @@ pub use self::compilation::{Compilation, Doctest};
 pub use self::compile_kind::{CompileKind, CompileTarget};
 pub use self::context::{Context, Metadata};
 pub use self::crate_type::CrateType;
-pub use self::custom_build::{BuildOutput, BuildScriptOutputs, BuildScripts};
+pub use self::custom_build::{
+    BuildDiagnostic, BuildOutput, BuildScriptOutputs, BuildScripts, DiagnosticLevel,
+};
 pub use self::job::Freshness;
 use self::job::{Job, Work};
 use self::job_queue::{JobQueue, JobState};
 pub(crate) use self::layout::Layout;
 pub use self::lto::Lto;
 use self::output_depinfo::output_depinfo;
+pub use self::timings::TimingsReport;
 use self::unit_graph::UnitDep;
 pub use crate::core::compiler::unit::{Unit, UnitInterner};
 use crate::core::features::nightly_features_allowed;
@@ -254,6 +260,7 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
     let fingerprint_dir = cx.files().fingerprint_dir(unit);
     let script_metadata = cx.find_build_script_metadata(unit.clone());
     let is_local = unit.is_local();
+    let checksum_freshness = cx.bcx.config.cli_unstable().checksum_freshness;
 
     return Ok(Work::new(move |state| {
         // Only at runtime have we discovered what the extra -L and -l
@@ -331,6 +338,11 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
                 &rustc,
                 // Do not track source files in the fingerprint for registry dependencies.
                 is_local,
+                if checksum_freshness {
+                    Some(timestamp)
+                } else {
+                    None
+                },
             )
             .chain_err(|| {
                 internal(format!(
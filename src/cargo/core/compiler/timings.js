@@ -106,6 +106,15 @@ function render_pipeline_graph() {
       roundedRect(ctx, rmeta_x, y, px_per_sec * ctime, BOX_HEIGHT, RADIUS);
       ctx.fill();
     }
+    if (unit.critical_path) {
+      ctx.save();
+      ctx.beginPath();
+      ctx.strokeStyle = '#e80000';
+      ctx.lineWidth = 2;
+      roundedRect(ctx, x, y, width, BOX_HEIGHT, RADIUS);
+      ctx.stroke();
+      ctx.restore();
+    }
     ctx.fillStyle = "#000";
     ctx.textAlign = 'start';
     ctx.textBaseline = 'hanging';
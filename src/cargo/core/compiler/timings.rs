@@ -2,15 +2,18 @@
 //!
 //! This module implements some simple tracking information for timing of how
 //! long it takes for different units to compile.
+use super::job_limits::Throttle;
 use super::{CompileMode, Unit};
 use crate::core::compiler::job_queue::JobId;
 use crate::core::compiler::BuildContext;
 use crate::core::PackageId;
 use crate::util::cpu::State;
 use crate::util::machine_message::{self, Message};
-use crate::util::{paths, CargoResult, CargoResultExt, Config};
+use crate::util::{self, paths, CargoResult, CargoResultExt, Config};
+use anyhow::bail;
 use std::collections::HashMap;
 use std::io::{BufWriter, Write};
+use std::path::Path;
 use std::time::{Duration, Instant, SystemTime};
 
 pub struct Timings<'cfg> {
@@ -37,6 +40,12 @@ pub struct Timings<'cfg> {
     total_fresh: u32,
     /// Total number of dirty units.
     total_dirty: u32,
+    /// Units that could use the build cache, and whether they were restored
+    /// from it.
+    build_cache: HashMap<Unit, bool>,
+    /// For each reason `-Z job-limits` held units back, the number of units
+    /// held back and the total time, in seconds, they waited.
+    throttled: HashMap<Throttle, (usize, f64)>,
     /// Time tracking for each individual unit.
     unit_times: Vec<UnitTime>,
     /// Units that are in the process of being built.
@@ -72,8 +81,30 @@ struct UnitTime {
     unlocked_rmeta_units: Vec<Unit>,
 }
 
+/// A unit on the critical path of a build.
+struct CriticalPathStep {
+    /// The index of the unit in `Timings::unit_times`.
+    index: usize,
+    /// Time in seconds from when the unit started until it unlocked the next
+    /// unit on the path, or until it finished for the last unit.
+    time: f64,
+    /// Whether the next unit on the path was unlocked by this unit's
+    /// `.rmeta` rather than its completion.
+    unlocked_next_by_rmeta: bool,
+}
+
+/// The number of crates listed with their time on the critical path.
+const CRITICAL_PATH_CRATES: usize = 10;
+
+/// Units taking at least this share of the critical path are suggested to be
+/// split.
+const CRITICAL_PATH_SPLIT_SHARE: f64 = 0.2;
+
+/// Savings shorter than this, in seconds, aren't worth a suggestion.
+const CRITICAL_PATH_MIN_SAVING: f64 = 0.1;
+
 /// Periodic concurrency tracking information.
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
 struct Concurrency {
     /// Time as an offset in seconds from `Timings::start`.
     t: f64,
@@ -90,6 +121,309 @@ struct Concurrency {
     rustc_parallelism: usize,
 }
 
+/// The version of the JSON timings report written by `-Z timings=json`.
+///
+/// Bump this whenever the meaning of a field changes or a field is removed,
+/// so that `cargo report timings` doesn't misread older reports.
+const TIMINGS_REPORT_VERSION: u32 = 1;
+
+/// The timings of a whole build, saved to `cargo-timing.json` by
+/// `-Z timings=json` and read back by `cargo report timings --compare`.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct TimingsReport {
+    /// Always `TIMINGS_REPORT_VERSION` for reports written by this Cargo.
+    version: u32,
+    /// When the build started, in RFC 3339 format.
+    start: String,
+    /// Descriptions of the root units, such as `foo 0.1.0 (lib, bin "foo")`.
+    targets: Vec<String>,
+    profile: String,
+    /// The first line of `rustc -vV`.
+    rustc: String,
+    jobs: u32,
+    ncpu: usize,
+    /// Total time of the build in seconds.
+    duration: f64,
+    fresh_units: u32,
+    dirty_units: u32,
+    /// The error that stopped the build, if it failed.
+    error: Option<String>,
+    /// The units that were compiled, in the order they started.
+    units: Vec<UnitReport>,
+    concurrency: Vec<Concurrency>,
+    /// Tuples of the time of a sample and the percentage of CPU in use.
+    cpu_usage: Vec<(f64, f64)>,
+}
+
+impl TimingsReport {
+    /// Reads a report saved by `-Z timings=json`.
+    pub fn load(path: &Path) -> CargoResult<TimingsReport> {
+        #[derive(serde::Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let contents = paths::read(path)?;
+        let version: Version = serde_json::from_str(&contents)
+            .chain_err(|| format!("`{}` is not a timings report", path.display()))?;
+        if version.version != TIMINGS_REPORT_VERSION {
+            bail!(
+                "`{}` is a version {} timings report, but this version of Cargo \
+                 only supports version {}",
+                path.display(),
+                version.version,
+                TIMINGS_REPORT_VERSION
+            );
+        }
+        serde_json::from_str(&contents)
+            .chain_err(|| format!("failed to parse timings report `{}`", path.display()))
+    }
+
+    /// Renders an HTML page comparing the timings of the build in `self` to
+    /// those of a later build in `new`, with the units that got slower
+    /// highlighted.
+    pub fn render_comparison(&self, new: &TimingsReport) -> CargoResult<String> {
+        let mut f = Vec::new();
+        f.write_all(
+            HTML_TMPL
+                .replace("{ROOTS}", &new.targets.join(", "))
+                .replace(
+                    "Cargo Build Timings</h1>",
+                    "Cargo Build Timings Comparison</h1>",
+                )
+                .as_bytes(),
+        )?;
+        let mut rows: Vec<ComparisonRow<'_>> = Vec::new();
+        for unit in self.units.iter() {
+            rows.push(ComparisonRow {
+                unit,
+                old: Some(unit.duration()),
+                new: None,
+            });
+        }
+        for unit in new.units.iter() {
+            match rows.iter_mut().find(|row| row.unit.same_unit(unit)) {
+                Some(row) if row.new.is_none() => row.new = Some(unit.duration()),
+                _ => rows.push(ComparisonRow {
+                    unit,
+                    old: None,
+                    new: Some(unit.duration()),
+                }),
+            }
+        }
+        rows.sort_by(|a, b| b.change().partial_cmp(&a.change()).unwrap());
+        let regressions = rows
+            .iter()
+            .filter(|row| row.class() == "regression")
+            .count();
+
+        let duration = |report: &TimingsReport| format!("{:.1}s", report.duration);
+        write!(
+            f,
+            r#"
+<table class="my-table summary-table">
+  <tr>
+    <td></td><td>Old</td><td>New</td>
+  </tr>
+  <tr>
+    <td>Targets:</td><td>{}</td><td>{}</td>
+  </tr>
+  <tr>
+    <td>Profile:</td><td>{}</td><td>{}</td>
+  </tr>
+  <tr>
+    <td>Build start:</td><td>{}</td><td>{}</td>
+  </tr>
+  <tr>
+    <td>rustc:</td><td>{}</td><td>{}</td>
+  </tr>
+  <tr>
+    <td>Fresh units:</td><td>{}</td><td>{}</td>
+  </tr>
+  <tr>
+    <td>Dirty units:</td><td>{}</td><td>{}</td>
+  </tr>
+  <tr>
+    <td>Total time:</td><td>{}</td><td>{} ({:+.1}s)</td>
+  </tr>
+  <tr>
+    <td>Regressions:</td><td colspan="2">{}</td>
+  </tr>
+</table>
+"#,
+            self.targets.join("<br>"),
+            new.targets.join("<br>"),
+            self.profile,
+            new.profile,
+            self.start,
+            new.start,
+            self.rustc,
+            new.rustc,
+            self.fresh_units,
+            new.fresh_units,
+            self.dirty_units,
+            new.dirty_units,
+            duration(self),
+            duration(new),
+            new.duration - self.duration,
+            regressions,
+        )?;
+
+        write!(
+            f,
+            r#"
+<table class="my-table">
+  <thead>
+    <tr>
+      <th>Unit</th>
+      <th>Old</th>
+      <th>New</th>
+      <th>Change</th>
+    </tr>
+  </thead>
+  <tbody>
+"#
+        )?;
+        let time = |t: Option<f64>| t.map_or("-".to_string(), |t| format!("{:.1}s", t));
+        for row in rows.iter() {
+            let change = match (row.old, row.new) {
+                (Some(old), Some(new)) if old > 0.0 => {
+                    format!("{:+.1}s ({:+.0}%)", new - old, (new - old) / old * 100.0)
+                }
+                (Some(old), Some(new)) => format!("{:+.1}s", new - old),
+                (Some(_), None) => "removed".to_string(),
+                (None, _) => "added".to_string(),
+            };
+            write!(
+                f,
+                r#"
+<tr class="{}">
+  <td>{} v{}{}</td>
+  <td>{}</td>
+  <td>{}</td>
+  <td>{}</td>
+</tr>
+"#,
+                row.class(),
+                row.unit.name,
+                row.unit.version,
+                if row.unit.target.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {}", row.unit.target)
+                },
+                time(row.old),
+                time(row.new),
+                change,
+            )?;
+        }
+        write!(f, "</tbody>\n</table>\n</body>\n</html>\n")?;
+        Ok(String::from_utf8(f).unwrap())
+    }
+}
+
+/// A unit's duration in the two builds of a comparison.
+struct ComparisonRow<'a> {
+    unit: &'a UnitReport,
+    old: Option<f64>,
+    new: Option<f64>,
+}
+
+impl ComparisonRow<'_> {
+    /// How much longer the unit took in the new build, for sorting.
+    fn change(&self) -> f64 {
+        self.new.unwrap_or(0.0) - self.old.unwrap_or(0.0)
+    }
+
+    /// The CSS class of the row. A unit regressed if it took at least 10%
+    /// and a tenth of a second longer.
+    fn class(&self) -> &'static str {
+        let (old, new) = match (self.old, self.new) {
+            (Some(old), Some(new)) => (old, new),
+            _ => return "",
+        };
+        if new - old >= 0.1 && new >= old * 1.1 {
+            "regression"
+        } else if old - new >= 0.1 && old >= new * 1.1 {
+            "improvement"
+        } else {
+            ""
+        }
+    }
+}
+
+/// A unit in a `TimingsReport`. Times are offsets in seconds from the start
+/// of the build.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct UnitReport {
+    name: String,
+    version: String,
+    /// The target and mode, such as `bin "foo" (test)`, or empty for a
+    /// library.
+    target: String,
+    features: Vec<String>,
+    start: f64,
+    /// When the `.rmeta` file was generated, if the unit has one.
+    rmeta: Option<f64>,
+    end: f64,
+}
+
+/// The file in the target directory holding `UnitDurations`.
+const UNIT_DURATIONS_FILE: &str = ".unit-durations.json";
+
+/// How long units took to build in previous `-Z timings` builds, saved in
+/// the target directory.
+///
+/// `JobQueue` uses these as the costs of units in its `DependencyQueue`, so
+/// that slow units with many dependents, such as `syn`, start first.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct UnitDurations {
+    /// Durations in seconds, keyed by `duration_key`.
+    units: HashMap<String, f64>,
+    /// The mean of `units`, used for units that weren't built before.
+    #[serde(skip)]
+    mean: Option<f64>,
+}
+
+impl UnitDurations {
+    /// Reads the durations saved in the target directory. A missing or
+    /// unreadable file is the same as no durations at all.
+    pub fn load(bcx: &BuildContext<'_, '_>) -> UnitDurations {
+        let path = bcx
+            .ws
+            .target_dir()
+            .as_path_unlocked()
+            .join(UNIT_DURATIONS_FILE);
+        let mut durations = paths::read(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<UnitDurations>(&contents).ok())
+            .unwrap_or_default();
+        if !durations.units.is_empty() {
+            let total: f64 = durations.units.values().sum();
+            durations.mean = Some(total / durations.units.len() as f64);
+        }
+        durations
+    }
+
+    /// Returns the expected cost of building `unit` for the `DependencyQueue`:
+    /// its duration in milliseconds, or the mean duration if it wasn't built
+    /// before. Without any durations every unit costs the same.
+    pub fn cost(&self, unit: &Unit) -> usize {
+        let mean = match self.mean {
+            Some(mean) => mean,
+            None => return 100,
+        };
+        let seconds = self.units.get(&duration_key(unit)).copied().unwrap_or(mean);
+        ((seconds * 1000.0) as usize).max(1)
+    }
+}
+
+/// Identifies `unit` across Cargo invocations. Unlike `Unit::buildkey`,
+/// which hashes the address of the unit, this hashes what the unit is made
+/// of.
+fn duration_key(unit: &Unit) -> String {
+    format!("{}-{}", unit.pkg.name(), util::short_hash(&**unit))
+}
+
 impl<'cfg> Timings<'cfg> {
     pub fn new(bcx: &BuildContext<'_, 'cfg>, root_units: &[Unit]) -> Timings<'cfg> {
         let has_report = |what| {
@@ -145,6 +479,8 @@ impl<'cfg> Timings<'cfg> {
             profile,
             total_fresh: 0,
             total_dirty: 0,
+            build_cache: HashMap::new(),
+            throttled: HashMap::new(),
             unit_times: Vec::new(),
             active: HashMap::new(),
             concurrency: Vec::new(),
@@ -159,23 +495,7 @@ impl<'cfg> Timings<'cfg> {
         if !self.enabled {
             return;
         }
-        let mut target = if unit.target.is_lib() && unit.mode == CompileMode::Build {
-            // Special case for brevity, since most dependencies hit
-            // this path.
-            "".to_string()
-        } else {
-            format!(" {}", unit.target.description_named())
-        };
-        match unit.mode {
-            CompileMode::Test => target.push_str(" (test)"),
-            CompileMode::Build => {}
-            CompileMode::Check { test: true } => target.push_str(" (check-test)"),
-            CompileMode::Check { test: false } => target.push_str(" (check)"),
-            CompileMode::Bench => target.push_str(" (bench)"),
-            CompileMode::Doc { .. } => target.push_str(" (doc)"),
-            CompileMode::Doctest => target.push_str(" (doc test)"),
-            CompileMode::RunCustomBuild => target.push_str(" (run)"),
-        }
+        let target = target_description(&unit);
         let unit_time = UnitTime {
             unit,
             target,
@@ -281,6 +601,45 @@ impl<'cfg> Timings<'cfg> {
         self.total_dirty += 1;
     }
 
+    /// Mark whether a unit was restored from the build cache (`hit`) or had
+    /// to be compiled.
+    pub fn add_build_cache(&mut self, unit: &Unit, hit: bool) {
+        self.build_cache.insert(unit.clone(), hit);
+    }
+
+    /// Returns the number of units restored from the build cache, and the
+    /// number that could have been but weren't in it.
+    fn build_cache_hits(&self) -> (usize, usize) {
+        let hits = self.build_cache.values().filter(|hit| **hit).count();
+        (hits, self.build_cache.len() - hits)
+    }
+
+    /// Mark that a unit waited `waited` seconds to start because of
+    /// `-Z job-limits`.
+    pub fn unit_throttled(&mut self, throttle: Throttle, waited: f64) {
+        let entry = self.throttled.entry(throttle).or_insert((0, 0.0));
+        entry.0 += 1;
+        entry.1 += waited;
+    }
+
+    /// Describes the units held back by `-Z job-limits`, one line per
+    /// reason.
+    fn throttled_lines(&self) -> Vec<String> {
+        [Throttle::Links, Throttle::Memory]
+            .iter()
+            .filter_map(|throttle| {
+                let (units, waited) = self.throttled.get(throttle)?;
+                Some(format!(
+                    "{} unit{} waited {:.1}s for {}",
+                    units,
+                    if *units == 1 { "" } else { "s" },
+                    waited,
+                    throttle
+                ))
+            })
+            .collect()
+    }
+
     /// Take a sample of CPU usage
     pub fn record_cpu(&mut self) {
         if !self.enabled {
@@ -321,10 +680,330 @@ impl<'cfg> Timings<'cfg> {
         self.mark_concurrency(0, 0, 0, 0);
         self.unit_times
             .sort_unstable_by(|a, b| a.start.partial_cmp(&b.start).unwrap());
+        if self.report_info && !self.build_cache.is_empty() {
+            let (hits, misses) = self.build_cache_hits();
+            let msg = format!("build cache: {} hits, {} misses", hits, misses);
+            self.config
+                .shell()
+                .status_with_color("Timing", msg, termcolor::Color::Cyan)?;
+        }
+        if self.report_info {
+            for line in self.throttled_lines() {
+                self.config
+                    .shell()
+                    .status_with_color("Timing", line, termcolor::Color::Cyan)?;
+            }
+        }
         if self.report_html {
             self.report_html(bcx, error)
                 .chain_err(|| "failed to save timing report")?;
         }
+        if self.report_json {
+            self.report_json(bcx, error)
+                .chain_err(|| "failed to save timing report")?;
+        }
+        self.save_durations(bcx)
+            .chain_err(|| "failed to save unit durations")?;
+        Ok(())
+    }
+
+    /// Adds the durations of the units built by this build to the
+    /// `UnitDurations` in the target directory.
+    fn save_durations(&self, bcx: &BuildContext<'_, '_>) -> CargoResult<()> {
+        if self.unit_times.is_empty() {
+            return Ok(());
+        }
+        let mut durations = UnitDurations::load(bcx);
+        for ut in &self.unit_times {
+            durations.units.insert(
+                duration_key(&ut.unit),
+                (ut.duration * 1000.0).round() / 1000.0,
+            );
+        }
+        let target_dir = bcx.ws.target_dir().into_path_unlocked();
+        paths::create_dir_all(&target_dir)?;
+        // Builds of other profiles may save at the same time, so write a
+        // temporary file and move it into place. Either build's durations
+        // may be lost, but never the whole file.
+        let tmp = tempfile::NamedTempFile::new_in(&target_dir)?;
+        paths::write(tmp.path(), serde_json::to_string(&durations)?)?;
+        tmp.persist(target_dir.join(UNIT_DURATIONS_FILE))
+            .map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    /// Computes the critical path of the build: the chain of units that
+    /// limited its wall time.
+    ///
+    /// The path is traced back from the unit that finished last. At each
+    /// step it goes to the unit whose completion, or `.rmeta` with
+    /// pipelining, was the last to unlock the current unit, as recorded in
+    /// `unlocked_units` and `unlocked_rmeta_units`. The result is in build
+    /// order.
+    fn critical_path(&self) -> Vec<CriticalPathStep> {
+        let unit_map: HashMap<&Unit, usize> = self
+            .unit_times
+            .iter()
+            .enumerate()
+            .map(|(i, ut)| (&ut.unit, i))
+            .collect();
+        // For each unit, the units that unlocked it, when they did, and
+        // whether it was with their `.rmeta`.
+        let mut unlocked_by: HashMap<usize, Vec<(usize, f64, bool)>> = HashMap::new();
+        for (i, ut) in self.unit_times.iter().enumerate() {
+            let end = ut.start + ut.duration;
+            for unit in ut.unlocked_units.iter() {
+                if let Some(&index) = unit_map.get(unit) {
+                    unlocked_by.entry(index).or_default().push((i, end, false));
+                }
+            }
+            if let Some(rmeta_time) = ut.rmeta_time {
+                for unit in ut.unlocked_rmeta_units.iter() {
+                    if let Some(&index) = unit_map.get(unit) {
+                        let t = ut.start + rmeta_time;
+                        unlocked_by.entry(index).or_default().push((i, t, true));
+                    }
+                }
+            }
+        }
+
+        let last = self.unit_times.iter().enumerate().max_by(|(_, a), (_, b)| {
+            (a.start + a.duration)
+                .partial_cmp(&(b.start + b.duration))
+                .unwrap()
+        });
+        let (mut index, last) = match last {
+            Some(last) => last,
+            None => return Vec::new(),
+        };
+        let mut path = vec![CriticalPathStep {
+            index,
+            time: last.duration,
+            unlocked_next_by_rmeta: false,
+        }];
+        while let Some(&(prev, t, by_rmeta)) = unlocked_by
+            .get(&index)
+            .and_then(|units| units.iter().max_by(|a, b| a.1.partial_cmp(&b.1).unwrap()))
+        {
+            path.push(CriticalPathStep {
+                index: prev,
+                time: t - self.unit_times[prev].start,
+                unlocked_next_by_rmeta: by_rmeta,
+            });
+            index = prev;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Render the crates that took the most time on the critical path, and
+    /// suggestions on how to shorten it.
+    fn write_critical_path(
+        &self,
+        f: &mut impl Write,
+        bcx: &BuildContext<'_, '_>,
+        critical_path: &[CriticalPathStep],
+    ) -> CargoResult<()> {
+        let total: f64 = critical_path.iter().map(|step| step.time).sum();
+        if total <= 0.0 {
+            return Ok(());
+        }
+        let mut crates: Vec<(String, f64)> = Vec::new();
+        for step in critical_path {
+            let name = self.unit_times[step.index].name_ver();
+            match crates.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, time)) => *time += step.time,
+                None => crates.push((name, step.time)),
+            }
+        }
+        crates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        write!(
+            f,
+            r#"
+<h2>Critical path</h2>
+<table class="my-table">
+  <thead>
+    <tr>
+      <th>Crate</th>
+      <th>Time on critical path</th>
+    </tr>
+  </thead>
+  <tbody>
+"#
+        )?;
+        for (name, time) in crates.iter().take(CRITICAL_PATH_CRATES) {
+            write!(
+                f,
+                r#"
+<tr>
+  <td>{}</td>
+  <td>{:.1}s ({:.0}%)</td>
+</tr>
+"#,
+                name,
+                time,
+                time / total * 100.0
+            )?;
+        }
+        write!(f, "</tbody>\n</table>\n")?;
+
+        let pipelining = bcx.config.build_config()?.pipelining.unwrap_or(true);
+        let suggestions = self.critical_path_suggestions(critical_path, total, pipelining);
+        if !suggestions.is_empty() {
+            writeln!(f, "<ul>")?;
+            for suggestion in suggestions {
+                writeln!(f, "  <li>{}</li>", suggestion)?;
+            }
+            writeln!(f, "</ul>")?;
+        }
+        Ok(())
+    }
+
+    /// Suggests where splitting a crate or pipelining would shorten the
+    /// critical path the most, largest savings first.
+    fn critical_path_suggestions(
+        &self,
+        critical_path: &[CriticalPathStep],
+        total: f64,
+        pipelining: bool,
+    ) -> Vec<String> {
+        let mut suggestions = Vec::new();
+        // Pairs of units on the path where the second one waited for the
+        // first one to finish, rather than for its `.rmeta`.
+        let waits = critical_path
+            .iter()
+            .zip(critical_path.iter().skip(1))
+            .filter(|(step, _)| !step.unlocked_next_by_rmeta)
+            .map(|(step, next)| (&self.unit_times[step.index], &self.unit_times[next.index]));
+
+        // Without pipelining, the dependents of a library always wait for its
+        // code generation. That's worth a single mention, first.
+        if !pipelining {
+            let lib_wait = waits.clone().find(|(ut, next)| {
+                ut.unit.target.is_lib() && !next.unit.mode.is_run_custom_build()
+            });
+            if let Some((ut, next)) = lib_wait {
+                suggestions.push(format!(
+                    "Pipelining is disabled by <code>build.pipelining</code>. Enabling it \
+                     would let {}{} start when the metadata of {}{} is ready, instead of \
+                     waiting for it to finish.",
+                    next.name_ver(),
+                    next.target,
+                    ut.name_ver(),
+                    ut.target
+                ));
+            }
+        }
+
+        let mut savings: Vec<(f64, String)> = Vec::new();
+        for (ut, next) in waits {
+            if let Some((_, ctime, _)) = ut.codegen_time() {
+                if ctime >= CRITICAL_PATH_MIN_SAVING {
+                    savings.push((
+                        ctime,
+                        format!(
+                            "{}{} waited {:.1}s for the code generation of {}{}, which \
+                             pipelining can't overlap because it needs the compiled code. \
+                             Reducing that code generation would shorten the build.",
+                            next.name_ver(),
+                            next.target,
+                            ctime,
+                            ut.name_ver(),
+                            ut.target
+                        ),
+                    ));
+                }
+            }
+        }
+        for step in critical_path {
+            let ut = &self.unit_times[step.index];
+            if !ut.unit.target.is_lib()
+                || step.time < total * CRITICAL_PATH_SPLIT_SHARE
+                || step.time < CRITICAL_PATH_MIN_SAVING
+            {
+                continue;
+            }
+            savings.push((
+                step.time,
+                format!(
+                    "{}{} takes {:.0}% of the critical path. Splitting it into smaller \
+                     crates would let more of it build in parallel.",
+                    ut.name_ver(),
+                    ut.target,
+                    step.time / total * 100.0
+                ),
+            ));
+        }
+        savings.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        suggestions.extend(savings.into_iter().map(|(_, s)| s));
+        suggestions
+    }
+
+    /// Save the JSON report to disk.
+    fn report_json(
+        &self,
+        bcx: &BuildContext<'_, '_>,
+        error: &Option<anyhow::Error>,
+    ) -> CargoResult<()> {
+        let round = |x: f64| (x * 100.0).round() / 100.0;
+        let units = self
+            .unit_times
+            .iter()
+            .map(|ut| UnitReport {
+                name: ut.unit.pkg.name().to_string(),
+                version: ut.unit.pkg.version().to_string(),
+                target: ut.target.trim_start().to_string(),
+                features: ut.unit.features.iter().map(|f| f.to_string()).collect(),
+                start: round(ut.start),
+                rmeta: ut.rmeta_time.map(|t| round(ut.start + t)),
+                end: round(ut.start + ut.duration),
+            })
+            .collect();
+        let report = TimingsReport {
+            version: TIMINGS_REPORT_VERSION,
+            start: self.start_str.clone(),
+            targets: self
+                .root_targets
+                .iter()
+                .map(|(name, targets)| format!("{} ({})", name, targets.join(", ")))
+                .collect(),
+            profile: self.profile.clone(),
+            rustc: rustc_version(bcx).to_string(),
+            jobs: bcx.build_config.jobs,
+            ncpu: num_cpus::get(),
+            duration: round(self.start.elapsed().as_secs_f64()),
+            fresh_units: self.total_fresh,
+            dirty_units: self.total_dirty,
+            error: error.as_ref().map(|e| e.to_string()),
+            units,
+            concurrency: self
+                .concurrency
+                .iter()
+                .map(|c| Concurrency {
+                    t: round(c.t),
+                    ..*c
+                })
+                .collect(),
+            cpu_usage: self
+                .cpu_usage
+                .iter()
+                .map(|(t, usage)| (round(*t), round(*usage)))
+                .collect(),
+        };
+        let timestamp = self.start_str.replace(&['-', ':'][..], "");
+        let filename = format!("cargo-timing-{}.json", timestamp);
+        paths::write(&filename, serde_json::to_string_pretty(&report)?)?;
+        paths::link_or_copy(&filename, "cargo-timing.json")?;
+        let msg = format!(
+            "report saved to {}",
+            std::env::current_dir()
+                .unwrap_or_default()
+                .join(&filename)
+                .display()
+        );
+        self.config
+            .shell()
+            .status_with_color("Timing", msg, termcolor::Color::Cyan)?;
         Ok(())
     }
 
@@ -343,10 +1022,12 @@ impl<'cfg> Timings<'cfg> {
             .iter()
             .map(|(name, _targets)| name.as_str())
             .collect();
+        let critical_path = self.critical_path();
         f.write_all(HTML_TMPL.replace("{ROOTS}", &roots.join(", ")).as_bytes())?;
-        self.write_summary_table(&mut f, duration, bcx, error)?;
+        self.write_summary_table(&mut f, duration, bcx, error, &critical_path)?;
         f.write_all(HTML_CANVAS.as_bytes())?;
-        self.write_unit_table(&mut f)?;
+        self.write_critical_path(&mut f, bcx, &critical_path)?;
+        self.write_unit_table(&mut f, &critical_path)?;
         // It helps with pixel alignment to use whole numbers.
         writeln!(
             f,
@@ -354,7 +1035,7 @@ impl<'cfg> Timings<'cfg> {
              DURATION = {};",
             f64::ceil(duration) as u32
         )?;
-        self.write_js_data(&mut f)?;
+        self.write_js_data(&mut f, &critical_path)?;
         write!(
             f,
             "{}\n\
@@ -386,6 +1067,7 @@ impl<'cfg> Timings<'cfg> {
         duration: f64,
         bcx: &BuildContext<'_, '_>,
         error: &Option<anyhow::Error>,
+        critical_path: &[CriticalPathStep],
     ) -> CargoResult<()> {
         let targets: Vec<String> = self
             .root_targets
@@ -418,6 +1100,29 @@ impl<'cfg> Timings<'cfg> {
             ),
             None => "".to_string(),
         };
+        let build_cache = if self.build_cache.is_empty() {
+            "".to_string()
+        } else {
+            let (hits, misses) = self.build_cache_hits();
+            format!(
+                r#"
+  <tr>
+    <td>Build cache:</td><td>{} hits, {} misses</td>
+  </tr>"#,
+                hits, misses
+            )
+        };
+        let throttled = if self.throttled.is_empty() {
+            "".to_string()
+        } else {
+            format!(
+                r#"
+  <tr>
+    <td>Throttled:</td><td>{}</td>
+  </tr>"#,
+                self.throttled_lines().join("<br>")
+            )
+        };
         write!(
             f,
             r#"
@@ -436,16 +1141,19 @@ impl<'cfg> Timings<'cfg> {
   </tr>
   <tr>
     <td>Total units:</td><td>{}</td>
-  </tr>
+  </tr>{}
   <tr>
     <td>Max concurrency:</td><td>{} (jobs={} ncpu={})</td>
-  </tr>
+  </tr>{}
   <tr>
     <td>Build start:</td><td>{}</td>
   </tr>
   <tr>
     <td>Total time:</td><td>{}</td>
   </tr>
+  <tr>
+    <td>Critical path:</td><td>{:.1}s ({} units)</td>
+  </tr>
   <tr>
     <td>rustc:</td><td>{}</td>
   </tr>
@@ -460,11 +1168,15 @@ impl<'cfg> Timings<'cfg> {
             self.total_fresh,
             self.total_dirty,
             self.total_fresh + self.total_dirty,
+            build_cache,
             max_concurrency,
             bcx.build_config.jobs,
             num_cpus::get(),
+            throttled,
             self.start_str,
             total_time,
+            critical_path.iter().map(|step| step.time).sum::<f64>(),
+            critical_path.len(),
             rustc_info,
             max_rustc_concurrency,
             error_msg,
@@ -472,7 +1184,11 @@ impl<'cfg> Timings<'cfg> {
         Ok(())
     }
 
-    fn write_js_data(&self, f: &mut impl Write) -> CargoResult<()> {
+    fn write_js_data(
+        &self,
+        f: &mut impl Write,
+        critical_path: &[CriticalPathStep],
+    ) -> CargoResult<()> {
         // Create a map to link indices of unlocked units.
         let unit_map: HashMap<Unit, usize> = self
             .unit_times
@@ -492,6 +1208,7 @@ impl<'cfg> Timings<'cfg> {
             rmeta_time: Option<f64>,
             unlocked_units: Vec<usize>,
             unlocked_rmeta_units: Vec<usize>,
+            critical_path: bool,
         }
         let round = |x: f64| (x * 100.0).round() / 100.0;
         let unit_data: Vec<UnitData> = self
@@ -530,6 +1247,7 @@ impl<'cfg> Timings<'cfg> {
                     rmeta_time: ut.rmeta_time.map(|t| round(t)),
                     unlocked_units,
                     unlocked_rmeta_units,
+                    critical_path: critical_path.iter().any(|step| step.index == i),
                 }
             })
             .collect();
@@ -552,7 +1270,11 @@ impl<'cfg> Timings<'cfg> {
     }
 
     /// Render the table of all units.
-    fn write_unit_table(&self, f: &mut impl Write) -> CargoResult<()> {
+    fn write_unit_table(
+        &self,
+        f: &mut impl Write,
+        critical_path: &[CriticalPathStep],
+    ) -> CargoResult<()> {
         write!(
             f,
             r#"
@@ -563,36 +1285,44 @@ impl<'cfg> Timings<'cfg> {
       <th>Unit</th>
       <th>Total</th>
       <th>Codegen</th>
+      <th>Critical path</th>
       <th>Features</th>
     </tr>
   </thead>
   <tbody>
 "#
         )?;
-        let mut units: Vec<&UnitTime> = self.unit_times.iter().collect();
-        units.sort_unstable_by(|a, b| b.duration.partial_cmp(&a.duration).unwrap());
-        for (i, unit) in units.iter().enumerate() {
+        let mut units: Vec<(usize, &UnitTime)> = self.unit_times.iter().enumerate().collect();
+        units.sort_unstable_by(|a, b| b.1.duration.partial_cmp(&a.1.duration).unwrap());
+        for (i, (index, unit)) in units.iter().enumerate() {
             let codegen = match unit.codegen_time() {
                 None => "".to_string(),
                 Some((_rt, ctime, cent)) => format!("{:.1}s ({:.0}%)", ctime, cent),
             };
+            let (class, critical) = match critical_path.iter().find(|step| step.index == *index) {
+                Some(step) => (" class=\"critical\"", format!("{:.1}s", step.time)),
+                None => ("", "".to_string()),
+            };
             let features = unit.unit.features.join(", ");
             write!(
                 f,
                 r#"
-<tr>
+<tr{}>
   <td>{}.</td>
   <td>{}{}</td>
   <td>{:.1}s</td>
   <td>{}</td>
   <td>{}</td>
+  <td>{}</td>
 </tr>
 "#,
+                class,
                 i + 1,
                 unit.name_ver(),
                 unit.target,
                 unit.duration,
                 codegen,
+                critical,
                 features,
             )?;
         }
@@ -616,13 +1346,49 @@ impl UnitTime {
     }
 }
 
-fn render_rustc_info(bcx: &BuildContext<'_, '_>) -> String {
-    let version = bcx
-        .rustc()
+impl UnitReport {
+    fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+
+    fn same_unit(&self, other: &UnitReport) -> bool {
+        self.name == other.name && self.version == other.version && self.target == other.target
+    }
+}
+
+/// Describes the target and mode of `unit` for display next to its package,
+/// for example ` bin "foo" (test)`.
+pub fn target_description(unit: &Unit) -> String {
+    let mut target = if unit.target.is_lib() && unit.mode == CompileMode::Build {
+        // Special case for brevity, since most dependencies hit
+        // this path.
+        "".to_string()
+    } else {
+        format!(" {}", unit.target.description_named())
+    };
+    match unit.mode {
+        CompileMode::Test => target.push_str(" (test)"),
+        CompileMode::Build => {}
+        CompileMode::Check { test: true } => target.push_str(" (check-test)"),
+        CompileMode::Check { test: false } => target.push_str(" (check)"),
+        CompileMode::Bench => target.push_str(" (bench)"),
+        CompileMode::Doc { .. } => target.push_str(" (doc)"),
+        CompileMode::Doctest => target.push_str(" (doc test)"),
+        CompileMode::RunCustomBuild => target.push_str(" (run)"),
+    }
+    target
+}
+
+fn rustc_version<'a>(bcx: &'a BuildContext<'_, '_>) -> &'a str {
+    bcx.rustc()
         .verbose_version
         .lines()
         .next()
-        .expect("rustc version");
+        .expect("rustc version")
+}
+
+fn render_rustc_info(bcx: &BuildContext<'_, '_>) -> String {
+    let version = rustc_version(bcx);
     let requested_target = bcx
         .build_config
         .requested_kinds
@@ -744,6 +1510,18 @@ h1 {
   color: #e80000;
 }
 
+.my-table tr.critical td {
+  font-weight: bold;
+}
+
+.my-table tr.regression td {
+  background: #ffd5d5;
+}
+
+.my-table tr.improvement td {
+  background: #d5f5d5;
+}
+
 </style>
 </head>
 <body>
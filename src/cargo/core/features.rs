@@ -346,6 +346,13 @@ pub struct CliUnstable {
     pub config_include: bool,
     pub dual_proc_macros: bool,
     pub mtime_on_use: bool,
+    pub checksum_freshness: bool,
+    pub build_cache: bool,
+    pub build_script_sandbox: bool,
+    pub build_script_cache: bool,
+    pub job_limits: bool,
+    pub sparse_registry: bool,
+    pub next_lockfile_bump: bool,
     pub named_profiles: bool,
     pub binary_dep_depinfo: bool,
     #[serde(deserialize_with = "deserialize_build_std")]
@@ -453,6 +460,13 @@ impl CliUnstable {
             "dual-proc-macros" => self.dual_proc_macros = parse_empty(k, v)?,
             // can also be set in .cargo/config or with and ENV
             "mtime-on-use" => self.mtime_on_use = parse_empty(k, v)?,
+            "checksum-freshness" => self.checksum_freshness = parse_empty(k, v)?,
+            "build-cache" => self.build_cache = parse_empty(k, v)?,
+            "build-script-sandbox" => self.build_script_sandbox = parse_empty(k, v)?,
+            "build-script-cache" => self.build_script_cache = parse_empty(k, v)?,
+            "job-limits" => self.job_limits = parse_empty(k, v)?,
+            "sparse-registry" => self.sparse_registry = parse_empty(k, v)?,
+            "next-lockfile-bump" => self.next_lockfile_bump = parse_empty(k, v)?,
             "named-profiles" => self.named_profiles = parse_empty(k, v)?,
             "binary-dep-depinfo" => self.binary_dep_depinfo = parse_empty(k, v)?,
             "build-std" => {
@@ -480,18 +494,21 @@ impl CliUnstable {
 
     /// Generates an error if `-Z unstable-options` was not used.
     /// Intended to be used when a user passes a command-line flag that
-    /// requires `-Z unstable-options`.
-    pub fn fail_if_stable_opt(&self, flag: &str, issue: u32) -> CargoResult<()> {
+    /// requires `-Z unstable-options`. `issue` is the flag's tracking issue,
+    /// if it has one.
+    pub fn fail_if_stable_opt(&self, flag: &str, issue: impl Into<Option<u32>>) -> CargoResult<()> {
         if !self.unstable_options {
-            let see = format!(
-                "See https://github.com/rust-lang/cargo/issues/{} for more \
-                 information about the `{}` flag.",
-                issue, flag
-            );
+            let see = match issue.into() {
+                Some(issue) => format!(
+                    "\nSee https://github.com/rust-lang/cargo/issues/{} for more \
+                     information about the `{}` flag.",
+                    issue, flag
+                ),
+                None => String::new(),
+            };
             if nightly_features_allowed() {
                 bail!(
-                    "the `{}` flag is unstable, pass `-Z unstable-options` to enable it\n\
-                     {}",
+                    "the `{}` flag is unstable, pass `-Z unstable-options` to enable it{}",
                     flag,
                     see
                 );
@@ -499,8 +516,7 @@ impl CliUnstable {
                 bail!(
                     "the `{}` flag is unstable, and only available on the nightly channel \
                      of Cargo, but this is the `{}` channel\n\
-                     {}\n\
-                     {}",
+                     {}{}",
                     flag,
                     channel(),
                     SEE_CHANNELS,
@@ -510,6 +526,29 @@ impl CliUnstable {
         }
         Ok(())
     }
+
+    /// Generates an error if `-Z unstable-options` was not used.
+    /// Intended to be used by subcommands that are entirely unstable.
+    pub fn fail_if_stable_command(&self, command: &str) -> CargoResult<()> {
+        if !self.unstable_options {
+            if nightly_features_allowed() {
+                bail!(
+                    "the `cargo {}` command is unstable, pass `-Z unstable-options` to enable it",
+                    command
+                );
+            } else {
+                bail!(
+                    "the `cargo {}` command is unstable, and only available on the nightly \
+                     channel of Cargo, but this is the `{}` channel\n\
+                     {}",
+                    command,
+                    channel(),
+                    SEE_CHANNELS
+                );
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Returns the current release channel ("stable", "beta", "nightly", "dev").
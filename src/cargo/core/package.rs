@@ -151,6 +151,13 @@ impl Package {
         self.package_id().registry_safe_file_name()
     }
 
+    /// Gets the name of the `.crate` file for this package.
+    ///
+    /// See `PackageId::crate_file_name`.
+    pub fn crate_file_name(&self) -> String {
+        self.package_id().crate_file_name()
+    }
+
     pub fn rust_code_safe_name(&self) -> InternedString {
         let i_str = self
             .package_id()
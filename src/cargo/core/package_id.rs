@@ -158,6 +158,21 @@ impl PackageId {
         i_str.into()
     }
 
+    /// The name of the `.crate` file for this package, such as
+    /// `foo~bar-0.1.0.crate` for `foo/bar` 0.1.0.
+    ///
+    /// This is the one mapping from packages to `.crate` files. It names the
+    /// file written by `cargo package`, the files in a local registry and the
+    /// registry download cache, and the `{crate}` marker of a registry's
+    /// download URL is replaced with the same `registry_safe_file_name`.
+    pub fn crate_file_name(self) -> String {
+        format!(
+            "{}-{}.crate",
+            self.registry_safe_file_name(),
+            self.version()
+        )
+    }
+
     pub fn version(self) -> &'static semver::Version {
         &self.inner.version
     }
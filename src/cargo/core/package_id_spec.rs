@@ -5,10 +5,11 @@ use semver::Version;
 use serde::{de, ser};
 use url::Url;
 
+use crate::core::manifest::SUBCRATE_DELIMETER;
 use crate::core::PackageId;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::interning::InternedString;
-use crate::util::{validate_package_name, IntoUrl, ToSemver};
+use crate::util::{closest_msg, validate_package_name, IntoUrl, ToSemver};
 
 /// Some or all of the data required to identify a package:
 ///
@@ -19,6 +20,11 @@ use crate::util::{validate_package_name, IntoUrl, ToSemver};
 /// If any of the optional fields are omitted, then the package ID may be ambiguous, there may be
 /// more than one package/version/url combo that will match. However, often just the name is
 /// sufficient to uniquely define a package ID.
+///
+/// Namespaced package names (like `foo/bar`) contain the subcrate delimiter,
+/// which is also the URL path separator. A spec without a scheme is only
+/// treated as a URL if the part before the version is not a valid package
+/// name, so `foo/bar:1.2.3` names a package while `crates.io/foo` is a URL.
 #[derive(Clone, PartialEq, Eq, Debug, Hash, Ord, PartialOrd)]
 pub struct PackageIdSpec {
     name: InternedString,
@@ -44,12 +50,16 @@ impl PackageIdSpec {
     ///     "crates.io/foo#bar:1.2.3",
     ///     "foo",
     ///     "foo:1.2.3",
+    ///     "foo@1.2.3",
+    ///     "foo/bar",
+    ///     "foo/bar:1.2.3",
+    ///     "registry+https://crates.io/foo#foo/bar@1.2.3",
     /// ];
     /// for spec in specs {
     ///     assert!(PackageIdSpec::parse(spec).is_ok());
     /// }
     pub fn parse(spec: &str) -> CargoResult<PackageIdSpec> {
-        if spec.contains('/') {
+        if spec.contains('/') && !is_namespaced_name_spec(spec) {
             if let Ok(url) = spec.into_url() {
                 return PackageIdSpec::from_url(url);
             }
@@ -59,9 +69,8 @@ impl PackageIdSpec {
                 }
             }
         }
-        let mut parts = spec.splitn(2, ':');
-        let name = parts.next().unwrap();
-        let version = match parts.next() {
+        let (name, version) = split_name_version(spec);
+        let version = match version {
             Some(version) => Some(version.to_semver()?),
             None => None,
         };
@@ -94,7 +103,15 @@ impl PackageIdSpec {
     }
 
     /// Tries to convert a valid `Url` to a `PackageIdSpec`.
+    ///
+    /// A source kind prefix on the scheme (`registry+`, `git+` or `path+`)
+    /// is accepted and stripped, since `SourceId` URLs are stored without it.
     fn from_url(mut url: Url) -> CargoResult<PackageIdSpec> {
+        if let Some(i) = url.scheme().find('+') {
+            if ["registry", "git", "path"].contains(&&url.scheme()[..i]) {
+                url = url.as_str()[i + 1..].into_url()?;
+            }
+        }
         if url.query().is_some() {
             anyhow::bail!("cannot have a query string in a pkgid: {}", url)
         }
@@ -113,9 +130,8 @@ impl PackageIdSpec {
             })?;
             match frag {
                 Some(fragment) => {
-                    let mut parts = fragment.splitn(2, ':');
-                    let name_or_version = parts.next().unwrap();
-                    match parts.next() {
+                    let (name_or_version, version) = split_name_version(&fragment);
+                    match version {
                         Some(part) => {
                             let version = part.to_semver()?;
                             (InternedString::new(name_or_version), Some(version))
@@ -180,13 +196,15 @@ impl PackageIdSpec {
     where
         I: IntoIterator<Item = PackageId>,
     {
-        let mut ids = i.into_iter().filter(|p| self.matches(*p));
+        let all_ids: Vec<_> = i.into_iter().collect();
+        let mut ids = all_ids.iter().cloned().filter(|p| self.matches(*p));
         let ret = match ids.next() {
             Some(id) => id,
             None => anyhow::bail!(
                 "package ID specification `{}` \
-                 matched no packages",
-                self
+                 matched no packages{}",
+                self,
+                namespace_suggestion(self, &all_ids)
             ),
         };
         return match ids.next() {
@@ -225,6 +243,56 @@ impl PackageIdSpec {
     }
 }
 
+/// Splits a spec (or URL fragment) of the form `name:version` or
+/// `name@version` into its name and optional version.
+fn split_name_version(spec: &str) -> (&str, Option<&str>) {
+    match spec.find(|c| c == ':' || c == '@') {
+        Some(i) => (&spec[..i], Some(&spec[i + 1..])),
+        None => (spec, None),
+    }
+}
+
+/// Returns `true` if a spec containing a `/` should be read as a namespaced
+/// package name (like `foo/bar` or `foo/bar:1.2.3`) rather than as a URL.
+fn is_namespaced_name_spec(spec: &str) -> bool {
+    if spec.contains("://") {
+        return false;
+    }
+    let (name, _) = split_name_version(spec);
+    name.contains(SUBCRATE_DELIMETER) && validate_package_name(name, "pkgid", "").is_ok()
+}
+
+/// Builds a "did you mean" hint for a spec that matched nothing, when the
+/// mismatch looks like it involves a namespace: the spec names only the last
+/// component of a namespaced package, the namespace delimiter was written as
+/// `_` or `-`, or the spec is a misspelled namespaced name.
+fn namespace_suggestion(spec: &PackageIdSpec, ids: &[PackageId]) -> String {
+    let flatten = |name: &str| name.replace(SUBCRATE_DELIMETER, "_").replace('-', "_");
+    let spec_name = spec.name().as_str();
+    let mut names: Vec<&str> = ids
+        .iter()
+        .map(|id| id.name().as_str())
+        .filter(|name| *name != spec_name)
+        .filter(|name| name.contains(SUBCRATE_DELIMETER) || spec_name.contains(SUBCRATE_DELIMETER))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let exact: Vec<&str> = names
+        .iter()
+        .cloned()
+        .filter(|name| {
+            name.rsplit(SUBCRATE_DELIMETER).next() == Some(spec_name)
+                || flatten(name) == flatten(spec_name)
+        })
+        .collect();
+    if exact.is_empty() {
+        return closest_msg(spec_name, names.into_iter(), |name| name);
+    }
+    let exact: Vec<_> = exact.iter().map(|name| format!("`{}`", name)).collect();
+    format!("\n\n\tDid you mean {}?", exact.join(" or "))
+}
+
 impl fmt::Display for PackageIdSpec {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut printed_name = false;
@@ -351,6 +419,63 @@ mod tests {
                 url: None,
             },
         );
+        ok(
+            "foo/bar",
+            PackageIdSpec {
+                name: InternedString::new("foo/bar"),
+                version: None,
+                url: None,
+            },
+        );
+        ok(
+            "foo/bar:1.2.3",
+            PackageIdSpec {
+                name: InternedString::new("foo/bar"),
+                version: Some("1.2.3".to_semver().unwrap()),
+                url: None,
+            },
+        );
+        ok(
+            "https://crates.io/foo#foo/bar:1.2.3",
+            PackageIdSpec {
+                name: InternedString::new("foo/bar"),
+                version: Some("1.2.3".to_semver().unwrap()),
+                url: Some(Url::parse("https://crates.io/foo").unwrap()),
+            },
+        );
+    }
+
+    #[test]
+    fn alternate_parsing() {
+        fn same(spec: &str, canonical: &str) {
+            assert_eq!(
+                PackageIdSpec::parse(spec).unwrap(),
+                PackageIdSpec::parse(canonical).unwrap()
+            );
+        }
+
+        same("foo@1.2.3", "foo:1.2.3");
+        same("foo/bar@1.2.3", "foo/bar:1.2.3");
+        same(
+            "registry+https://crates.io/foo#foo/bar@1.2.3",
+            "https://crates.io/foo#foo/bar:1.2.3",
+        );
+        same(
+            "git+https://crates.io/foo#1.2.3",
+            "https://crates.io/foo#1.2.3",
+        );
+    }
+
+    #[test]
+    fn namespaced_round_trip() {
+        let url = Url::parse("https://example.com").unwrap();
+        let sid = SourceId::for_registry(&url).unwrap();
+        let foo_bar = PackageId::new("foo/bar", "1.2.3", sid).unwrap();
+
+        let spec = PackageIdSpec::from_package_id(foo_bar);
+        let parsed = PackageIdSpec::parse(&spec.to_string()).unwrap();
+        assert_eq!(parsed, spec);
+        assert!(parsed.matches(foo_bar));
     }
 
     #[test]
@@ -374,4 +499,22 @@ mod tests {
         assert!(PackageIdSpec::parse("foo:1.2.3").unwrap().matches(foo));
         assert!(!PackageIdSpec::parse("foo:1.2.2").unwrap().matches(foo));
     }
+
+    #[test]
+    fn namespaced_matching() {
+        let url = Url::parse("https://example.com").unwrap();
+        let sid = SourceId::for_registry(&url).unwrap();
+        let foo_bar = PackageId::new("foo/bar", "1.2.3", sid).unwrap();
+        let bar = PackageId::new("bar", "1.2.3", sid).unwrap();
+
+        assert!(PackageIdSpec::parse("foo/bar").unwrap().matches(foo_bar));
+        assert!(!PackageIdSpec::parse("foo/bar").unwrap().matches(bar));
+        assert!(!PackageIdSpec::parse("bar").unwrap().matches(foo_bar));
+
+        let err = PackageIdSpec::parse("foo_bar")
+            .unwrap()
+            .query(vec![foo_bar, bar])
+            .unwrap_err();
+        assert!(err.to_string().contains("Did you mean `foo/bar`?"));
+    }
 }
@@ -57,6 +57,14 @@
 //! Listed from most recent to oldest, these are some of the changes we've made
 //! to `Cargo.lock`'s serialization format:
 //!
+//! * Package names in `dependencies` and `replace` entries are now
+//!   percent-encoded, so a namespaced name like `foo/bar` is written as
+//!   `foo%2Fbar` and can't be mistaken for part of the version or source that
+//!   may follow it. Packages from alternative registries also record the name
+//!   the registry was configured with in a `registry` key next to the
+//!   `source` URL. This is `version = 4` and is currently only written with
+//!   `-Z next-lockfile-bump`.
+//!
 //! * A `version` marker is now at the top of the lock file which is a way for
 //!   super-old Cargos (at least since this was implemented) to give a formal
 //!   error if they see a lock file from a super-future Cargo. Additionally as
@@ -118,9 +126,11 @@ use crate::util::interning::InternedString;
 use crate::util::{internal, Graph};
 use anyhow::bail;
 use log::debug;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
 use serde::de;
 use serde::ser;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::str::FromStr;
@@ -144,6 +154,10 @@ struct Patch {
 
 pub type Metadata = BTreeMap<String, String>;
 
+/// Characters of a package name that are percent-encoded in V4 package
+/// references, which are of the form `name version (source)`.
+const NAME_ESCAPES: &AsciiSet = &CONTROLS.add(b' ').add(b'%').add(b'(').add(b')').add(b'/');
+
 impl EncodableResolve {
     /// Convert a `Cargo.lock` to a Resolve.
     ///
@@ -159,6 +173,7 @@ impl EncodableResolve {
 
         let mut version = match self.version {
             Some(3) => ResolveVersion::V3,
+            Some(4) => ResolveVersion::V4,
             Some(n) => bail!(
                 "lock file version `{}` was found, but this version of Cargo \
                  does not understand this lock file, perhaps Cargo needs \
@@ -194,7 +209,11 @@ impl EncodableResolve {
                 if !all_pkgs.insert(enc_id.clone()) {
                     anyhow::bail!("package `{}` is specified twice in the lockfile", pkg.name);
                 }
-                let id = match pkg.source.as_ref().or_else(|| path_deps.get(&pkg.name)) {
+                let id = match pkg
+                    .source_id()
+                    .as_ref()
+                    .or_else(|| path_deps.get(&pkg.name))
+                {
                     // We failed to find a local package in the workspace.
                     // It must have been removed and should be ignored.
                     None => {
@@ -233,8 +252,13 @@ impl EncodableResolve {
 
         let mut lookup_id = |enc_id: &EncodablePackageId| -> Option<PackageId> {
             // The name of this package should always be in the larger list of
-            // all packages.
-            let by_version = map.get(enc_id.name.as_str())?;
+            // all packages. Starting with V4 the name is percent-encoded.
+            let name = if version >= ResolveVersion::V4 {
+                percent_decode_str(&enc_id.name).decode_utf8().ok()?
+            } else {
+                Cow::Borrowed(enc_id.name.as_str())
+            };
+            let by_version = map.get(&*name)?;
 
             // If the version is provided, look that up. Otherwise if the
             // version isn't provided this is a V2 manifest and we should only
@@ -358,7 +382,11 @@ impl EncodableResolve {
 
         let mut unused_patches = Vec::new();
         for pkg in self.patch.unused {
-            let id = match pkg.source.as_ref().or_else(|| path_deps.get(&pkg.name)) {
+            let id = match pkg
+                .source_id()
+                .as_ref()
+                .or_else(|| path_deps.get(&pkg.name))
+            {
                 Some(&src) => PackageId::new(&pkg.name, &pkg.version, src)?,
                 None => continue,
             };
@@ -484,11 +512,27 @@ pub struct EncodableDependency {
     name: String,
     version: String,
     source: Option<SourceId>,
+    /// The name of the alternative registry `source` was configured as,
+    /// recorded since V4.
+    registry: Option<String>,
     checksum: Option<String>,
     dependencies: Option<Vec<EncodablePackageId>>,
     replace: Option<EncodablePackageId>,
 }
 
+impl EncodableDependency {
+    /// The `source` of this package, along with the name of the alternative
+    /// registry it came from if one was recorded.
+    fn source_id(&self) -> Option<SourceId> {
+        match (self.source, &self.registry) {
+            (Some(source), Some(registry)) if source.is_registry() => {
+                Some(source.with_alt_registry_key(registry))
+            }
+            (source, _) => source,
+        }
+    }
+}
+
 #[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone)]
 pub struct EncodablePackageId {
     name: String,
@@ -599,6 +643,7 @@ impl<'a> ser::Serialize for Resolve {
                     name: id.name().to_string(),
                     version: id.version().to_string(),
                     source: encode_source(id.source_id()),
+                    registry: encode_registry(id.source_id(), self.version()),
                     dependencies: None,
                     replace: None,
                     checksum: if self.version() >= ResolveVersion::V2 {
@@ -615,6 +660,7 @@ impl<'a> ser::Serialize for Resolve {
             metadata,
             patch,
             version: match self.version() {
+                ResolveVersion::V4 => Some(4),
                 ResolveVersion::V3 => Some(3),
                 ResolveVersion::V2 | ResolveVersion::V1 => None,
             },
@@ -671,6 +717,7 @@ fn encodable_resolve_node(
         name: id.name().to_string(),
         version: id.version().to_string(),
         source: encode_source(id.source_id()),
+        registry: encode_registry(id.source_id(), resolve.version()),
         dependencies: deps,
         replace,
         checksum: if resolve.version() >= ResolveVersion::V2 {
@@ -706,8 +753,13 @@ pub fn encodable_package_id(
             }
         }
     }
+    let name = if resolve_version >= ResolveVersion::V4 {
+        utf8_percent_encode(&id.name(), NAME_ESCAPES).to_string()
+    } else {
+        id.name().to_string()
+    };
     EncodablePackageId {
-        name: id.name().to_string(),
+        name,
         version,
         source,
     }
@@ -720,3 +772,11 @@ fn encode_source(id: SourceId) -> Option<SourceId> {
         Some(id)
     }
 }
+
+fn encode_registry(id: SourceId, resolve_version: ResolveVersion) -> Option<String> {
+    if resolve_version >= ResolveVersion::V4 {
+        id.alt_registry_key().map(|key| key.to_string())
+    } else {
+        None
+    }
+}
@@ -1,9 +1,11 @@
 use std::fmt;
 
 use crate::core::{Dependency, PackageId, Registry, Summary};
+use crate::util::interning::InternedString;
 use crate::util::lev_distance::lev_distance;
 use crate::util::Config;
 use anyhow::Error;
+use serde::Serialize;
 
 use super::context::Context;
 use super::types::{ConflictMap, ConflictReason};
@@ -12,6 +14,7 @@ use super::types::{ConflictMap, ConflictReason};
 pub struct ResolveError {
     cause: Error,
     package_path: Vec<PackageId>,
+    conflict: Option<Box<ConflictReport>>,
 }
 
 impl ResolveError {
@@ -19,6 +22,7 @@ impl ResolveError {
         Self {
             cause: cause.into(),
             package_path,
+            conflict: None,
         }
     }
 
@@ -27,6 +31,201 @@ impl ResolveError {
     pub fn package_path(&self) -> &[PackageId] {
         &self.package_path
     }
+
+    /// Returns the conflicts that prevented the dependency from resolving, if
+    /// there were candidates for it at all.
+    pub fn conflict(&self) -> Option<&ConflictReport> {
+        self.conflict.as_deref()
+    }
+}
+
+/// A structured record of why no candidate for a dependency could be
+/// activated.
+#[derive(Debug, Serialize)]
+pub struct ConflictReport {
+    /// The name of the dependency that couldn't be resolved.
+    pub name: InternedString,
+    /// The requirement that couldn't be resolved, along with the full path
+    /// of packages that led to it.
+    pub requirement: Requirement,
+    /// The versions meeting the requirement, newest first.
+    pub candidates: Vec<String>,
+    /// The previously selected packages the candidates conflict with, most
+    /// recently selected first.
+    pub conflicts: Vec<Conflict>,
+}
+
+/// A version requirement on the dependency a `ConflictReport` is about.
+#[derive(Debug, Serialize)]
+pub struct Requirement {
+    /// The package that declared the requirement.
+    pub package_id: PackageId,
+    /// The version requirement, as in `Cargo.toml`.
+    pub req: String,
+    /// The packages that led to `package_id`, nearest first.
+    pub path: Vec<PackageId>,
+}
+
+/// One reason a `ConflictReport`'s candidates couldn't be activated.
+#[derive(Debug, Serialize)]
+pub struct Conflict {
+    /// The previously selected package that conflicts with the candidates.
+    pub package_id: PackageId,
+    pub reason: ConflictReason,
+    /// The failing requirement, with its path cut off at the package closest
+    /// to it that also led to `package_id`.
+    pub requirement: Requirement,
+    /// The requirement that selected `package_id`, with its path cut off the
+    /// same way. This is `None` if `package_id` isn't a dependency of another
+    /// package.
+    pub selected_by: Option<Requirement>,
+}
+
+impl ConflictReport {
+    fn new(
+        cx: &Context,
+        parent: &Summary,
+        dep: &Dependency,
+        conflicting_activations: &[(&PackageId, &ConflictReason)],
+        candidates: &[Summary],
+    ) -> ConflictReport {
+        let parent_id = parent.package_id();
+        let path = cx.parents.path_to_bottom(&parent_id);
+        let conflicts = conflicting_activations
+            .iter()
+            .map(|&(&p, reason)| {
+                let selected_path = cx.parents.path_to_bottom(&p);
+                let (req_path, selected_path) = minimize_paths(&path, &selected_path[1..]);
+                let selected_by = selected_path.split_first().map(|(&by, rest)| {
+                    let req = cx
+                        .parents
+                        .edge(&p, &by)
+                        .and_then(|deps| deps.iter().find(|d| d.package_name() == p.name()))
+                        .map(|d| d.version_req().to_string())
+                        .unwrap_or_default();
+                    Requirement {
+                        package_id: by,
+                        req,
+                        path: rest.to_vec(),
+                    }
+                });
+                Conflict {
+                    package_id: p,
+                    reason: reason.clone(),
+                    requirement: Requirement {
+                        package_id: parent.package_id(),
+                        req: dep.version_req().to_string(),
+                        path: req_path[1..].to_vec(),
+                    },
+                    selected_by,
+                }
+            })
+            .collect();
+        ConflictReport {
+            name: dep.package_name(),
+            requirement: Requirement {
+                package_id: parent.package_id(),
+                req: dep.version_req().to_string(),
+                path: path[1..].iter().map(|&&p| p).collect(),
+            },
+            candidates: candidates.iter().map(|s| s.version().to_string()).collect(),
+            conflicts,
+        }
+    }
+
+    /// Describes the semver conflicts as a chain of requirements, starting
+    /// from the two that clash up to the package that needs both of them.
+    pub fn explain(&self) -> String {
+        let mut explanations = Vec::new();
+        for conflict in &self.conflicts {
+            if conflict.reason != ConflictReason::Semver {
+                continue;
+            }
+            let req = &conflict.requirement;
+            let mut lines = vec![format!(
+                "`{}` depends on `{} {}`",
+                req.package_id, self.name, req.req
+            )];
+            match &conflict.selected_by {
+                Some(by) => {
+                    lines.push(format!(
+                        "`{}` depends on `{} {}`, which selected `{}`",
+                        by.package_id, self.name, by.req, conflict.package_id
+                    ));
+                    lines.push(format!(
+                        "only one semver-compatible version of `{}` can be selected, \
+                         so `{}` and `{}` conflict",
+                        self.name, req.package_id, by.package_id
+                    ));
+                    if let Some(common) = common_dependent(req, by) {
+                        lines.push(describe_common_dependent(common, req, by));
+                    }
+                }
+                None => lines.push(format!(
+                    "`{}` was already selected, and only one semver-compatible \
+                     version of `{}` can be selected",
+                    conflict.package_id, self.name
+                )),
+            }
+            explanations.push(lines.join("\n  "));
+        }
+        explanations.join("\n\n  ")
+    }
+}
+
+/// Cuts two paths to the root off at the first package they share, so only
+/// the part of the dependency graph that actually leads to a conflict is
+/// left. The shared package is kept at the end of both paths.
+fn minimize_paths(a: &[&PackageId], b: &[&PackageId]) -> (Vec<PackageId>, Vec<PackageId>) {
+    for (i, p) in a.iter().enumerate() {
+        if let Some(j) = b.iter().position(|q| q == p) {
+            return (
+                a[..=i].iter().map(|&&p| p).collect(),
+                b[..=j].iter().map(|&&p| p).collect(),
+            );
+        }
+    }
+    (
+        a.iter().map(|&&p| p).collect(),
+        b.iter().map(|&&p| p).collect(),
+    )
+}
+
+/// Returns the package that the minimized paths of `a` and `b` both lead to,
+/// if they lead anywhere.
+fn common_dependent(a: &Requirement, b: &Requirement) -> Option<PackageId> {
+    let end = |r: &Requirement| r.path.last().copied().unwrap_or(r.package_id);
+    if (!a.path.is_empty() || !b.path.is_empty()) && end(a) == end(b) {
+        Some(end(a))
+    } else {
+        None
+    }
+}
+
+fn describe_common_dependent(common: PackageId, a: &Requirement, b: &Requirement) -> String {
+    let describe = |r: &Requirement| {
+        let through = &r.path[..r.path.len() - 1];
+        if through.is_empty() {
+            format!("`{}`", r.package_id)
+        } else {
+            let through = through
+                .iter()
+                .map(|p| format!("`{}`", p))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("`{}` (through {})", r.package_id, through)
+        }
+    };
+    let deps = [a, b]
+        .iter()
+        .filter(|r| !r.path.is_empty())
+        .map(|r| describe(r))
+        .collect::<Vec<_>>();
+    if deps.len() == 2 {
+        format!("`{}` depends on both {} and {}", common, deps[0], deps[1])
+    } else {
+        format!("`{}` depends on {}", common, deps.join(""))
+    }
 }
 
 impl std::error::Error for ResolveError {
@@ -187,6 +386,8 @@ pub(super) fn activation_error(
             }
         }
 
+        let report = ConflictReport::new(cx, parent, dep, &conflicting_activations, candidates);
+
         if has_semver {
             // Group these errors together.
             msg.push_str("\n\nall possible versions conflict with previously selected packages.");
@@ -196,13 +397,17 @@ pub(super) fn activation_error(
                     msg.push_str(&describe_path(&cx.parents.path_to_bottom(p)));
                 }
             }
+            msg.push_str("\n\nthis conflict is explained by:\n  ");
+            msg.push_str(&report.explain());
         }
 
         msg.push_str("\n\nfailed to select a version for `");
         msg.push_str(&*dep.package_name());
         msg.push_str("` which could resolve this conflict");
 
-        return to_resolve_err(anyhow::format_err!("{}", msg));
+        let mut err = to_resolve_err(anyhow::format_err!("{}", msg));
+        err.conflict = Some(Box::new(report));
+        return err;
     }
 
     // We didn't actually find any candidates, so we need to
@@ -339,6 +544,33 @@ pub(super) fn activation_error(
                  without the offline flag.",
             );
         }
+        if let Ok(Some(max_age)) = config.registry_max_age() {
+            let skipped = config
+                .max_age_skipped()
+                .iter()
+                // Match on name and version only, the recorded ids may come
+                // from a source replacing the one `dep` asked for.
+                .filter(|(id, _)| {
+                    id.name() == dep.package_name() && dep.version_req().matches(id.version())
+                })
+                .map(|(id, pubtime)| {
+                    format!(
+                        "\n    {} v{} (published {})",
+                        id.name(),
+                        id.version(),
+                        humantime::format_rfc3339_seconds(*pubtime)
+                    )
+                })
+                .collect::<String>();
+            if !skipped.is_empty() {
+                msg.push_str(&format!(
+                    "\nthe following versions were skipped because they were published \
+                     less than {} ago (`registry.max-age`):{}",
+                    humantime::format_duration(max_age),
+                    skipped
+                ));
+            }
+        }
     }
 
     to_resolve_err(anyhow::format_err!("{}", msg))
@@ -73,6 +73,12 @@ pub enum ResolveVersion {
     /// `branch = "master"` are no longer encoded the same way as those without
     /// branch specifiers.
     V3,
+    /// Package names in `dependencies` and `replace` references are
+    /// percent-encoded so namespaced names like `foo/bar` can't be confused
+    /// with the rest of the reference, and packages from alternative
+    /// registries record the name of the registry in a `registry` key next
+    /// to their `source`.
+    V4,
 }
 
 impl Resolve {
@@ -316,11 +322,7 @@ unable to verify that `{0}` is the same as when the lockfile was generated
         let crate_name = to_target.rust_code_safe_name();
         let mut names = deps.iter().map(|d| {
             d.explicit_name_in_toml()
-                .map(|s| {
-                    s.as_str()
-                        .replace("-", "_")
-                        .replace(SUBCRATE_DELIMETER, SUBCRATE_DELIMETER_RUST_CODE_REPLACEMENT)
-                })
+                .map(|s| rust_code_safe(&s))
                 .unwrap_or_else(|| crate_name.clone())
         });
         let name = names.next().unwrap_or_else(|| crate_name.clone());
@@ -332,6 +334,40 @@ unable to verify that `{0}` is the same as when the lockfile was generated
                 to,
             );
         }
+
+        // A namespaced package and an ordinary one may flatten to the same
+        // Rust identifier (`foo/bar` and `foo_bar` are both `foo_bar`), in
+        // which case rustc can't tell the two `--extern` flags apart.
+        if from != to {
+            for (other, other_deps) in self.deps(from) {
+                if other.name() == to.name()
+                    || !(other.name().contains(SUBCRATE_DELIMETER)
+                        || to.name().contains(SUBCRATE_DELIMETER))
+                    || !other_deps
+                        .iter()
+                        .any(|od| deps.iter().any(|d| d.kind() == od.kind()))
+                {
+                    continue;
+                }
+                let other_names = other_deps.iter().map(|d| {
+                    d.explicit_name_in_toml()
+                        .map(|s| rust_code_safe(&s))
+                        .unwrap_or_else(|| rust_code_safe(&other.name()))
+                });
+                for other_name in other_names {
+                    anyhow::ensure!(
+                        other_name != name,
+                        "the crate `{}` depends on both `{}` and `{}`, which are both \
+                         named `{}` in Rust code\n\
+                         Rename one of the dependencies in `Cargo.toml` with the `package` key.",
+                        from,
+                        to,
+                        other,
+                        name,
+                    );
+                }
+            }
+        }
         Ok(name)
     }
 
@@ -371,6 +407,13 @@ unable to verify that `{0}` is the same as when the lockfile was generated
     }
 }
 
+/// Converts a package or dependency name into the identifier it is referred to
+/// by from Rust code.
+fn rust_code_safe(name: &str) -> String {
+    name.replace("-", "_")
+        .replace(SUBCRATE_DELIMETER, SUBCRATE_DELIMETER_RUST_CODE_REPLACEMENT)
+}
+
 impl PartialEq for Resolve {
     fn eq(&self, other: &Resolve) -> bool {
         macro_rules! compare {
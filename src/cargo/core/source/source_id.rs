@@ -262,6 +262,12 @@ impl SourceId {
         matches!(self.inner.kind, SourceKind::Registry)
     }
 
+    /// Returns `true` if this source is a sparse registry, whose index is
+    /// fetched file by file over HTTP rather than cloned with git.
+    pub fn is_sparse(self) -> bool {
+        self.inner.kind == SourceKind::Registry && self.inner.url.scheme().starts_with("sparse+")
+    }
+
     /// Returns `true` if this source from a Git repository.
     pub fn is_git(self) -> bool {
         matches!(self.inner.kind, SourceKind::Git(_))
@@ -283,11 +289,20 @@ impl SourceId {
                 };
                 Ok(Box::new(PathSource::new(&path, self, config)))
             }
-            SourceKind::Registry => Ok(Box::new(RegistrySource::remote(
-                self,
-                yanked_whitelist,
-                config,
-            ))),
+            SourceKind::Registry => {
+                if self.is_sparse() && !config.cli_unstable().sparse_registry {
+                    anyhow::bail!(
+                        "usage of sparse registries requires `-Z sparse-registry`, \
+                         found `{}`",
+                        self.url()
+                    );
+                }
+                Ok(Box::new(RegistrySource::remote(
+                    self,
+                    yanked_whitelist,
+                    config,
+                )))
+            }
             SourceKind::LocalRegistry => {
                 let path = match self.inner.url.to_file_path() {
                     Ok(p) => p,
@@ -331,6 +346,24 @@ impl SourceId {
         })
     }
 
+    /// Returns the name of the alternative registry this source was
+    /// configured as, if known.
+    pub fn alt_registry_key(self) -> Option<&'static str> {
+        if self.inner.kind != SourceKind::Registry || self.is_default_registry() {
+            return None;
+        }
+        self.inner.name.as_deref()
+    }
+
+    /// Creates a new `SourceId` from this registry source, recording that it
+    /// was configured as the alternative registry `key`.
+    pub fn with_alt_registry_key(self, key: &str) -> SourceId {
+        SourceId::wrap(SourceIdInner {
+            name: Some(key.to_string()),
+            ..(*self.inner).clone()
+        })
+    }
+
     /// Returns `true` if the remote registry is the standard <https://crates.io>.
     pub fn is_default_registry(self) -> bool {
         match self.inner.kind {
@@ -34,14 +34,15 @@ use crate::core::compiler::{CompileKind, CompileMode, CompileTarget, RustcTarget
 use crate::core::compiler::{DefaultExecutor, Executor, UnitInterner};
 use crate::core::profiles::{Profiles, UnitFor};
 use crate::core::resolver::features::{self, FeaturesFor, RequestedFeatures};
-use crate::core::resolver::{HasDevUnits, Resolve, ResolveOpts};
+use crate::core::resolver::{HasDevUnits, Resolve, ResolveError, ResolveOpts};
 use crate::core::{FeatureValue, Package, PackageSet, Shell, Summary, Target};
 use crate::core::{PackageId, PackageIdSpec, TargetKind, Workspace};
 use crate::ops;
 use crate::ops::resolve::WorkspaceResolve;
 use crate::util::config::Config;
+use crate::util::machine_message::Message;
 use crate::util::restricted_names::is_glob_pattern;
-use crate::util::{closest_msg, profile, CargoResult, StableHasher};
+use crate::util::{closest_msg, machine_message, profile, CargoResult, StableHasher};
 
 use anyhow::Context as _;
 
@@ -353,7 +354,16 @@ pub fn create_bcx<'a, 'cfg>(
         &specs,
         has_dev_units,
         crate::core::resolver::features::ForceAllTargets::No,
-    )?;
+    );
+    let resolve = match resolve {
+        Ok(resolve) => resolve,
+        Err(e) => {
+            if build_config.emit_json() {
+                emit_resolve_conflict(config, &e)?;
+            }
+            return Err(e);
+        }
+    };
     let WorkspaceResolve {
         mut pkg_set,
         workspace_resolve,
@@ -1455,3 +1465,21 @@ fn opt_patterns_and_names(
     }
     Ok((opt_patterns, opt_names))
 }
+
+/// Emits a "resolve-conflict" message if `err` failed because no candidate
+/// for a dependency could be activated.
+fn emit_resolve_conflict(config: &Config, err: &anyhow::Error) -> CargoResult<()> {
+    let resolve_err = err
+        .chain()
+        .filter_map(|e| e.downcast_ref::<ResolveError>())
+        .find(|e| e.conflict().is_some());
+    if let Some(resolve_err) = resolve_err {
+        let msg = machine_message::ResolveConflict {
+            report: resolve_err.conflict().unwrap(),
+            rendered: resolve_err.to_string(),
+        }
+        .to_json_string();
+        writeln!(config.shell().out(), "{}", msg)?;
+    }
+    Ok(())
+}
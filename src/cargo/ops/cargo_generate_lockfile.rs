@@ -1,6 +1,8 @@
 use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
 
 use log::debug;
+use serde::Serialize;
 use termcolor::Color::{self, Cyan, Green, Red};
 
 use crate::core::registry::PackageRegistry;
@@ -9,6 +11,7 @@ use crate::core::{PackageId, PackageIdSpec};
 use crate::core::{Resolve, SourceId, Workspace};
 use crate::ops;
 use crate::util::config::Config;
+use crate::util::interning::InternedString;
 use crate::util::CargoResult;
 
 pub struct UpdateOptions<'a> {
@@ -18,6 +21,84 @@ pub struct UpdateOptions<'a> {
     pub aggressive: bool,
     pub dry_run: bool,
     pub workspace: bool,
+    pub message_format: UpdateMessageFormat,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum UpdateMessageFormat {
+    Human,
+    /// Also prints the changes to the lock file as JSON on stdout.
+    Json,
+}
+
+impl FromStr for UpdateMessageFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<UpdateMessageFormat, &'static str> {
+        match s {
+            "human" => Ok(UpdateMessageFormat::Human),
+            "json" => Ok(UpdateMessageFormat::Json),
+            _ => Err("invalid message format"),
+        }
+    }
+}
+
+/// A change to a package in the lock file, as found by `update_lockfile`.
+enum Change {
+    Updated(PackageId, PackageId),
+    Removed(PackageId),
+    Added(PackageId),
+}
+
+/// The JSON report of `cargo update --message-format=json`.
+#[derive(Serialize)]
+struct LockfileDiff {
+    dry_run: bool,
+    changes: Vec<LockfileChange>,
+}
+
+#[derive(Serialize)]
+struct LockfileChange {
+    kind: &'static str,
+    name: InternedString,
+    old: Option<LockedPackage>,
+    new: Option<LockedPackage>,
+}
+
+#[derive(Serialize)]
+struct LockedPackage {
+    version: String,
+    /// The source, including the exact revision of git sources. This is
+    /// `null` for path sources.
+    source: SourceId,
+    /// The name of the alternative registry the package is from, if known.
+    registry: Option<&'static str>,
+}
+
+impl LockedPackage {
+    fn new(id: PackageId) -> LockedPackage {
+        LockedPackage {
+            version: id.version().to_string(),
+            source: id.source_id(),
+            registry: id.source_id().alt_registry_key(),
+        }
+    }
+}
+
+impl From<&Change> for LockfileChange {
+    fn from(change: &Change) -> LockfileChange {
+        let (kind, old, new) = match *change {
+            Change::Updated(old, new) => ("updated", Some(old), Some(new)),
+            Change::Removed(old) => ("removed", Some(old), None),
+            Change::Added(new) => ("added", None, Some(new)),
+        };
+        LockfileChange {
+            kind,
+            name: old.or(new).unwrap().name(),
+            old: old.map(LockedPackage::new),
+            new: new.map(LockedPackage::new),
+        }
+    }
 }
 
 pub fn generate_lockfile(ws: &Workspace<'_>) -> CargoResult<()> {
@@ -56,7 +137,30 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
         Some(resolve) => resolve,
         None => {
             match opts.precise {
-                None => return generate_lockfile(ws),
+                None if !opts.dry_run && opts.message_format == UpdateMessageFormat::Human => {
+                    return generate_lockfile(ws)
+                }
+                // Without a lock file everything is being added, so report
+                // that before writing it.
+                None => {
+                    let mut registry = PackageRegistry::new(opts.config)?;
+                    let mut resolve = ops::resolve_with_previous(
+                        &mut registry,
+                        ws,
+                        &ResolveOpts::everything(),
+                        None,
+                        None,
+                        &[],
+                        true,
+                    )?;
+                    let changes = compare_dependency_graphs(None, &resolve)
+                        .into_iter()
+                        .flat_map(|(_, added)| added)
+                        .filter(|id| !id.source_id().is_path())
+                        .map(Change::Added)
+                        .collect::<Vec<_>>();
+                    return report_changes(ws, opts, &changes, &mut resolve);
+                }
 
                 // Precise option specified, so calculate a previous_resolve required
                 // by precise package update later.
@@ -126,38 +230,16 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
         true,
     )?;
 
-    // Summarize what is changing for the user.
-    let print_change = |status: &str, msg: String, color: Color| {
-        opts.config.shell().status_with_color(status, msg, color)
-    };
-    for (removed, added) in compare_dependency_graphs(&previous_resolve, &resolve) {
+    let mut changes = Vec::new();
+    for (removed, added) in compare_dependency_graphs(Some(&previous_resolve), &resolve) {
         if removed.len() == 1 && added.len() == 1 {
-            let msg = if removed[0].source_id().is_git() {
-                format!(
-                    "{} -> #{}",
-                    removed[0],
-                    &added[0].source_id().precise().unwrap()[..8]
-                )
-            } else {
-                format!("{} -> v{}", removed[0], added[0].version())
-            };
-            print_change("Updating", msg, Green)?;
+            changes.push(Change::Updated(removed[0], added[0]));
         } else {
-            for package in removed.iter() {
-                print_change("Removing", format!("{}", package), Red)?;
-            }
-            for package in added.iter() {
-                print_change("Adding", format!("{}", package), Cyan)?;
-            }
+            changes.extend(removed.into_iter().map(Change::Removed));
+            changes.extend(added.into_iter().map(Change::Added));
         }
     }
-    if opts.dry_run {
-        opts.config
-            .shell()
-            .warn("not updating lockfile due to dry run")?;
-    } else {
-        ops::write_pkg_lockfile(ws, &mut resolve)?;
-    }
+    report_changes(ws, opts, &changes, &mut resolve)?;
     return Ok(());
 
     fn fill_with_deps<'a>(
@@ -176,7 +258,7 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
     }
 
     fn compare_dependency_graphs(
-        previous_resolve: &Resolve,
+        previous_resolve: Option<&Resolve>,
         resolve: &Resolve,
     ) -> Vec<(Vec<PackageId>, Vec<PackageId>)> {
         fn key(dep: PackageId) -> (&'static str, SourceId) {
@@ -220,7 +302,7 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
         // Map `(package name, package source)` to `(removed versions, added versions)`.
         let mut changes = BTreeMap::new();
         let empty = (Vec::new(), Vec::new());
-        for dep in previous_resolve.iter() {
+        for dep in previous_resolve.into_iter().flat_map(|r| r.iter()) {
             changes
                 .entry(key(dep))
                 .or_insert_with(|| empty.clone())
@@ -249,3 +331,45 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
         changes.into_iter().map(|(_, v)| v).collect()
     }
 }
+
+/// Summarizes what is changing for the user, then writes the lock file
+/// unless this is a dry run.
+fn report_changes(
+    ws: &Workspace<'_>,
+    opts: &UpdateOptions<'_>,
+    changes: &[Change],
+    resolve: &mut Resolve,
+) -> CargoResult<()> {
+    let print_change = |status: &str, msg: String, color: Color| {
+        opts.config.shell().status_with_color(status, msg, color)
+    };
+    for change in changes {
+        match *change {
+            Change::Updated(old, new) => {
+                let msg = if old.source_id().is_git() {
+                    format!("{} -> #{}", old, &new.source_id().precise().unwrap()[..8])
+                } else {
+                    format!("{} -> v{}", old, new.version())
+                };
+                print_change("Updating", msg, Green)?;
+            }
+            Change::Removed(id) => print_change("Removing", format!("{}", id), Red)?,
+            Change::Added(id) => print_change("Adding", format!("{}", id), Cyan)?,
+        }
+    }
+    if opts.message_format == UpdateMessageFormat::Json {
+        let diff = LockfileDiff {
+            dry_run: opts.dry_run,
+            changes: changes.iter().map(LockfileChange::from).collect(),
+        };
+        opts.config.shell().print_json(&diff);
+    }
+    if opts.dry_run {
+        opts.config
+            .shell()
+            .warn("not updating lockfile due to dry run")?;
+    } else {
+        ops::write_pkg_lockfile(ws, resolve)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,511 @@
+//! Generating and checking registry indexes laid out for namespaced crates.
+//!
+//! The index stores a namespaced crate like `foo/bar` at `3/f/foo@/bar`
+//! rather than at a path derived from its full name (see
+//! `sources::registry::package_index_path`). This module builds such an
+//! index from a directory of `.crate` files or from an existing index with
+//! some other layout, and checks that an index is laid out correctly. It
+//! backs the unstable `cargo index` command.
+
+use crate::core::dependency::DepKind;
+use crate::core::manifest::{SUBCRATE_DELIMETER, SUBCRATE_DELIMETER_REGISTRY_FILENAME_REPLACEMENT};
+use crate::core::{PackageId, SourceId};
+use crate::ops;
+use crate::sources::package_index_path;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::restricted_names::{validate_package_name_for_registry, NamespacePolicy};
+use crate::util::{paths, Config, IntoUrl, Sha256};
+use anyhow::bail;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use tar::Archive;
+
+/// Where `build_index` reads packages from.
+pub enum IndexInput {
+    /// A directory of `.crate` files, as produced by `cargo package`.
+    Crates(PathBuf),
+    /// An existing index, in any layout.
+    Index(PathBuf),
+}
+
+pub struct IndexBuildOptions {
+    pub input: IndexInput,
+    /// The directory to write the index to. It must be empty or missing.
+    pub output: PathBuf,
+    /// The URL the index will be served from.
+    ///
+    /// Only used with `IndexInput::Crates`, to tell which dependencies are
+    /// from the same registry. Defaults to crates.io.
+    pub index_url: Option<String>,
+}
+
+pub struct IndexVerifyOptions {
+    pub index: PathBuf,
+    /// A directory of `.crate` files to check the `cksum` fields against.
+    pub crates: Option<PathBuf>,
+}
+
+/// Something wrong with an index, found by `verify_index`.
+#[derive(Debug)]
+pub struct IndexProblem {
+    /// The path of the index file, relative to the root of the index.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for IndexProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// A single line in an index file.
+///
+/// This mirrors `RegistryPackage`, but owns its data and keeps any fields it
+/// doesn't know about so that migrating an index doesn't lose them.
+#[derive(Serialize, Deserialize)]
+struct IndexPackage {
+    name: String,
+    vers: String,
+    deps: Vec<IndexDependency>,
+    cksum: String,
+    features: BTreeMap<String, Vec<String>>,
+    yanked: Option<bool>,
+    links: Option<String>,
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexDependency {
+    name: String,
+    req: String,
+    features: Vec<String>,
+    optional: bool,
+    default_features: bool,
+    target: Option<String>,
+    kind: Option<String>,
+    registry: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_json::Value>,
+}
+
+impl IndexDependency {
+    /// The name of the package this dependency is on.
+    fn package_name(&self) -> &str {
+        self.package.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Writes a namespaced index for the packages in `opts.input` to
+/// `opts.output`.
+pub fn build_index(config: &Config, opts: &IndexBuildOptions) -> CargoResult<()> {
+    if opts.output.exists() && fs::read_dir(&opts.output)?.next().is_some() {
+        bail!(
+            "the output directory `{}` is not empty",
+            opts.output.display()
+        );
+    }
+
+    let (packages, config_json) = match &opts.input {
+        IndexInput::Crates(dir) => (read_crates(config, dir, opts.index_url.as_deref())?, None),
+        IndexInput::Index(dir) => {
+            let config_json = dir.join("config.json");
+            let config_json = if config_json.exists() {
+                Some(paths::read_bytes(&config_json)?)
+            } else {
+                None
+            };
+            (read_index(config, dir)?, config_json)
+        }
+    };
+
+    let mut files: BTreeMap<String, Vec<IndexPackage>> = BTreeMap::new();
+    for pkg in packages {
+        files
+            .entry(package_index_path(&pkg.name))
+            .or_default()
+            .push(pkg);
+    }
+
+    paths::create_dir_all(&opts.output)?;
+    if let Some(config_json) = config_json {
+        paths::write(&opts.output.join("config.json"), config_json)?;
+    }
+    for (path, mut versions) in files {
+        versions.sort_by_key(|pkg| semver::Version::parse(&pkg.vers).ok());
+        for pair in versions.windows(2) {
+            if pair[0].vers == pair[1].vers {
+                bail!("found `{}` v{} more than once", pair[1].name, pair[1].vers);
+            }
+        }
+        let mut contents = String::new();
+        for pkg in &versions {
+            contents.push_str(&serde_json::to_string(pkg)?);
+            contents.push('\n');
+        }
+        let dst = opts.output.join(&path);
+        paths::create_dir_all(dst.parent().unwrap())?;
+        paths::write(&dst, contents)?;
+    }
+    Ok(())
+}
+
+/// Checks that the index at `opts.index` is laid out for namespaced crates,
+/// returning everything found wrong with it.
+pub fn verify_index(opts: &IndexVerifyOptions) -> CargoResult<Vec<IndexProblem>> {
+    #[derive(Deserialize)]
+    struct IndexConfig {
+        #[serde(default)]
+        namespaces: NamespacePolicy,
+    }
+
+    let config_json = opts.index.join("config.json");
+    let policy = if config_json.exists() {
+        let contents = paths::read(&config_json)?;
+        serde_json::from_str::<IndexConfig>(&contents)
+            .chain_err(|| format!("failed to parse `{}`", config_json.display()))?
+            .namespaces
+    } else {
+        NamespacePolicy::default()
+    };
+
+    let crates = match &opts.crates {
+        Some(dir) => Some((dir, SourceId::for_local_registry(dir)?)),
+        None => None,
+    };
+
+    let mut problems = Vec::new();
+    let mut files = Vec::new();
+    for file in index_files(&opts.index)? {
+        let path = relative_index_path(&opts.index, &file);
+        let mut packages = Vec::new();
+        let mut errors = Vec::new();
+        for (i, line) in paths::read(&file)?.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<IndexPackage>(line) {
+                Ok(pkg) => packages.push(pkg),
+                Err(e) => errors.push(format!("line {} is not a valid index entry: {}", i + 1, e)),
+            }
+        }
+        files.push((path, packages, errors));
+    }
+    let known = files
+        .iter()
+        .flat_map(|(_, packages, _)| packages.iter())
+        .map(|pkg| pkg.name.to_lowercase())
+        .collect::<HashSet<_>>();
+
+    for (path, packages, errors) in files.iter() {
+        let mut problem = |message: String| {
+            problems.push(IndexProblem {
+                path: path.clone(),
+                message,
+            })
+        };
+        for error in errors {
+            problem(error.clone());
+        }
+        let mut seen = HashSet::new();
+        let mut misplaced = HashSet::new();
+        for pkg in packages {
+            if let Err(e) =
+                validate_package_name_for_registry(&pkg.name, "package name", "", &policy)
+            {
+                problem(e.to_string());
+            }
+            let expected = package_index_path(&pkg.name);
+            if *path != expected && misplaced.insert(pkg.name.as_str()) {
+                problem(format!(
+                    "`{}` should be at `{}` in a namespaced index",
+                    pkg.name, expected
+                ));
+            }
+            if !seen.insert(pkg.vers.as_str()) {
+                problem(format!(
+                    "`{}` v{} is listed more than once",
+                    pkg.name, pkg.vers
+                ));
+            }
+
+            for dep in &pkg.deps {
+                let dep_name = dep.package_name();
+                if let Some(namespaced) = namespaced_name(dep_name) {
+                    problem(format!(
+                        "dependency `{}` of `{}` v{} should be written `{}`",
+                        dep_name, pkg.name, pkg.vers, namespaced
+                    ));
+                } else if let Err(e) =
+                    validate_package_name_for_registry(dep_name, "dependency name", "", &policy)
+                {
+                    problem(format!(
+                        "dependency of `{}` v{} is invalid: {}",
+                        pkg.name, pkg.vers, e
+                    ));
+                } else if dep.registry.is_none()
+                    && dep_name.contains(SUBCRATE_DELIMETER)
+                    && !known.contains(&dep_name.to_lowercase())
+                {
+                    problem(format!(
+                        "`{}` v{} depends on `{}`, which is not in this index",
+                        pkg.name, pkg.vers, dep_name
+                    ));
+                }
+            }
+
+            if let Some((crates, crates_id)) = crates {
+                let id = match PackageId::new(pkg.name.as_str(), pkg.vers.as_str(), crates_id) {
+                    Ok(id) => id,
+                    Err(e) => {
+                        problem(format!("`{}` has an invalid version: {}", pkg.name, e));
+                        continue;
+                    }
+                };
+                let crate_file = crates.join(id.crate_file_name());
+                if !crate_file.exists() {
+                    problem(format!(
+                        "`{}` v{} has no `.crate` file at `{}`",
+                        pkg.name,
+                        pkg.vers,
+                        crate_file.display()
+                    ));
+                    continue;
+                }
+                let cksum = Sha256::new().update_path(&crate_file)?.finish_hex();
+                if cksum != pkg.cksum {
+                    problem(format!(
+                        "`{}` v{} has cksum `{}`, but `{}` has cksum `{}`",
+                        pkg.name,
+                        pkg.vers,
+                        pkg.cksum,
+                        crate_file.display(),
+                        cksum
+                    ));
+                }
+            }
+        }
+    }
+    Ok(problems)
+}
+
+/// If `name` is a namespaced name written in its file name form, like
+/// `foo~bar`, returns the name it stands for, like `foo/bar`.
+fn namespaced_name(name: &str) -> Option<String> {
+    if name.contains(SUBCRATE_DELIMETER_REGISTRY_FILENAME_REPLACEMENT) {
+        Some(name.replace(
+            SUBCRATE_DELIMETER_REGISTRY_FILENAME_REPLACEMENT,
+            SUBCRATE_DELIMETER,
+        ))
+    } else {
+        None
+    }
+}
+
+/// Reads index entries for every `.crate` file in `dir`.
+fn read_crates(
+    config: &Config,
+    dir: &Path,
+    index_url: Option<&str>,
+) -> CargoResult<Vec<IndexPackage>> {
+    let registry_id = match index_url {
+        Some(url) => SourceId::for_registry(&url.into_url()?)?,
+        None => SourceId::crates_io(config)?,
+    };
+
+    let mut crate_files = Vec::new();
+    for entry in fs::read_dir(dir).chain_err(|| format!("failed to read `{}`", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().and_then(|s| s.to_str()) == Some("crate") {
+            crate_files.push(path);
+        }
+    }
+    crate_files.sort();
+
+    let mut packages = Vec::new();
+    for path in crate_files {
+        let pkg = read_crate(config, &path, registry_id)
+            .chain_err(|| format!("failed to read `{}`", path.display()))?;
+        packages.push(pkg);
+    }
+    Ok(packages)
+}
+
+/// Unpacks the `.crate` file at `path` and builds its index entry.
+fn read_crate(config: &Config, path: &Path, registry_id: SourceId) -> CargoResult<IndexPackage> {
+    let cksum = Sha256::new().update_path(path)?.finish_hex();
+
+    let tmp = tempfile::Builder::new().prefix("cargo-index").tempdir()?;
+    let mut archive = Archive::new(GzDecoder::new(File::open(path)?));
+    archive.set_preserve_mtime(false);
+    archive.unpack(tmp.path())?;
+    let root = match fs::read_dir(tmp.path())?.next() {
+        Some(entry) => entry?.path(),
+        None => bail!("the archive is empty"),
+    };
+    let manifest_path = root.join("Cargo.toml");
+    let (pkg, _) = ops::read_package(&manifest_path, SourceId::for_path(&root)?, config)?;
+
+    let file_name = path.file_name().unwrap().to_string_lossy();
+    let expected = pkg.crate_file_name();
+    if file_name != expected {
+        config.shell().warn(format!(
+            "`{}` contains `{} v{}`, which would be named `{}` in a local registry",
+            file_name,
+            pkg.name(),
+            pkg.version(),
+            expected
+        ))?;
+    }
+    config
+        .shell()
+        .status("Indexing", format!("{} v{}", pkg.name(), pkg.version()))?;
+
+    let deps = pkg
+        .dependencies()
+        .iter()
+        .filter(|dep| {
+            // Skip dev-dependency without version, like `cargo publish`.
+            dep.is_transitive() || dep.specified_req()
+        })
+        .map(|dep| {
+            // In the index, `None` means "from the same registry", whereas in
+            // Cargo.toml it means "from crates.io".
+            let dep_registry_id = match dep.registry_id() {
+                Some(id) => id,
+                None => SourceId::crates_io(config)?,
+            };
+            let registry = if dep_registry_id != registry_id {
+                Some(dep_registry_id.url().to_string())
+            } else {
+                None
+            };
+            let (name, package) = match dep.explicit_name_in_toml() {
+                Some(name) => (name.to_string(), Some(dep.package_name().to_string())),
+                None => (dep.package_name().to_string(), None),
+            };
+            Ok(IndexDependency {
+                name,
+                req: dep.version_req().to_string(),
+                features: dep.features().iter().map(|s| s.to_string()).collect(),
+                optional: dep.is_optional(),
+                default_features: dep.uses_default_features(),
+                target: dep.platform().map(|s| s.to_string()),
+                kind: Some(
+                    match dep.kind() {
+                        DepKind::Normal => "normal",
+                        DepKind::Build => "build",
+                        DepKind::Development => "dev",
+                    }
+                    .to_string(),
+                ),
+                registry,
+                package,
+                other: BTreeMap::new(),
+            })
+        })
+        .collect::<CargoResult<Vec<_>>>()?;
+
+    let features = match pkg.manifest().original().features() {
+        Some(features) => features
+            .iter()
+            .map(|(feat, values)| {
+                (
+                    feat.to_string(),
+                    values.iter().map(|fv| fv.to_string()).collect(),
+                )
+            })
+            .collect(),
+        None => BTreeMap::new(),
+    };
+
+    Ok(IndexPackage {
+        name: pkg.name().to_string(),
+        vers: pkg.version().to_string(),
+        deps,
+        cksum,
+        features,
+        yanked: Some(false),
+        links: pkg.manifest().links().map(|s| s.to_string()),
+        other: BTreeMap::new(),
+    })
+}
+
+/// Reads every entry of the index at `dir`, whatever its layout, and
+/// rewrites names written in their file name form (`foo~bar`) to namespaced
+/// names (`foo/bar`).
+fn read_index(config: &Config, dir: &Path) -> CargoResult<Vec<IndexPackage>> {
+    let mut packages = Vec::new();
+    for file in index_files(dir)? {
+        let path = relative_index_path(dir, &file);
+        let mut moved = false;
+        for (i, line) in paths::read(&file)?.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut pkg = serde_json::from_str::<IndexPackage>(line)
+                .chain_err(|| format!("failed to parse line {} of `{}`", i + 1, file.display()))?;
+            if let Some(name) = namespaced_name(&pkg.name) {
+                pkg.name = name;
+            }
+            for dep in pkg.deps.iter_mut() {
+                let package = dep.package.as_mut().unwrap_or(&mut dep.name);
+                if let Some(name) = namespaced_name(package) {
+                    *package = name;
+                }
+            }
+            let expected = package_index_path(&pkg.name);
+            if !moved && path != expected {
+                config
+                    .shell()
+                    .status("Moving", format!("{} to {}", path, expected))?;
+                moved = true;
+            }
+            packages.push(pkg);
+        }
+    }
+    Ok(packages)
+}
+
+/// Lists the files of the index at `dir`, skipping `config.json` and hidden
+/// files like `.git`.
+fn index_files(dir: &Path) -> CargoResult<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        bail!("`{}` is not a directory", dir.display());
+    }
+    let mut files = Vec::new();
+    let walker = walkdir::WalkDir::new(dir)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+        });
+    for entry in walker {
+        let entry = entry.chain_err(|| format!("failed to read `{}`", dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if entry.depth() == 1 && entry.file_name() == "config.json" {
+            continue;
+        }
+        files.push(entry.into_path());
+    }
+    Ok(files)
+}
+
+/// The path of `file` relative to the index at `dir`, with `/` separators.
+fn relative_index_path(dir: &Path, file: &Path) -> String {
+    file.strip_prefix(dir)
+        .unwrap()
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -1,4 +1,5 @@
-use crate::core::{manifest::SUBCRATE_DELIMETER, Shell, Workspace};
+use crate::core::manifest::{SUBCRATE_DELIMETER, SUBCRATE_DELIMETER_RUST_CODE_REPLACEMENT};
+use crate::core::{Shell, Workspace};
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::{existing_vcs_repo, FossilRepo, GitRepo, HgRepo, PijulRepo};
 use crate::util::{paths, restricted_names, Config};
@@ -48,6 +49,38 @@ impl<'de> de::Deserialize<'de> for VersionControl {
     }
 }
 
+/// How the directory of a new namespaced package is laid out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NamespaceLayout {
+    /// `foo/bar` is created in `foo/bar/`.
+    Nested,
+    /// `foo/bar` is created in `foo-bar/`.
+    Flat,
+}
+
+impl FromStr for NamespaceLayout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, anyhow::Error> {
+        match s {
+            "nested" => Ok(NamespaceLayout::Nested),
+            "flat" => Ok(NamespaceLayout::Flat),
+            other => anyhow::bail!("unknown namespace layout: `{}`", other),
+        }
+    }
+}
+
+impl NamespaceLayout {
+    /// The directory for the package `name`, relative to where the package
+    /// would have been created without a namespace.
+    fn dir_name(self, name: &str) -> PathBuf {
+        match self {
+            NamespaceLayout::Nested => name.split(SUBCRATE_DELIMETER).collect(),
+            NamespaceLayout::Flat => PathBuf::from(name.replace(SUBCRATE_DELIMETER, "-")),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct NewOptions {
     pub version_control: Option<VersionControl>,
@@ -59,6 +92,13 @@ pub struct NewOptions {
     pub name: Option<String>,
     pub edition: Option<String>,
     pub registry: Option<String>,
+    /// Namespace to create the package in, as in `--namespace foo`
+    pub namespace: Option<String>,
+    /// Whether the path argument is the full namespaced package name, as in
+    /// `foo/bar --namespaced`
+    pub namespaced: bool,
+    /// Directory layout for a new namespaced package
+    pub namespace_layout: NamespaceLayout,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -97,6 +137,8 @@ struct MkOptions<'a> {
     bin: bool,
     edition: Option<&'a str>,
     registry: Option<&'a str>,
+    /// Whether the package was created with `--namespace` or `--namespaced`.
+    namespaced: bool,
 }
 
 impl NewOptions {
@@ -109,6 +151,9 @@ impl NewOptions {
         name: Option<String>,
         edition: Option<String>,
         registry: Option<String>,
+        namespace: Option<String>,
+        namespaced: bool,
+        namespace_layout: NamespaceLayout,
     ) -> CargoResult<NewOptions> {
         let kind = match (bin, lib) {
             (true, true) => anyhow::bail!("can't specify both lib and binary outputs"),
@@ -116,6 +161,12 @@ impl NewOptions {
             // default to bin
             (_, false) => NewProjectKind::Bin,
         };
+        if namespaced && namespace.is_some() {
+            anyhow::bail!("can't specify both --namespace and --namespaced");
+        }
+        if namespaced && name.is_some() {
+            anyhow::bail!("can't specify both --name and --namespaced");
+        }
 
         let opts = NewOptions {
             version_control,
@@ -125,6 +176,9 @@ impl NewOptions {
             name,
             edition,
             registry,
+            namespace,
+            namespaced,
+            namespace_layout,
         };
         Ok(opts)
     }
@@ -158,6 +212,52 @@ fn get_name<'a>(path: &'a Path, opts: &'a NewOptions) -> CargoResult<&'a str> {
     })
 }
 
+/// Works out the name of a namespaced package from `--namespace` or
+/// `--namespaced`, along with the directory to create it in if `layout` is
+/// given. Returns `None` for a package that isn't namespaced.
+fn get_namespaced_name(
+    opts: &NewOptions,
+    layout: Option<NamespaceLayout>,
+) -> CargoResult<Option<(String, PathBuf)>> {
+    let (name, base) = if let Some(ref namespace) = opts.namespace {
+        let namespace = namespace.trim_end_matches(SUBCRATE_DELIMETER);
+        let name = format!(
+            "{}{}{}",
+            namespace,
+            SUBCRATE_DELIMETER,
+            get_name(&opts.path, opts)?
+        );
+        (name, opts.path.parent())
+    } else if opts.namespaced {
+        let name = opts.raw_path.trim_end_matches(SUBCRATE_DELIMETER);
+        if !name.contains(SUBCRATE_DELIMETER) {
+            anyhow::bail!(
+                "`--namespaced` requires a namespaced package name like `foo{}bar`, \
+                 found `{}`",
+                SUBCRATE_DELIMETER,
+                name
+            );
+        }
+        // Strip the components of the name from the path, leaving where the
+        // package would be created without a namespace.
+        let depth = name.split(SUBCRATE_DELIMETER).count();
+        (name.to_string(), opts.path.ancestors().nth(depth))
+    } else {
+        return Ok(None);
+    };
+    let path = match (layout, base) {
+        (Some(layout), Some(base)) => base.join(layout.dir_name(&name)),
+        _ => opts.path.clone(),
+    };
+    Ok(Some((name, path)))
+}
+
+/// The name of the library target of the package `name`, which must be
+/// usable from Rust code.
+fn lib_target_name(package_name: &str) -> String {
+    package_name.replace(SUBCRATE_DELIMETER, SUBCRATE_DELIMETER_RUST_CODE_REPLACEMENT)
+}
+
 fn check_name(
     name: &str,
     show_name_help: bool,
@@ -181,8 +281,10 @@ fn check_name(
     {
         shell.warn(format!(
             "Cargo will use only the directory name as a crate name ({})\n\
-             The path that you provided is also a valid namespaced crate name, so this may not be what you intended.{}",
+             The path that you provided is also a valid namespaced crate name, so this may not be what you intended.\n\
+             Use --namespaced to create the package `{}` instead.{}",
             name,
+            raw_path,
             name_help
         ))?;
     }
@@ -314,7 +416,7 @@ fn detect_source_paths_and_types(
             },
             H::Lib => SourceFileInformation {
                 relative_path: pp,
-                target_name: package_name.to_string(),
+                target_name: lib_target_name(package_name),
                 bin: false,
             },
             H::Detect => {
@@ -322,7 +424,11 @@ fn detect_source_paths_and_types(
                 let isbin = content.contains("fn main");
                 SourceFileInformation {
                     relative_path: pp,
-                    target_name: package_name.to_string(),
+                    target_name: if isbin {
+                        package_name.to_string()
+                    } else {
+                        lib_target_name(package_name)
+                    },
                     bin: isbin,
                 }
             }
@@ -376,14 +482,18 @@ fn plan_new_source_file(bin: bool, package_name: String) -> SourceFileInformatio
     } else {
         SourceFileInformation {
             relative_path: "src/lib.rs".to_string(),
-            target_name: package_name,
+            target_name: lib_target_name(&package_name),
             bin: false,
         }
     }
 }
 
 pub fn new(opts: &NewOptions, config: &Config) -> CargoResult<String> {
-    let path = &opts.path;
+    let namespaced = get_namespaced_name(opts, Some(opts.namespace_layout))?;
+    let (name, path) = match namespaced {
+        Some((ref name, ref path)) => (name.as_str(), path),
+        None => (get_name(&opts.path, opts)?, &opts.path),
+    };
     if path.exists() {
         anyhow::bail!(
             "destination `{}` already exists\n\n\
@@ -392,10 +502,9 @@ pub fn new(opts: &NewOptions, config: &Config) -> CargoResult<String> {
         )
     }
 
-    let name = get_name(path, opts)?;
     check_name(
         name,
-        opts.name.is_none(),
+        opts.name.is_none() && namespaced.is_none(),
         opts.kind.is_bin(),
         &opts.raw_path,
         &mut config.shell(),
@@ -409,6 +518,7 @@ pub fn new(opts: &NewOptions, config: &Config) -> CargoResult<String> {
         bin: opts.kind.is_bin(),
         edition: opts.edition.as_deref(),
         registry: opts.registry.as_deref(),
+        namespaced: namespaced.is_some(),
     };
 
     mk(config, &mkopts).chain_err(|| {
@@ -433,7 +543,11 @@ pub fn init(opts: &NewOptions, config: &Config) -> CargoResult<()> {
         anyhow::bail!("`cargo init` cannot be run on existing Cargo packages")
     }
 
-    let name = get_name(path, opts)?;
+    let namespaced = get_namespaced_name(opts, None)?;
+    let name = match namespaced {
+        Some((ref name, _)) => name.as_str(),
+        None => get_name(path, opts)?,
+    };
 
     let mut src_paths_types = vec![];
 
@@ -449,7 +563,7 @@ pub fn init(opts: &NewOptions, config: &Config) -> CargoResult<()> {
     let has_bin = src_paths_types.iter().any(|x| x.bin);
     check_name(
         name,
-        opts.name.is_none(),
+        opts.name.is_none() && namespaced.is_none(),
         has_bin,
         &opts.raw_path,
         &mut config.shell(),
@@ -499,6 +613,7 @@ pub fn init(opts: &NewOptions, config: &Config) -> CargoResult<()> {
         source_files: src_paths_types,
         edition: opts.edition.as_deref(),
         registry: opts.registry.as_deref(),
+        namespaced: namespaced.is_some(),
     };
 
     mk(config, &mkopts).chain_err(|| {
@@ -723,6 +838,14 @@ path = {}
                 i.target_name,
                 toml::Value::String(i.relative_path.clone())
             ));
+        } else if i.target_name != name {
+            cargotoml_path_specifier.push_str(&format!(
+                r#"
+[lib]
+name = "{}"
+"#,
+                i.target_name
+            ));
         }
     }
 
@@ -804,6 +927,18 @@ mod tests {
         }
     }
 
+    // Namespaced packages usually live in a directory per namespace, which
+    // an explicit `members` list is unlikely to cover yet.
+    if opts.namespaced {
+        if let Err(e) = add_to_workspace_members(path, name, config) {
+            crate::display_warning_with_error(
+                "failed to add the new package to the workspace members",
+                &e,
+                &mut config.shell(),
+            );
+        }
+    }
+
     if let Err(e) = Workspace::new(&path.join("Cargo.toml"), config) {
         crate::display_warning_with_error(
             "compiling this new crate may not work due to invalid \
@@ -816,6 +951,218 @@ mod tests {
     Ok(())
 }
 
+/// Adds the package at `path` to the `members` of the enclosing workspace, if
+/// the workspace lists its members explicitly and none of them match `path`.
+///
+/// The workspace manifest is edited in place so that its formatting and
+/// comments are kept.
+fn add_to_workspace_members(path: &Path, name: &str, config: &Config) -> CargoResult<()> {
+    let (root_manifest, contents, workspace) = match find_workspace_root(path, config)? {
+        Some(root) => root,
+        None => return Ok(()),
+    };
+    let root_dir = root_manifest.parent().unwrap();
+    let members = match workspace.get("members").and_then(|m| m.as_array()) {
+        Some(members) => members,
+        None => return Ok(()),
+    };
+    let matches = |pattern: &toml::Value| {
+        pattern.as_str().map_or(false, |pattern| {
+            let pattern = root_dir.join(pattern);
+            match glob::Pattern::new(&pattern.to_string_lossy()) {
+                Ok(glob) => glob.matches_path(path),
+                Err(_) => pattern == path,
+            }
+        })
+    };
+    let excluded = workspace
+        .get("exclude")
+        .and_then(|e| e.as_array())
+        .map_or(false, |exclude| {
+            exclude.iter().any(|ex| {
+                ex.as_str()
+                    .map_or(false, |ex| path.starts_with(root_dir.join(ex)))
+            })
+        });
+    if excluded || members.iter().any(matches) {
+        return Ok(());
+    }
+
+    let relative = path
+        .strip_prefix(root_dir)?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let new_contents = insert_workspace_member(&contents, &relative).ok_or_else(|| {
+        anyhow::format_err!(
+            "could not find the `workspace.members` array in `{}`",
+            root_manifest.display()
+        )
+    })?;
+    // The edit is done on the raw text, so make sure that the only thing it
+    // changed is the new entry before writing it back.
+    let mut expected = members.clone();
+    expected.push(toml::Value::String(relative.clone()));
+    let new_members = toml::from_str::<toml::Value>(&new_contents)
+        .ok()
+        .and_then(|v| v.get("workspace")?.get("members")?.as_array().cloned());
+    if new_members.as_ref() != Some(&expected) {
+        anyhow::bail!(
+            "could not add `{}` to the `workspace.members` array in `{}`",
+            relative,
+            root_manifest.display()
+        );
+    }
+    paths::write(&root_manifest, new_contents)?;
+    config.shell().status(
+        "Adding",
+        format!(
+            "`{}` as member of workspace at `{}`",
+            name,
+            root_dir.display()
+        ),
+    )?;
+    Ok(())
+}
+
+/// Finds the closest manifest above `path` with a `[workspace]` table,
+/// returning its path, contents, and the parsed table.
+fn find_workspace_root(
+    path: &Path,
+    config: &Config,
+) -> CargoResult<Option<(PathBuf, String, toml::value::Table)>> {
+    for dir in paths::ancestors(path).skip(1) {
+        let manifest = dir.join("Cargo.toml");
+        if manifest.exists() {
+            let contents = paths::read(&manifest)?;
+            // An invalid manifest is reported when the new package is
+            // checked against its workspace.
+            let value: toml::Value = match toml::from_str(&contents) {
+                Ok(value) => value,
+                Err(_) => return Ok(None),
+            };
+            if let Some(workspace) = value.get("workspace").and_then(|w| w.as_table()) {
+                let workspace = workspace.clone();
+                return Ok(Some((manifest, contents, workspace)));
+            }
+        }
+        // Like `Workspace::find_root`, don't look for a workspace outside of
+        // `CARGO_HOME`.
+        if config.home() == dir {
+            break;
+        }
+    }
+    Ok(None)
+}
+
+/// Appends `member` to the `members` array of the `[workspace]` table in the
+/// manifest `contents`. Returns `None` if the array can't be found, for
+/// example if it is written as an inline table.
+fn insert_workspace_member(contents: &str, member: &str) -> Option<String> {
+    // Find the `members =` key between `[workspace]` and the next table.
+    let mut in_workspace = false;
+    let mut key = None;
+    let mut line_start = 0;
+    for line in contents.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            if in_workspace {
+                break;
+            }
+            in_workspace = trimmed.starts_with("[workspace]");
+        } else if in_workspace {
+            if let Some(rest) = trimmed.strip_prefix("members") {
+                if rest.trim_start().starts_with('=') {
+                    key = Some(line_start + line.len() - rest.len());
+                    break;
+                }
+            }
+        }
+        line_start += line.len() + 1;
+    }
+    let key = key?;
+    let open = key + contents[key..].find('[')?;
+
+    // Find the end of the last element and the closing bracket, skipping
+    // over strings and comments.
+    let bytes = contents.as_bytes();
+    let mut last_end = None;
+    let mut i = open + 1;
+    let close = loop {
+        match *bytes.get(i)? {
+            b']' => break i,
+            b'#' => i += contents[i..].find('\n')?,
+            quote @ b'"' | quote @ b'\'' => {
+                // Multi-line strings are delimited by three quotes.
+                let delim = if quote == b'"' { "\"" } else { "'" };
+                let delim = if contents[i..].starts_with(&delim.repeat(3)) {
+                    delim.repeat(3)
+                } else {
+                    delim.to_string()
+                };
+                i += delim.len();
+                while !bytes.get(i..)?.starts_with(delim.as_bytes()) {
+                    if quote == b'"' && bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i += delim.len() - 1;
+                last_end = Some(i + 1);
+            }
+            _ => {}
+        }
+        i += 1;
+    };
+
+    let entry = toml::Value::String(member.to_string()).to_string();
+    let end = match last_end {
+        Some(end) => end,
+        None => {
+            return Some(format!(
+                "{}{}{}",
+                &contents[..=open],
+                entry,
+                &contents[close..]
+            ))
+        }
+    };
+    if !contents[open..close].contains('\n') {
+        return Some(format!(
+            "{}, {}{}",
+            &contents[..end],
+            entry,
+            &contents[end..]
+        ));
+    }
+
+    // One element per line, so add a line after the last element (and any
+    // comment following it) with the same indentation.
+    let line_start = contents[..end].rfind('\n').map_or(0, |i| i + 1);
+    let line = &contents[line_start..end];
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let line_end = contents[end..close].find('\n').map_or(close, |i| end + i);
+    if contents[end..line_end].trim_start().starts_with(',') {
+        Some(format!(
+            "{}\n{}{},{}",
+            &contents[..line_end],
+            indent,
+            entry,
+            &contents[line_end..]
+        ))
+    } else {
+        Some(format!(
+            "{},{}\n{}{}{}",
+            &contents[..end],
+            &contents[end..line_end],
+            indent,
+            entry,
+            &contents[line_end..]
+        ))
+    }
+}
+
 fn get_environment_variable(variables: &[&str]) -> Option<String> {
     variables.iter().filter_map(|var| env::var(var).ok()).next()
 }
@@ -108,7 +108,7 @@ pub fn package(ws: &Workspace<'_>, opts: &PackageOpts<'_>) -> CargoResult<Option
 
     verify_dependencies(pkg)?;
 
-    let filename = format!("{}-{}.crate", pkg.registry_safe_file_name(), pkg.version());
+    let filename = pkg.crate_file_name();
     let dir = ws.target_dir().join("package");
     let mut dst = {
         let tmp = format!(".{}", filename);
@@ -0,0 +1,22 @@
+use std::path::Path;
+
+use crate::core::compiler::TimingsReport;
+use crate::util::{paths, CargoResult, Config};
+
+/// The file `cargo report timings --compare` saves its report to.
+const COMPARISON_FILENAME: &str = "cargo-timing-compare.html";
+
+/// Saves an HTML report comparing the timings of the build saved at `old`
+/// by `-Z timings=json` to those of the build saved at `new`.
+pub fn report_timings_comparison(config: &Config, old: &Path, new: &Path) -> CargoResult<()> {
+    let old = TimingsReport::load(old)?;
+    let new = TimingsReport::load(new)?;
+    let path = config.cwd().join(COMPARISON_FILENAME);
+    paths::write(&path, old.render_comparison(&new)?)?;
+    config.shell().status_with_color(
+        "Timing",
+        format!("comparison saved to {}", path.display()),
+        termcolor::Color::Cyan,
+    )?;
+    Ok(())
+}
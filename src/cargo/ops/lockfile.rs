@@ -66,9 +66,14 @@ pub fn write_pkg_lockfile(ws: &Workspace<'_>, resolve: &mut Resolve) -> CargoRes
     // encoding to whatever the latest default is. That way we can slowly roll
     // out lock file updates as they're otherwise already updated, and changes
     // which don't touch dependencies won't seemingly spuriously update the lock
-    // file.
-    if resolve.version() < ResolveVersion::default() {
-        resolve.set_version(ResolveVersion::default());
+    // file. `-Z next-lockfile-bump` opts into the next encoding early.
+    let default_version = if ws.config().cli_unstable().next_lockfile_bump {
+        ResolveVersion::V4
+    } else {
+        ResolveVersion::default()
+    };
+    if resolve.version() < default_version {
+        resolve.set_version(default_version);
         out = serialize_resolve(resolve, orig.as_deref());
     }
 
@@ -198,6 +203,9 @@ fn emit_package(dep: &toml::value::Table, out: &mut String) {
     if dep.contains_key("source") {
         out.push_str(&format!("source = {}\n", &dep["source"]));
     }
+    if dep.contains_key("registry") {
+        out.push_str(&format!("registry = {}\n", &dep["registry"]));
+    }
     if dep.contains_key("checksum") {
         out.push_str(&format!("checksum = {}\n", &dep["checksum"]));
     }
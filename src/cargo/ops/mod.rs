@@ -7,13 +7,17 @@ pub use self::cargo_doc::{doc, DocOptions};
 pub use self::cargo_fetch::{fetch, FetchOptions};
 pub use self::cargo_generate_lockfile::generate_lockfile;
 pub use self::cargo_generate_lockfile::update_lockfile;
-pub use self::cargo_generate_lockfile::UpdateOptions;
+pub use self::cargo_generate_lockfile::{UpdateMessageFormat, UpdateOptions};
+pub use self::cargo_index::{
+    build_index, verify_index, IndexBuildOptions, IndexInput, IndexProblem, IndexVerifyOptions,
+};
 pub use self::cargo_install::{install, install_list};
-pub use self::cargo_new::{init, new, NewOptions, VersionControl};
+pub use self::cargo_new::{init, new, NamespaceLayout, NewOptions, VersionControl};
 pub use self::cargo_output_metadata::{output_metadata, ExportInfo, OutputMetadataOptions};
 pub use self::cargo_package::{package, PackageOpts};
 pub use self::cargo_pkgid::pkgid;
 pub use self::cargo_read_manifest::{read_package, read_packages};
+pub use self::cargo_report::report_timings_comparison;
 pub use self::cargo_run::run;
 pub use self::cargo_test::{run_benches, run_tests, TestOptions};
 pub use self::cargo_uninstall::uninstall;
@@ -34,12 +38,14 @@ mod cargo_compile;
 mod cargo_doc;
 mod cargo_fetch;
 mod cargo_generate_lockfile;
+mod cargo_index;
 mod cargo_install;
 mod cargo_new;
 mod cargo_output_metadata;
 mod cargo_package;
 mod cargo_pkgid;
 mod cargo_read_manifest;
+mod cargo_report;
 mod cargo_run;
 mod cargo_test;
 mod cargo_uninstall;
@@ -8,7 +8,7 @@ use std::time::Duration;
 use std::{cmp, env};
 
 use anyhow::{bail, format_err};
-use crates_io::{self, NewCrate, NewCrateDependency, Registry};
+use crates_io::{self, NewCrate, NewCrateDependency, Registry, ResponseError};
 use curl::easy::{Easy, InfoType, SslOpt, SslVersion};
 use log::{log, Level};
 use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
@@ -25,8 +25,9 @@ use crate::sources::{RegistrySource, SourceConfigMap, CRATES_IO_REGISTRY};
 use crate::util::config::{self, Config, SslVersionConfig, SslVersionConfigRange};
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::important_paths::find_root_manifest_for_wd;
+use crate::util::restricted_names::NamespacePolicy;
 use crate::util::IntoUrl;
-use crate::util::{paths, validate_package_name};
+use crate::util::{paths, validate_package_name, validate_package_name_for_registry};
 use crate::{drop_print, drop_println, version};
 
 mod auth;
@@ -92,15 +93,36 @@ pub fn publish(ws: &Workspace<'_>, opts: &PublishOpts<'_>) -> CargoResult<()> {
         }
     }
 
+    // A dry run doesn't need a token, but the namespace check should still
+    // use the same one a real publish would, if one is configured.
+    let validate_token = if !opts.dry_run || opts.token.is_some() {
+        true
+    } else if opts.index.is_some() {
+        false
+    } else {
+        let reg_cfg = registry_configuration(opts.config, publish_registry.as_deref())?;
+        reg_cfg.token.is_some() || reg_cfg.credential_process.is_some()
+    };
     let (mut registry, _reg_cfg, reg_id) = registry(
         opts.config,
         opts.token.clone(),
         opts.index.clone(),
         publish_registry,
         true,
-        !opts.dry_run,
+        validate_token,
+    )?;
+    let policy = namespace_policy(opts.config, reg_id)?;
+    validate_package_name_for_registry(&pkg.name(), "package name", "", &policy).chain_err(
+        || {
+            format!(
+                "`{}` cannot be published to registry `{}`",
+                pkg.name(),
+                reg_id.display_registry_name()
+            )
+        },
     )?;
     verify_dependencies(pkg, &registry, reg_id)?;
+    verify_namespace_owner(opts.config, &mut registry, pkg, reg_id)?;
 
     // Prepare a tarball, with a non-suppressible warning if metadata
     // is missing since this is being put online.
@@ -194,6 +216,82 @@ fn verify_dependencies(
     Ok(())
 }
 
+/// Checks with the registry that the namespace of a namespaced package is
+/// either unclaimed or owned by the user publishing it, so that a conflict is
+/// reported before anything is packaged or uploaded.
+fn verify_namespace_owner(
+    config: &Config,
+    registry: &mut Registry,
+    pkg: &Package,
+    registry_src: SourceId,
+) -> CargoResult<()> {
+    let name = pkg.name();
+    let namespace = match name.find(SUBCRATE_DELIMETER) {
+        Some(i) => &name[..i],
+        None => return Ok(()),
+    };
+    let ns = match registry.namespace(namespace) {
+        // Nobody owns it yet, so the registry decides what happens on upload.
+        Ok(None) => return Ok(()),
+        Ok(Some(ns)) => ns,
+        // Registries without namespace support don't have the endpoint at
+        // all, which shouldn't stop a publish they may accept anyway.
+        Err(e) if is_unsupported_endpoint(&e) => {
+            return config.shell().warn(format!(
+                "registry `{}` does not support checking the owners of namespace `{}/`, \
+                 skipping the check",
+                registry_src.display_registry_name(),
+                namespace
+            ));
+        }
+        Err(e) => {
+            return Err(e)
+                .chain_err(|| format!("failed to check the owners of namespace `{}/`", namespace))
+        }
+    };
+    let owners = ns
+        .owners
+        .iter()
+        .map(|o| format!("`{}`", o.login))
+        .collect::<Vec<_>>()
+        .join(", ");
+    match ns.is_owner {
+        Some(true) => Ok(()),
+        Some(false) => bail!(
+            "`{}` cannot be published to registry `{}`\n\
+             The namespace `{}/` is owned by {}, and you are not one of its owners.\n\
+             Ask an owner to add you with `cargo owner --namespace {} --add <login>`.",
+            name,
+            registry_src.display_registry_name(),
+            namespace,
+            owners,
+            namespace
+        ),
+        None if registry.has_token() => config.shell().warn(format!(
+            "could not check whether you own the namespace `{}/` (owned by {}), \
+             registry `{}` did not say whether you are one of its owners",
+            namespace,
+            owners,
+            registry_src.display_registry_name()
+        )),
+        None => config.shell().warn(format!(
+            "could not check whether you own the namespace `{}/` \
+             (owned by {}) without an API token",
+            namespace, owners
+        )),
+    }
+}
+
+/// Whether a failed API request means the registry doesn't implement the
+/// endpoint, rather than that the request itself went wrong. A `404` already
+/// means that nobody owns the namespace.
+fn is_unsupported_endpoint(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<ResponseError>(),
+        Some(r) if r.code() == 405 || r.code() == 501
+    )
+}
+
 fn transmit(
     config: &Config,
     pkg: &Package,
@@ -517,6 +615,14 @@ fn registry(
     Ok((Registry::new_handle(api_host, token, handle), reg_cfg, sid))
 }
 
+/// Loads the namespace policy advertised in the `config.json` of a remote
+/// registry's index.
+fn namespace_policy(config: &Config, sid: SourceId) -> CargoResult<NamespacePolicy> {
+    let _lock = config.acquire_package_cache_lock()?;
+    let mut src = RegistrySource::remote(sid, &HashSet::new(), config);
+    Ok(src.namespace_policy())
+}
+
 /// Creates a new HTTP handle with appropriate global configuration for cargo.
 pub fn http_handle(config: &Config) -> CargoResult<Easy> {
     let (mut handle, timeout) = http_handle_and_timeout(config)?;
@@ -793,6 +899,7 @@ pub fn registry_logout(config: &Config, reg: Option<String>) -> CargoResult<()>
 
 pub struct OwnersOptions {
     pub krate: Option<String>,
+    pub namespace: Option<String>,
     pub token: Option<String>,
     pub index: Option<String>,
     pub to_add: Option<Vec<String>>,
@@ -802,6 +909,10 @@ pub struct OwnersOptions {
 }
 
 pub fn modify_owners(config: &Config, opts: &OwnersOptions) -> CargoResult<()> {
+    if let Some(ref namespace) = opts.namespace {
+        return modify_namespace_owners(config, opts, namespace);
+    }
+
     let name = match opts.krate {
         Some(ref name) => name.clone(),
         None => {
@@ -847,19 +958,80 @@ pub fn modify_owners(config: &Config, opts: &OwnersOptions) -> CargoResult<()> {
         let owners = registry
             .list_owners(&registry_safe_name)
             .chain_err(|| format!("failed to list owners of crate {}", name))?;
-        for owner in owners.iter() {
-            drop_print!(config, "{}", owner.login);
-            match (owner.name.as_ref(), owner.email.as_ref()) {
-                (Some(name), Some(email)) => drop_println!(config, " ({} <{}>)", name, email),
-                (Some(s), None) | (None, Some(s)) => drop_println!(config, " ({})", s),
-                (None, None) => drop_println!(config),
-            }
-        }
+        print_owners(config, &owners);
     }
 
     Ok(())
 }
 
+/// Like `modify_owners`, but for the owners of a whole namespace such as
+/// `foo/` rather than a single crate.
+fn modify_namespace_owners(
+    config: &Config,
+    opts: &OwnersOptions,
+    namespace: &str,
+) -> CargoResult<()> {
+    let namespace = namespace
+        .strip_suffix(SUBCRATE_DELIMETER)
+        .unwrap_or(namespace);
+    if namespace.contains(SUBCRATE_DELIMETER) {
+        bail!(
+            "invalid namespace `{}`: only top-level namespaces have owners",
+            namespace
+        );
+    }
+    validate_package_name(namespace, "namespace", "")?;
+
+    let (mut registry, _, _) = registry(
+        config,
+        opts.token.clone(),
+        opts.index.clone(),
+        opts.registry.clone(),
+        true,
+        true,
+    )?;
+
+    if let Some(ref v) = opts.to_add {
+        let v = v.iter().map(|s| &s[..]).collect::<Vec<_>>();
+        let msg = registry
+            .add_namespace_owners(namespace, &v)
+            .chain_err(|| format!("failed to invite owners to namespace `{}`", namespace))?;
+
+        config.shell().status("Owner", msg)?;
+    }
+
+    if let Some(ref v) = opts.to_remove {
+        let v = v.iter().map(|s| &s[..]).collect::<Vec<_>>();
+        config.shell().status(
+            "Owner",
+            format!("removing {:?} from namespace `{}`", v, namespace),
+        )?;
+        registry
+            .remove_namespace_owners(namespace, &v)
+            .chain_err(|| format!("failed to remove owners from namespace `{}`", namespace))?;
+    }
+
+    if opts.list {
+        let owners = registry
+            .list_namespace_owners(namespace)
+            .chain_err(|| format!("failed to list owners of namespace `{}`", namespace))?;
+        print_owners(config, &owners);
+    }
+
+    Ok(())
+}
+
+fn print_owners(config: &Config, owners: &[crates_io::User]) {
+    for owner in owners.iter() {
+        drop_print!(config, "{}", owner.login);
+        match (owner.name.as_ref(), owner.email.as_ref()) {
+            (Some(name), Some(email)) => drop_println!(config, " ({} <{}>)", name, email),
+            (Some(s), None) | (None, Some(s)) => drop_println!(config, " ({})", s),
+            (None, None) => drop_println!(config),
+        }
+    }
+}
+
 pub fn yank(
     config: &Config,
     krate: Option<String>,
@@ -923,13 +1095,136 @@ fn get_source_id(
     }
 }
 
+/// The most results the registry returns for one search request.
+const SEARCH_MAX_LIMIT: u32 = 100;
+
+/// Searches the registry for `query`.
+///
+/// A query like `foo/` lists the crates in the `foo` namespace instead. This
+/// fetches as many pages as needed to show `limit` crates (all of them by
+/// default), and walks the local copy of the index when offline.
 pub fn search(
     query: &str,
     config: &Config,
     index: Option<String>,
-    limit: u32,
+    limit: Option<u32>,
     reg: Option<String>,
 ) -> CargoResult<()> {
+    let namespace = query
+        .strip_suffix(SUBCRATE_DELIMETER)
+        .filter(|ns| validate_package_name(ns, "namespace", "").is_ok());
+    if let Some(namespace) = namespace {
+        if config.offline() {
+            let crates = search_namespace_offline(config, namespace, index, reg)?;
+            let total_crates = crates.len() as u32;
+            let limit = limit.unwrap_or(total_crates);
+            let crates = crates.into_iter().take(limit as usize).collect::<Vec<_>>();
+            print_search_results(config, &crates);
+            print_more_results(config, total_crates, limit, u32::MAX, None);
+            return Ok(());
+        }
+    }
+
+    let (mut registry, _, source_id) = registry(config, None, index, reg, false, false)?;
+    let (crates, total_crates, limit) = match namespace {
+        Some(namespace) => {
+            let limit = limit.unwrap_or(u32::MAX);
+            let (crates, total_crates) = search_namespace(&mut registry, namespace, limit)
+                .chain_err(|| "failed to retrieve search results from the registry")?;
+            (crates, total_crates, limit)
+        }
+        None => {
+            let limit = cmp::min(SEARCH_MAX_LIMIT, limit.unwrap_or(10));
+            let (crates, total_crates) = registry
+                .search(query, limit)
+                .chain_err(|| "failed to retrieve search results from the registry")?;
+            (crates, total_crates, limit)
+        }
+    };
+
+    print_search_results(config, &crates);
+
+    let more_url = if source_id.is_default_registry() && namespace.is_none() {
+        Some(format!(
+            "https://crates.io/search?q={}",
+            percent_encode(query.as_bytes(), NON_ALPHANUMERIC)
+        ))
+    } else {
+        None
+    };
+    let max_limit = if namespace.is_some() {
+        u32::MAX
+    } else {
+        SEARCH_MAX_LIMIT
+    };
+    print_more_results(config, total_crates, limit, max_limit, more_url);
+
+    Ok(())
+}
+
+/// Fetches up to `limit` crates in `namespace`, a page at a time, along with
+/// the total number of crates in it.
+fn search_namespace(
+    registry: &mut Registry,
+    namespace: &str,
+    limit: u32,
+) -> CargoResult<(Vec<crates_io::Crate>, u32)> {
+    let prefix = format!("{}{}", namespace, SUBCRATE_DELIMETER);
+    let per_page = cmp::min(SEARCH_MAX_LIMIT, limit);
+    let mut crates = Vec::new();
+    let mut total_crates = 0;
+    let mut page = 1;
+    while (crates.len() as u32) < limit {
+        let (results, total) = registry.search_namespace(namespace, per_page, page)?;
+        total_crates = total;
+        if results.is_empty() {
+            break;
+        }
+        // Don't rely on the registry to have filtered by namespace.
+        crates.extend(results.into_iter().filter(|c| c.name.starts_with(&prefix)));
+        if page * per_page >= total {
+            break;
+        }
+        page += 1;
+    }
+    crates.truncate(limit as usize);
+    Ok((crates, total_crates))
+}
+
+/// Lists the crates in `namespace` from the local copy of the index.
+fn search_namespace_offline(
+    config: &Config,
+    namespace: &str,
+    index: Option<String>,
+    reg: Option<String>,
+) -> CargoResult<Vec<crates_io::Crate>> {
+    let sid = get_source_id(config, index.as_ref(), reg.as_ref())?;
+    let _lock = config.acquire_package_cache_lock()?;
+    let mut src = if sid.is_remote_registry() {
+        RegistrySource::remote(sid, &HashSet::new(), config)
+    } else if sid.is_registry() {
+        let path = sid.url().to_file_path().unwrap();
+        RegistrySource::local(sid, &path, &HashSet::new(), config)
+    } else {
+        bail!("{} does not have an index to search", sid);
+    };
+    let members = src.namespace_members(namespace).chain_err(|| {
+        format!(
+            "failed to list namespace `{}{}` from the local copy of {}",
+            namespace, SUBCRATE_DELIMETER, sid
+        )
+    })?;
+    Ok(members
+        .iter()
+        .map(|summary| crates_io::Crate {
+            name: summary.name().to_string(),
+            description: None,
+            max_version: summary.version().to_string(),
+        })
+        .collect())
+}
+
+fn print_search_results(config: &Config, crates: &[crates_io::Crate]) {
     fn truncate_with_ellipsis(s: &str, max_width: usize) -> String {
         // We should truncate at grapheme-boundary and compute character-widths,
         // yet the dependencies on unicode-segmentation and unicode-width are
@@ -942,11 +1237,6 @@ pub fn search(
         prefix
     }
 
-    let (mut registry, _, source_id) = registry(config, None, index, reg, false, false)?;
-    let (crates, total_crates) = registry
-        .search(query, limit)
-        .chain_err(|| "failed to retrieve search results from the registry")?;
-
     let names = crates
         .iter()
         .map(|krate| format!("{} = \"{}\"", krate.name, krate.max_version))
@@ -975,22 +1265,28 @@ pub fn search(
         };
         drop_println!(config, "{}", line);
     }
+}
 
-    let search_max_limit = 100;
-    if total_crates > limit && limit < search_max_limit {
+/// Prints how many of the `total_crates` results weren't shown. A `limit`
+/// below `max_limit` can be raised, otherwise `more_url` is the place to see
+/// the rest, if any.
+fn print_more_results(
+    config: &Config,
+    total_crates: u32,
+    limit: u32,
+    max_limit: u32,
+    more_url: Option<String>,
+) {
+    if total_crates > limit && limit < max_limit {
         drop_println!(
             config,
             "... and {} crates more (use --limit N to see more)",
             total_crates - limit
         );
-    } else if total_crates > limit && limit >= search_max_limit {
-        let extra = if source_id.is_default_registry() {
-            format!(
-                " (go to https://crates.io/search?q={} to see more)",
-                percent_encode(query.as_bytes(), NON_ALPHANUMERIC)
-            )
-        } else {
-            String::new()
+    } else if total_crates > limit {
+        let extra = match more_url {
+            Some(url) => format!(" (go to {} to see more)", url),
+            None => String::new(),
         };
         drop_println!(
             config,
@@ -999,6 +1295,4 @@ pub fn search(
             extra
         );
     }
-
-    Ok(())
 }
@@ -193,12 +193,15 @@ fn sync(
             .expect("manifest_path should point to a file");
         let max_version = *versions[&id.name()].iter().rev().next().unwrap().0;
         let dir_has_version_suffix = opts.versioned_dirs || id.version() != max_version;
+        // Namespaced packages use the same directory names as the registry
+        // cache, so `foo/bar` is vendored in `foo~bar`. Unlike `foo_bar`,
+        // that can't clash with the name of another package.
         let dst_name = if dir_has_version_suffix {
             // Eg vendor/futures-0.1.13
-            format!("{}-{}", id.file_safe_name(), id.version())
+            format!("{}-{}", id.registry_safe_file_name(), id.version())
         } else {
             // Eg vendor/futures
-            id.file_safe_name().to_string()
+            id.registry_safe_file_name().to_string()
         };
 
         sources.insert(id.source_id());
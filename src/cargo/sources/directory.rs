@@ -35,6 +35,18 @@ impl<'cfg> DirectorySource<'cfg> {
     }
 }
 
+/// Returns whether `dir` is named the way `cargo vendor` names the directory
+/// for `id`, such as `foo~bar` or `foo~bar-0.1.0` for `foo/bar`.
+fn is_vendor_dir_for(dir: &Path, id: PackageId) -> bool {
+    let name = id.registry_safe_file_name();
+    match dir.file_name().and_then(|s| s.to_str()) {
+        Some(dir_name) => {
+            dir_name == name.as_str() || dir_name == format!("{}-{}", name, id.version())
+        }
+        None => false,
+    }
+}
+
 impl<'cfg> Debug for DirectorySource<'cfg> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "DirectorySource {{ root: {:?} }}", self.root)
@@ -80,10 +92,12 @@ impl<'cfg> Source for DirectorySource<'cfg> {
             )
         })?;
 
-        for entry in entries {
-            let entry = entry?;
-            let path = entry.path();
+        let mut dirs = entries
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        dirs.sort();
 
+        for path in dirs {
             // Ignore hidden/dot directories as they typically don't contain
             // crates and otherwise may conflict with a VCS
             // (rust-lang/cargo#3414).
@@ -139,7 +153,27 @@ impl<'cfg> Source for DirectorySource<'cfg> {
                     .summary_mut()
                     .set_checksum(package.clone());
             }
-            self.packages.insert(pkg.package_id(), (pkg, cksum));
+
+            // A package can end up in two directories when a vendor directory
+            // made with an older layout is updated with `--no-delete`, for
+            // example `foo_bar` and `foo~bar` for `foo/bar`. Prefer the one
+            // named the way `cargo vendor` names it now.
+            let id = pkg.package_id();
+            if let Some((prev, _)) = self.packages.get(&id) {
+                let replace = is_vendor_dir_for(&path, id) && !is_vendor_dir_for(prev.root(), id);
+                let keep = if replace { path.as_path() } else { prev.root() };
+                self.config.shell().warn(format!(
+                    "package `{}` is in both `{}` and `{}`, using `{}`",
+                    id,
+                    prev.root().display(),
+                    path.display(),
+                    keep.display()
+                ))?;
+                if !replace {
+                    continue;
+                }
+            }
+            self.packages.insert(id, (pkg, cksum));
         }
 
         Ok(())
@@ -0,0 +1,131 @@
+//! Downloading of `.crate` files, shared by the registries that fetch them
+//! over the network.
+
+use crate::core::{manifest::SUBCRATE_DELIMETER, PackageId};
+use crate::sources::registry::{
+    RegistryConfig, CRATE_TEMPLATE, LOWER_PREFIX_TEMPLATE, PREFIX_TEMPLATE, VERSION_TEMPLATE,
+};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{Config, Filesystem, Sha256};
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::path::Path;
+
+fn make_dep_prefix(name: &str) -> String {
+    let namespace = name.split(SUBCRATE_DELIMETER).next().unwrap();
+    match namespace.len() {
+        1 => String::from("1"),
+        2 => String::from("2"),
+        3 => format!("3/{}", &namespace[..1]),
+        _ => format!("{}/{}", &namespace[0..2], &namespace[2..4]),
+    }
+}
+
+/// Returns the `.crate` file of `pkg` in `cache_path`, if it was already
+/// downloaded.
+pub fn downloaded(cache_path: &Filesystem, config: &Config, pkg: PackageId) -> Option<File> {
+    // Attempt to open an read-only copy first to avoid an exclusive write
+    // lock and also work with read-only filesystems. Note that we check the
+    // length of the file like below to handle interrupted downloads.
+    //
+    // If this fails then we fall through to the exclusive path where we may
+    // have to redownload the file.
+    let path = cache_path.join(pkg.crate_file_name());
+    let path = config.assert_package_cache_locked(&path);
+    let dst = File::open(path).ok()?;
+    let meta = dst.metadata().ok()?;
+    if meta.len() > 0 {
+        Some(dst)
+    } else {
+        None
+    }
+}
+
+/// Returns the URL to download `pkg` from, according to the `dl` template of
+/// the registry's `config.json`.
+pub fn url(registry_config: &RegistryConfig, pkg: PackageId) -> String {
+    let mut url = registry_config.dl.clone();
+    if !url.contains(CRATE_TEMPLATE)
+        && !url.contains(VERSION_TEMPLATE)
+        && !url.contains(PREFIX_TEMPLATE)
+        && !url.contains(LOWER_PREFIX_TEMPLATE)
+    {
+        write!(url, "/{}/{}/download", CRATE_TEMPLATE, VERSION_TEMPLATE).unwrap();
+    }
+    let prefix = make_dep_prefix(&*pkg.name());
+    url.replace(CRATE_TEMPLATE, &*pkg.registry_safe_file_name())
+        .replace(VERSION_TEMPLATE, &pkg.version().to_string())
+        .replace(PREFIX_TEMPLATE, &prefix)
+        .replace(LOWER_PREFIX_TEMPLATE, &prefix.to_lowercase())
+}
+
+/// Verifies the downloaded `data` of `pkg` and saves it in `cache_path`.
+pub fn finish(
+    cache_path: &Filesystem,
+    config: &Config,
+    pkg: PackageId,
+    checksum: &str,
+    data: &[u8],
+) -> CargoResult<File> {
+    // Verify what we just downloaded
+    let actual = Sha256::new().update(data).finish_hex();
+    if actual != checksum {
+        anyhow::bail!("failed to verify the checksum of `{}`", pkg)
+    }
+
+    cache_path.create_dir()?;
+    let path = cache_path.join(&pkg.crate_file_name());
+    let path = config.assert_package_cache_locked(&path);
+    let mut dst = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .open(&path)
+        .chain_err(|| format!("failed to open `{}`", path.display()))?;
+    let meta = dst.metadata()?;
+    if meta.len() > 0 {
+        return Ok(dst);
+    }
+
+    dst.write_all(data)?;
+    dst.seek(SeekFrom::Start(0))?;
+    Ok(dst)
+}
+
+pub fn is_crate_downloaded(cache_path: &Filesystem, config: &Config, pkg: PackageId) -> bool {
+    let filename = pkg.crate_file_name();
+    let path = Path::new(&filename);
+
+    let path = cache_path.join(path);
+    let path = config.assert_package_cache_locked(&path);
+    if let Ok(meta) = fs::metadata(path) {
+        return meta.len() > 0;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::make_dep_prefix;
+    use crate::core::manifest::SUBCRATE_DELIMETER;
+
+    #[test]
+    fn dep_prefix() {
+        assert_eq!(make_dep_prefix("a"), "1");
+        assert_eq!(make_dep_prefix("ab"), "2");
+        assert_eq!(make_dep_prefix("abc"), "3/a");
+        assert_eq!(make_dep_prefix("Abc"), "3/A");
+        assert_eq!(make_dep_prefix("AbCd"), "Ab/Cd");
+        assert_eq!(make_dep_prefix("aBcDe"), "aB/cD");
+    }
+
+    #[test]
+    fn dep_prefix_with_subcrates() {
+        assert_eq!(
+            make_dep_prefix(&format!("foo{}bar", SUBCRATE_DELIMETER)),
+            "3/f"
+        );
+    }
+}
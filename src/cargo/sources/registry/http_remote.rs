@@ -0,0 +1,545 @@
+//! Access to a sparse registry index over HTTP, enabled with
+//! `-Z sparse-registry`.
+//!
+//! A sparse index has the same layout as a git index (see `registry/mod.rs`),
+//! but is served by a plain HTTP server from a `sparse+http(s)://` URL.
+//! Instead of cloning the whole index, Cargo fetches the file of each crate
+//! the resolver asks about, at the path given by `package_index_path`.
+//!
+//! Fetched files go into the same `.cache` files as those of git indexes.
+//! Since there is no commit to version the whole index with, the version of
+//! each cache file is the `ETag` or `Last-Modified` header the server sent
+//! with it, and once `update_index` is called, cached files are revalidated
+//! with conditional requests. Until then, or when offline, cached files are
+//! used as they are, like a git index that wasn't fetched.
+//!
+//! The resolver only asks about one crate at a time, so to avoid fetching
+//! files one by one, every fetched file also starts fetching the files of the
+//! dependencies it lists, all on one curl `Multi` handle. Waiting for any
+//! file makes progress on all of them. A prefetched file may never be
+//! needed, so if its transfer fails, the error is only returned once the
+//! resolver asks about it.
+
+use crate::core::{PackageId, SourceId};
+use crate::ops;
+use crate::sources::registry::download;
+use crate::sources::registry::index::{self, package_index_path};
+use crate::sources::registry::{MaybeLock, RegistryConfig, RegistryData, RegistryPackage};
+use crate::util::errors::{CargoResult, CargoResultExt, HttpNot200};
+use crate::util::interning::InternedString;
+use crate::util::network::Retry;
+use crate::util::{paths, Config, Filesystem};
+use curl::easy::List;
+use curl::multi::{EasyHandle, Multi};
+use log::debug;
+use std::cell::{RefCell, RefMut};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Prefix of the index versions of files that came with an `ETag` header.
+const ETAG: &str = "etag: ";
+/// Prefix of the index versions of files that came with a `Last-Modified`
+/// header but no `ETag`.
+const LAST_MODIFIED: &str = "last-modified: ";
+
+const CONFIG_FILE: &str = "config.json";
+
+pub struct HttpRegistry<'cfg> {
+    index_path: Filesystem,
+    cache_path: Filesystem,
+    source_id: SourceId,
+    config: &'cfg Config,
+    /// The URL of the index, without the `sparse+` prefix or a trailing `/`.
+    url: String,
+    /// Whether `update_index` was called, after which cached files are
+    /// revalidated with the server before they're used.
+    requested_update: bool,
+    /// What the server said about the files fetched in this session.
+    fetched: RefCell<HashMap<PathBuf, Fetched>>,
+    /// Created when the first file is fetched.
+    downloads: RefCell<Option<Downloads<'cfg>>>,
+}
+
+/// The outcome of fetching an index file.
+enum Fetched {
+    /// The cached copy, at this version, is up to date.
+    NotModified(InternedString),
+    /// The file was downloaded. `version` is `None` if the server sent
+    /// neither an `ETag` nor a `Last-Modified` header, so the file can't be
+    /// revalidated later.
+    Modified {
+        version: Option<InternedString>,
+        data: Vec<u8>,
+    },
+    /// The file doesn't exist, so neither does the crate.
+    Missing,
+    /// The transfer failed. Since a prefetched file may never be needed, the
+    /// error is only returned once the file is asked for.
+    Failed(anyhow::Error),
+}
+
+impl Fetched {
+    fn version(&self) -> Option<InternedString> {
+        match self {
+            Fetched::NotModified(version) => Some(*version),
+            Fetched::Modified { version, .. } => *version,
+            Fetched::Missing | Fetched::Failed(_) => None,
+        }
+    }
+}
+
+/// The transfers in progress.
+struct Downloads<'cfg> {
+    multi: Multi,
+    next: usize,
+    pending: HashMap<usize, (Transfer<'cfg>, EasyHandle)>,
+    /// The paths in `pending`, so a file is never fetched twice at once.
+    pending_paths: HashSet<PathBuf>,
+    /// Finished transfers that curl reported but weren't handled yet.
+    results: Vec<(usize, Result<(), curl::Error>)>,
+}
+
+struct Transfer<'cfg> {
+    path: PathBuf,
+    url: String,
+    /// The version of the cached copy, sent along to only get the file back
+    /// if it changed.
+    cached_version: Option<InternedString>,
+    /// Filled in by curl's callbacks.
+    response: Arc<Mutex<Response>>,
+    retry: Retry<'cfg>,
+}
+
+#[derive(Default)]
+struct Response {
+    data: Vec<u8>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Response {
+    fn version(&self) -> Option<InternedString> {
+        let version = match (&self.etag, &self.last_modified) {
+            (Some(etag), _) => format!("{}{}", ETAG, etag),
+            (None, Some(last_modified)) => format!("{}{}", LAST_MODIFIED, last_modified),
+            (None, None) => return None,
+        };
+        Some(InternedString::new(&version))
+    }
+}
+
+impl<'cfg> HttpRegistry<'cfg> {
+    pub fn new(source_id: SourceId, config: &'cfg Config, name: &str) -> HttpRegistry<'cfg> {
+        let url = source_id.url().as_str();
+        let url = url.strip_prefix("sparse+").unwrap_or(url);
+        HttpRegistry {
+            index_path: config.registry_index_path().join(name),
+            cache_path: config.registry_cache_path().join(name),
+            source_id,
+            config,
+            url: url.trim_end_matches('/').to_string(),
+            requested_update: false,
+            fetched: RefCell::new(HashMap::new()),
+            downloads: RefCell::new(None),
+        }
+    }
+
+    /// Whether a file has to be fetched from the server, given whether there
+    /// is a copy in the cache.
+    fn needs_fetch(&self, cached: bool) -> bool {
+        self.config.network_allowed() && (self.requested_update || !cached)
+    }
+
+    fn downloads(&self) -> CargoResult<RefMut<'_, Downloads<'cfg>>> {
+        let mut downloads = self.downloads.borrow_mut();
+        if downloads.is_none() {
+            // Like package downloads, use HTTP/2 multiplexing if it's enabled
+            // and don't flood the server with connections.
+            let mut multi = Multi::new();
+            let multiplexing = self.config.http_config()?.multiplexing.unwrap_or(true);
+            multi
+                .pipelining(false, multiplexing)
+                .chain_err(|| "failed to enable multiplexing/pipelining in curl")?;
+            multi.set_max_host_connections(2)?;
+            *downloads = Some(Downloads {
+                multi,
+                next: 0,
+                pending: HashMap::new(),
+                pending_paths: HashSet::new(),
+                results: Vec::new(),
+            });
+        }
+        Ok(RefMut::map(downloads, |d| d.as_mut().unwrap()))
+    }
+
+    /// Fetches `path`, blocking until it's done. Other transfers, such as
+    /// prefetched files, make progress in the meantime.
+    fn fetch(&self, path: &Path, cached_version: Option<InternedString>) -> CargoResult<()> {
+        let mut downloads = self.downloads()?;
+        self.start(&mut downloads, path, cached_version)?;
+        while !self.fetched.borrow().contains_key(path) {
+            let (token, result) = downloads.wait_for_curl()?;
+            self.finish(&mut downloads, token, result)?;
+        }
+        self.take_error(path)
+    }
+
+    /// Returns the error of the transfer of `path` if it failed, forgetting
+    /// about it so that the file is fetched again if it's asked for again.
+    fn take_error(&self, path: &Path) -> CargoResult<()> {
+        let mut fetched = self.fetched.borrow_mut();
+        if !matches!(fetched.get(path), Some(Fetched::Failed(_))) {
+            return Ok(());
+        }
+        match fetched.remove(path) {
+            Some(Fetched::Failed(e)) => Err(e),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Starts fetching `path` unless it's already being fetched.
+    fn start(
+        &self,
+        downloads: &mut Downloads<'cfg>,
+        path: &Path,
+        cached_version: Option<InternedString>,
+    ) -> CargoResult<()> {
+        if !downloads.pending_paths.insert(path.to_path_buf()) {
+            return Ok(());
+        }
+        let url = format!("{}/{}", self.url, path.display());
+        debug!("fetching {}", url);
+        let transfer = Transfer {
+            path: path.to_path_buf(),
+            url,
+            cached_version,
+            response: Arc::new(Mutex::new(Response::default())),
+            retry: Retry::new(self.config)?,
+        };
+        downloads.enqueue(self.config, transfer)
+    }
+
+    /// Handles the end of the transfer `token`, retrying it if it failed in a
+    /// way that may be spurious.
+    fn finish(
+        &self,
+        downloads: &mut Downloads<'cfg>,
+        token: usize,
+        result: Result<(), curl::Error>,
+    ) -> CargoResult<()> {
+        let (mut transfer, handle) = downloads
+            .pending
+            .remove(&token)
+            .expect("got a token for a non-in-progress transfer");
+        let handle = downloads.multi.remove(handle)?;
+        let response = mem::take(&mut *transfer.response.lock().unwrap());
+        let cached_version = transfer.cached_version;
+        let url = &transfer.url;
+        let fetched = match transfer
+            .retry
+            .r#try(|| {
+                result?;
+                Ok(match (handle.response_code()?, cached_version) {
+                    (200, _) => Fetched::Modified {
+                        version: response.version(),
+                        data: response.data,
+                    },
+                    (304, Some(version)) => Fetched::NotModified(version),
+                    (404, _) | (410, _) | (451, _) => Fetched::Missing,
+                    (code, _) => {
+                        let url = handle.effective_url()?.unwrap_or(url);
+                        return Err(HttpNot200 {
+                            code,
+                            url: url.to_string(),
+                        }
+                        .into());
+                    }
+                })
+            })
+            .chain_err(|| format!("failed to fetch `{}`", transfer.url))
+        {
+            Ok(Some(fetched)) => fetched,
+            Ok(None) => return downloads.enqueue(self.config, transfer),
+            Err(e) => Fetched::Failed(e),
+        };
+        debug!("fetched {}", transfer.url);
+        downloads.pending_paths.remove(&transfer.path);
+        if let Fetched::Modified { data, .. } = &fetched {
+            if transfer.path != Path::new(CONFIG_FILE) {
+                self.prefetch(downloads, data)?;
+            }
+        }
+        self.fetched.borrow_mut().insert(transfer.path, fetched);
+        Ok(())
+    }
+
+    /// Starts fetching the files of the dependencies listed in `data`, the
+    /// contents of an index file, since the resolver is likely to ask about
+    /// them next. Dev-dependencies aren't needed to build a crate from a
+    /// registry, so they're skipped.
+    fn prefetch(&self, downloads: &mut Downloads<'cfg>, data: &[u8]) -> CargoResult<()> {
+        let mut names = HashSet::new();
+        for line in data.split(|&b| b == b'\n') {
+            let package = match serde_json::from_slice::<RegistryPackage<'_>>(line) {
+                Ok(package) => package,
+                Err(_) => continue,
+            };
+            for dep in package.deps {
+                if dep.registry.is_none() && dep.kind.as_deref() != Some("dev") {
+                    names.insert(dep.package.unwrap_or(dep.name));
+                }
+            }
+        }
+        let cache_root = self
+            .config
+            .assert_package_cache_locked(&self.index_path)
+            .join(".cache");
+        for name in names {
+            let path = PathBuf::from(package_index_path(&name));
+            if self.fetched.borrow().contains_key(&path) {
+                continue;
+            }
+            let cached_version = index::cache_version(&cache_root.join(&path));
+            if self.needs_fetch(cached_version.is_some()) {
+                let cached_version = cached_version.map(|v| InternedString::new(&v));
+                self.start(downloads, &path, cached_version)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'cfg> Downloads<'cfg> {
+    fn enqueue(&mut self, config: &Config, transfer: Transfer<'cfg>) -> CargoResult<()> {
+        let mut handle = ops::http_handle(config)?;
+        handle.get(true)?;
+        handle.url(&transfer.url)?;
+        handle.follow_location(true)?;
+
+        let mut headers = List::new();
+        if let Some(version) = transfer.cached_version {
+            if let Some(etag) = version.strip_prefix(ETAG) {
+                headers.append(&format!("If-None-Match: {}", etag))?;
+            } else if let Some(last_modified) = version.strip_prefix(LAST_MODIFIED) {
+                headers.append(&format!("If-Modified-Since: {}", last_modified))?;
+            }
+        }
+        handle.http_headers(headers)?;
+
+        let response = transfer.response.clone();
+        handle.write_function(move |buf| {
+            response.lock().unwrap().data.extend_from_slice(buf);
+            Ok(buf.len())
+        })?;
+        let response = transfer.response.clone();
+        handle.header_function(move |header| {
+            let header = match str::from_utf8(header) {
+                Ok(header) => header,
+                Err(_) => return true,
+            };
+            let mut response = response.lock().unwrap();
+            // Each response of a redirect starts with its status line.
+            if header.starts_with("HTTP/") {
+                *response = Response::default();
+                return true;
+            }
+            let mut parts = header.splitn(2, ':');
+            let name = parts.next().unwrap().trim();
+            let value = parts.next().unwrap_or("").trim();
+            if name.eq_ignore_ascii_case("etag") {
+                response.etag = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("last-modified") {
+                response.last_modified = Some(value.to_string());
+            }
+            true
+        })?;
+
+        let token = self.next;
+        self.next += 1;
+        let mut handle = self.multi.add(handle)?;
+        handle.set_token(token)?;
+        self.pending.insert(token, (transfer, handle));
+        Ok(())
+    }
+
+    /// Blocks until a transfer finishes, returning its token and whether it
+    /// succeeded.
+    fn wait_for_curl(&mut self) -> CargoResult<(usize, Result<(), curl::Error>)> {
+        loop {
+            if let Some(pair) = self.results.pop() {
+                return Ok(pair);
+            }
+            let n = self
+                .multi
+                .perform()
+                .chain_err(|| "failed to perform http requests")?;
+            debug!("handles remaining: {}", n);
+            let results = &mut self.results;
+            let pending = &self.pending;
+            self.multi.messages(|msg| {
+                let token = msg.token().expect("failed to read token");
+                let handle = &pending[&token].1;
+                if let Some(result) = msg.result_for(handle) {
+                    results.push((token, result));
+                } else {
+                    debug!("message without a result (?)");
+                }
+            });
+            if !self.results.is_empty() {
+                continue;
+            }
+            assert!(!self.pending.is_empty());
+            let timeout = self
+                .multi
+                .get_timeout()?
+                .unwrap_or_else(|| Duration::new(5, 0));
+            self.multi
+                .wait(&mut [], timeout)
+                .chain_err(|| "failed to wait on curl `Multi`")?;
+        }
+    }
+}
+
+impl<'cfg> RegistryData for HttpRegistry<'cfg> {
+    fn prepare(&self) -> CargoResult<()> {
+        Ok(())
+    }
+
+    fn index_path(&self) -> &Filesystem {
+        &self.index_path
+    }
+
+    fn assert_index_locked<'a>(&self, path: &'a Filesystem) -> &'a Path {
+        self.config.assert_package_cache_locked(path)
+    }
+
+    fn current_version(&self) -> Option<InternedString> {
+        None
+    }
+
+    fn file_version(
+        &self,
+        path: &Path,
+        cached_version: Option<&str>,
+    ) -> CargoResult<Option<InternedString>> {
+        self.take_error(path)?;
+        if let Some(fetched) = self.fetched.borrow().get(path) {
+            return Ok(fetched.version());
+        }
+        let cached_version = cached_version.map(InternedString::new);
+        if !self.needs_fetch(cached_version.is_some()) {
+            return Ok(cached_version);
+        }
+        self.fetch(path, cached_version)?;
+        Ok(self.fetched.borrow()[path].version())
+    }
+
+    fn load(
+        &self,
+        _root: &Path,
+        path: &Path,
+        data: &mut dyn FnMut(&[u8]) -> CargoResult<()>,
+    ) -> CargoResult<()> {
+        // `file_version` has already downloaded the file unless the cached
+        // copy was supposed to be used, in which case that copy turned out
+        // to be unreadable and has to be replaced.
+        self.take_error(path)?;
+        let downloaded = matches!(
+            self.fetched.borrow().get(path),
+            Some(Fetched::Modified { .. }) | Some(Fetched::Missing)
+        );
+        if !downloaded {
+            if !self.config.network_allowed() {
+                anyhow::bail!("`{}` is not in the cache", path.display());
+            }
+            self.fetched.borrow_mut().remove(path);
+            self.fetch(path, None)?;
+        }
+        match &self.fetched.borrow()[path] {
+            Fetched::Modified { data: contents, .. } => data(contents),
+            _ => anyhow::bail!("`{}` does not exist in the index", path.display()),
+        }
+    }
+
+    fn config(&mut self) -> CargoResult<Option<RegistryConfig>> {
+        debug!("loading config");
+        let path = Path::new(CONFIG_FILE);
+        let local = self
+            .config
+            .assert_package_cache_locked(&self.index_path)
+            .join(path);
+        if !self.fetched.borrow().contains_key(path) && self.needs_fetch(local.exists()) {
+            self.fetch(path, None)?;
+            match &self.fetched.borrow()[path] {
+                Fetched::Modified { data, .. } => {
+                    paths::create_dir_all(local.parent().unwrap())?;
+                    paths::write(&local, data)?;
+                }
+                _ => return Ok(None),
+            }
+        }
+        match paths::read_bytes(&local) {
+            Ok(json) => Ok(Some(serde_json::from_slice(&json)?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn update_index(&mut self) -> CargoResult<()> {
+        if self.config.offline() {
+            return Ok(());
+        }
+        if self.config.cli_unstable().no_index_update {
+            return Ok(());
+        }
+        // Files fetched before stay fresh for the rest of the session.
+        if self.requested_update || self.config.updated_sources().contains(&self.source_id) {
+            return Ok(());
+        }
+
+        // Like for git indexes, check the HTTP configuration before anything
+        // is fetched.
+        self.config.http()?;
+
+        self.config
+            .shell()
+            .status("Updating", self.source_id.display_index())?;
+        self.requested_update = true;
+        self.config.updated_sources().insert(self.source_id);
+        Ok(())
+    }
+
+    fn download(&mut self, pkg: PackageId, _checksum: &str) -> CargoResult<MaybeLock> {
+        if let Some(dst) = download::downloaded(&self.cache_path, self.config, pkg) {
+            return Ok(MaybeLock::Ready(dst));
+        }
+        let config = self.config()?.ok_or_else(|| {
+            anyhow::format_err!(
+                "the {} has no `{}`",
+                self.source_id.display_index(),
+                CONFIG_FILE
+            )
+        })?;
+        Ok(MaybeLock::Download {
+            url: download::url(&config, pkg),
+            descriptor: pkg.to_string(),
+        })
+    }
+
+    fn finish_download(
+        &mut self,
+        pkg: PackageId,
+        checksum: &str,
+        data: &[u8],
+    ) -> CargoResult<File> {
+        download::finish(&self.cache_path, self.config, pkg, checksum, data)
+    }
+
+    fn is_crate_downloaded(&self, pkg: PackageId) -> bool {
+        download::is_crate_downloaded(&self.cache_path, self.config, pkg)
+    }
+}
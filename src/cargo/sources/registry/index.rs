@@ -78,6 +78,7 @@ use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 use std::str;
+use std::time::SystemTime;
 
 /// Crates.io treats hyphen and underscores as interchangeable, but the index and old Cargo do not.
 /// Therefore, the index must store uncanonicalized version of the name so old Cargo's can find it.
@@ -211,10 +212,12 @@ enum MaybeIndexSummary {
 
 /// A parsed representation of a summary from the index.
 ///
-/// In addition to a full `Summary` we have information on whether it is `yanked`.
+/// In addition to a full `Summary` we have information on whether it is
+/// `yanked`, and when it was published if the registry told us.
 pub struct IndexSummary {
     pub summary: Summary,
     pub yanked: bool,
+    pub pubtime: Option<SystemTime>,
 }
 
 /// A representation of the cache on disk that Cargo maintains of summaries.
@@ -329,24 +332,7 @@ impl<'cfg> RegistryIndex<'cfg> {
 
         // See module comment in `registry/mod.rs` for why this is structured
         // the way it is.
-        let fs_name = name
-            .chars()
-            .flat_map(|c| c.to_lowercase())
-            .collect::<String>();
-
-        let fs_namespace = fs_name.split(SUBCRATE_DELIMETER).next().unwrap();
-        let fs_crate_part = fs_name.replace(SUBCRATE_DELIMETER, "@/");
-        let raw_path = match fs_namespace.len() {
-            1 => format!("1/{}", fs_crate_part),
-            2 => format!("2/{}", fs_crate_part),
-            3 => format!("3/{}/{}", &fs_namespace[..1], fs_crate_part),
-            _ => format!(
-                "{}/{}/{}",
-                &fs_namespace[0..2],
-                &fs_namespace[2..4],
-                fs_crate_part
-            ),
-        };
+        let raw_path = package_index_path(&name);
 
         // Attempt to handle misspellings by searching for a chain of related
         // names to the original `raw_path` name. Only return summaries
@@ -375,6 +361,42 @@ impl<'cfg> RegistryIndex<'cfg> {
         Ok(self.summaries_cache.get_mut(&name).unwrap())
     }
 
+    /// Lists the names of all crates in `namespace`, including those in
+    /// namespaces nested inside of it, by walking the directories of the
+    /// index. The names are lowercase, as they are on disk.
+    pub fn namespace_members(
+        &mut self,
+        namespace: &str,
+        load: &mut dyn RegistryData,
+    ) -> CargoResult<Vec<InternedString>> {
+        load.prepare()?;
+        let root = load.assert_index_locked(&self.path);
+        let namespace = namespace.to_lowercase();
+        let mut names = Vec::new();
+        let mut pending = vec![namespace];
+        while let Some(namespace) = pending.pop() {
+            // The directory holding the crates of `foo` is the path of a
+            // crate named `foo/` without the trailing `/`.
+            let dir = index_file_path(&format!("{}{}", namespace, SUBCRATE_DELIMETER));
+            let dir = dir.trim_end_matches('/');
+            for (entry, is_dir) in load.list_dir(root, Path::new(dir))? {
+                if entry.starts_with('.') {
+                    continue;
+                }
+                if is_dir {
+                    if let Some(nested) = entry.strip_suffix('@') {
+                        pending.push(format!("{}{}{}", namespace, SUBCRATE_DELIMETER, nested));
+                    }
+                } else {
+                    let name = format!("{}{}{}", namespace, SUBCRATE_DELIMETER, entry);
+                    names.push(InternedString::new(&name));
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
     pub fn query_inner(
         &mut self,
         dep: &Dependency,
@@ -408,6 +430,9 @@ impl<'cfg> RegistryIndex<'cfg> {
         online: bool,
     ) -> CargoResult<usize> {
         let source_id = self.source_id;
+        let config = self.config;
+        let max_age = config.registry_max_age()?;
+        let now = SystemTime::now();
         let summaries = self
             .summaries(dep.package_name(), dep.version_req(), load)?
             // First filter summaries for `--offline`. If we're online then
@@ -425,6 +450,25 @@ impl<'cfg> RegistryIndex<'cfg> {
             // leak throguh if they're in a whitelist (aka if they were
             // previously in `Cargo.lock`
             .filter(|s| !s.yanked || yanked_whitelist.contains(&s.summary.package_id()))
+            // Then skip anything published more recently than
+            // `registry.max-age` allows, again unless it was already locked.
+            // Skipped versions are remembered so a resolution failure can
+            // point at them.
+            .filter(|s| {
+                let pubtime = match (max_age, s.pubtime) {
+                    (Some(max_age), Some(pubtime)) => match now.duration_since(pubtime) {
+                        Ok(age) if age >= max_age => return true,
+                        _ => pubtime,
+                    },
+                    _ => return true,
+                };
+                let pkg_id = s.summary.package_id();
+                if yanked_whitelist.contains(&pkg_id) {
+                    return true;
+                }
+                config.max_age_skipped().insert(pkg_id, pubtime);
+                false
+            })
             .map(|s| s.summary.clone());
 
         // Handle `cargo update --precise` here. If specified, our own source
@@ -465,6 +509,46 @@ impl<'cfg> RegistryIndex<'cfg> {
     }
 }
 
+/// The path of the index file for the package `name`, relative to the root of
+/// the index.
+///
+/// Index lookups are case-insensitive, so the path is always lowercase. This
+/// is also used by `cargo index` to lay out and check generated indexes.
+pub fn package_index_path(name: &str) -> String {
+    let fs_name = name
+        .chars()
+        .flat_map(|c| c.to_lowercase())
+        .collect::<String>();
+    index_file_path(&fs_name)
+}
+
+/// The path of the index file for the crate `fs_name`, relative to the root of
+/// the index. The first component of a namespaced name picks the directory,
+/// and each namespace is a directory ending in `@`, so `foo/bar` is at
+/// `3/f/foo@/bar`. See the module comment in `registry/mod.rs`.
+fn index_file_path(fs_name: &str) -> String {
+    let fs_namespace = fs_name.split(SUBCRATE_DELIMETER).next().unwrap();
+    let fs_crate_part = fs_name.replace(SUBCRATE_DELIMETER, "@/");
+    match fs_namespace.len() {
+        1 => format!("1/{}", fs_crate_part),
+        2 => format!("2/{}", fs_crate_part),
+        3 => format!("3/{}/{}", &fs_namespace[..1], fs_crate_part),
+        _ => format!(
+            "{}/{}/{}",
+            &fs_namespace[0..2],
+            &fs_namespace[2..4],
+            fs_crate_part
+        ),
+    }
+}
+
+/// Returns the index version of the cache file at `cache_path`, if there is a
+/// readable one.
+pub fn cache_version(cache_path: &Path) -> Option<String> {
+    let contents = fs::read(cache_path).ok()?;
+    SummariesCache::index_version(&contents).map(|version| version.to_string())
+}
+
 impl Summaries {
     /// Parse out a `Summaries` instances from on-disk state.
     ///
@@ -477,7 +561,8 @@ impl Summaries {
     ///
     /// * `index_version` - a version string to describe the current state of
     ///   the index which for remote registries is the current git sha and
-    ///   for local registries is not available.
+    ///   for local and sparse registries is not available. Sparse registries
+    ///   version each file instead, see `RegistryData::file_version`.
     /// * `root` - this is the root argument passed to `load`
     /// * `cache_root` - this is the root on the filesystem itself of where to
     ///   store cache files.
@@ -500,13 +585,33 @@ impl Summaries {
         // of reasons, but consider all of them non-fatal and just log their
         // occurrence in case anyone is debugging anything.
         let cache_path = cache_root.join(relative);
+        let cache = fs::read(&cache_path);
+
+        // Indexes without a version as a whole may version each file instead,
+        // which can mean asking a server whether the cached copy is current.
+        let file_version = match index_version {
+            Some(_) => None,
+            None => {
+                let cached_version = cache
+                    .as_ref()
+                    .ok()
+                    .and_then(|contents| SummariesCache::index_version(contents));
+                load.file_version(relative, cached_version)?
+            }
+        };
+        let index_version = index_version.or_else(|| file_version.as_deref());
+
+        // Checking a fresh cache against the index is only cheap enough when
+        // the index is versioned as a whole, otherwise it means downloading
+        // the file again.
+        let verify_cache = cfg!(debug_assertions) && file_version.is_none();
         let mut cache_contents = None;
         if let Some(index_version) = index_version {
-            match fs::read(&cache_path) {
+            match cache {
                 Ok(contents) => match Summaries::parse_cache(contents, index_version) {
                     Ok(s) => {
                         log::debug!("fast path for registry cache of {:?}", relative);
-                        if cfg!(debug_assertions) {
+                        if verify_cache {
                             cache_contents = Some(s.raw_data);
                         } else {
                             return Ok(Some(s));
@@ -567,7 +672,7 @@ impl Summaries {
         // present and considered fresh this is where the debug assertions
         // actually happens to verify that our cache is indeed fresh and
         // computes exactly the same value as before.
-        if cfg!(debug_assertions) && cache_contents.is_some() {
+        if verify_cache && cache_contents.is_some() {
             assert_eq!(cache_bytes, cache_contents);
         }
 
@@ -673,6 +778,15 @@ impl<'a> SummariesCache<'a> {
         Ok(ret)
     }
 
+    /// Returns the index version a cache file was written for, without
+    /// parsing the rest of it.
+    fn index_version(data: &[u8]) -> Option<&str> {
+        match data.split_first() {
+            Some((&CURRENT_CACHE_VERSION, rest)) => str::from_utf8(split(rest, 0).next()?).ok(),
+            _ => None,
+        }
+    }
+
     fn serialize(&self, index_version: &str) -> Vec<u8> {
         // NB: keep this method in sync with `parse` above
         let size = self
@@ -739,6 +853,7 @@ impl IndexSummary {
             features,
             yanked,
             links,
+            pubtime,
         } = serde_json::from_slice(line)?;
         log::trace!("json parsed registry {}/{}", name, vers);
         let pkgid = PackageId::new(name, &vers, source_id)?;
@@ -748,9 +863,19 @@ impl IndexSummary {
             .collect::<CargoResult<Vec<_>>>()?;
         let mut summary = Summary::new(config, pkgid, deps, &features, links)?;
         summary.set_checksum(cksum);
+        // An unreadable publish time shouldn't make the whole version
+        // unavailable, so treat it the same as a missing one.
+        let pubtime = pubtime.and_then(|t| match humantime::parse_rfc3339_weak(&t) {
+            Ok(t) => Some(t),
+            Err(e) => {
+                log::debug!("invalid pubtime `{}` for {}: {}", t, pkgid, e);
+                None
+            }
+        });
         Ok(IndexSummary {
             summary,
             yanked: yanked.unwrap_or(false),
+            pubtime,
         })
     }
 }
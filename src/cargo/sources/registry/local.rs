@@ -1,6 +1,6 @@
 use crate::core::PackageId;
 use crate::sources::registry::{MaybeLock, RegistryConfig, RegistryData};
-use crate::util::errors::CargoResult;
+use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::interning::InternedString;
 use crate::util::paths;
 use crate::util::{Config, Filesystem, Sha256};
@@ -55,6 +55,20 @@ impl<'cfg> RegistryData for LocalRegistry<'cfg> {
         data(&paths::read_bytes(&root.join(path))?)
     }
 
+    fn list_dir(&self, root: &Path, path: &Path) -> CargoResult<Vec<(String, bool)>> {
+        let dir = root.join(path);
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut entries = Vec::new();
+        for entry in dir.read_dir()? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            entries.push((name, entry.file_type()?.is_dir()));
+        }
+        Ok(entries)
+    }
+
     fn config(&mut self) -> CargoResult<Option<RegistryConfig>> {
         // Local registries don't have configuration for remote APIs or anything
         // like that
@@ -80,13 +94,18 @@ impl<'cfg> RegistryData for LocalRegistry<'cfg> {
     }
 
     fn download(&mut self, pkg: PackageId, checksum: &str) -> CargoResult<MaybeLock> {
-        let crate_file = format!("{}-{}.crate", pkg.registry_safe_file_name(), pkg.version());
+        let crate_file = pkg.crate_file_name();
 
         // Note that the usage of `into_path_unlocked` here is because the local
         // crate files here never change in that we're not the one writing them,
         // so it's not our responsibility to synchronize access to them.
         let path = self.root.join(&crate_file).into_path_unlocked();
-        let mut crate_file = paths::open(&path)?;
+        let mut crate_file = paths::open(&path).chain_err(|| {
+            format!(
+                "failed to find `{}` for `{}` in the local registry",
+                crate_file, pkg
+            )
+        })?;
 
         // If we've already got an unpacked version of this crate, then skip the
         // checksum below as it is in theory already verified.
@@ -85,7 +85,8 @@
 //! ```
 //!
 //! The root of the index contains a `config.json` file with a few entries
-//! corresponding to the registry (see `RegistryConfig` below).
+//! corresponding to the registry (see `RegistryConfig` below), including the
+//! rules it applies to namespaced crate names.
 //!
 //! Otherwise, there are three numbered directories (1, 2, 3) for crates with
 //! names 1, 2, and 3 characters in length. The 1/2 directories simply have the
@@ -109,6 +110,14 @@
 //! modifications to this file that should happen over time are yanks of a
 //! particular version.
 //!
+//! ## Sparse indexes
+//!
+//! With `-Z sparse-registry`, an index whose URL starts with `sparse+` is not
+//! a git repository but the same hierarchy of files served over HTTP. Rather
+//! than cloning the whole index, Cargo fetches the files of the crates it
+//! needs, revalidating its cached copies with the `ETag` or `Last-Modified`
+//! headers of the server. See `http_remote.rs`.
+//!
 //! # Downloading Packages
 //!
 //! The purpose of the Index was to provide an efficient method to resolve the
@@ -179,7 +188,8 @@ use crate::util::errors::CargoResultExt;
 use crate::util::hex;
 use crate::util::interning::InternedString;
 use crate::util::into_url::IntoUrl;
-use crate::util::{restricted_names, CargoResult, Config, Filesystem};
+use crate::util::restricted_names::{self, NamespacePolicy};
+use crate::util::{CargoResult, Config, Filesystem};
 
 const PACKAGE_SOURCE_LOCK: &str = ".cargo-ok";
 pub const CRATES_IO_INDEX: &str = "https://github.com/rust-lang/crates.io-index";
@@ -197,6 +207,8 @@ pub struct RegistrySource<'cfg> {
     ops: Box<dyn RegistryData + 'cfg>,
     index: index::RegistryIndex<'cfg>,
     yanked_whitelist: HashSet<PackageId>,
+    /// The namespace policy from the index's `config.json`, loaded lazily.
+    namespace_policy: Option<NamespacePolicy>,
 }
 
 #[derive(Deserialize)]
@@ -222,6 +234,12 @@ pub struct RegistryConfig {
     /// operations like yanks, owner modifications, publish new crates, etc.
     /// If this is None, the registry does not support API commands.
     pub api: Option<String>,
+
+    /// Rules for namespaced crate names (like `foo/bar`) in this registry.
+    ///
+    /// If this is missing, the default `NamespacePolicy` applies.
+    #[serde(default)]
+    pub namespaces: NamespacePolicy,
 }
 
 /// A single line in the index representing a single version of a package.
@@ -243,6 +261,11 @@ pub struct RegistryPackage<'a> {
     /// Added early 2018 (see <https://github.com/rust-lang/cargo/pull/4978>),
     /// can be `None` if published before then.
     links: Option<InternedString>,
+    /// The time this version was published, as an RFC 3339 timestamp.
+    ///
+    /// Used to honor `registry.max-age`. Registries are not required to
+    /// provide it; versions without a publish time are never filtered.
+    pubtime: Option<String>,
 }
 
 #[test]
@@ -254,6 +277,9 @@ fn escaped_char_in_json() {
     let _: RegistryPackage<'_> = serde_json::from_str(
         r#"{"name":"a","vers":"0.0.1","deps":[],"cksum":"bae3","features":{"test":["k","q"]},"links":"a-sys"}"#
     ).unwrap();
+    let _: RegistryPackage<'_> = serde_json::from_str(
+        r#"{"name":"a","vers":"0.0.1","deps":[],"cksum":"bae3","features":{},"pubtime":"2021-01-01T00:00:00Z"}"#
+    ).unwrap();
 
     // Now we add escaped cher all the places they can go
     // these are not valid, but it should error later than json parsing
@@ -378,6 +404,15 @@ pub trait RegistryData {
         path: &Path,
         data: &mut dyn FnMut(&[u8]) -> CargoResult<()>,
     ) -> CargoResult<()>;
+    /// Lists the entries of the directory `path` in the index, as pairs of
+    /// the entry name and whether it is a directory. A missing directory has
+    /// no entries.
+    fn list_dir(&self, _root: &Path, path: &Path) -> CargoResult<Vec<(String, bool)>> {
+        anyhow::bail!(
+            "listing `{}` is not supported by this registry's index",
+            path.display()
+        )
+    }
     fn config(&mut self) -> CargoResult<Option<RegistryConfig>>;
     fn update_index(&mut self) -> CargoResult<()>;
     fn download(&mut self, pkg: PackageId, checksum: &str) -> CargoResult<MaybeLock>;
@@ -389,6 +424,20 @@ pub trait RegistryData {
     }
     fn assert_index_locked<'a>(&self, path: &'a Filesystem) -> &'a Path;
     fn current_version(&self) -> Option<InternedString>;
+    /// Returns the version of the index file at `path`, for indexes that
+    /// version each file on its own rather than the whole index with
+    /// `current_version`. `cached_version` is the version of the copy of the
+    /// file in the `.cache` directory, if there is one.
+    ///
+    /// Returning `cached_version` means the cached copy can be used, and
+    /// `None` means the file has to be loaded again.
+    fn file_version(
+        &self,
+        _path: &Path,
+        _cached_version: Option<&str>,
+    ) -> CargoResult<Option<InternedString>> {
+        Ok(None)
+    }
 }
 
 pub enum MaybeLock {
@@ -396,10 +445,14 @@ pub enum MaybeLock {
     Download { url: String, descriptor: String },
 }
 
+mod download;
+mod http_remote;
 mod index;
 mod local;
 mod remote;
 
+pub use self::index::package_index_path;
+
 fn short_name(id: SourceId) -> String {
     let hash = hex::short_hash(&id);
     let ident = id.url().host_str().unwrap_or("").to_string();
@@ -413,8 +466,12 @@ impl<'cfg> RegistrySource<'cfg> {
         config: &'cfg Config,
     ) -> RegistrySource<'cfg> {
         let name = short_name(source_id);
-        let ops = remote::RemoteRegistry::new(source_id, config, &name);
-        RegistrySource::new(source_id, config, &name, Box::new(ops), yanked_whitelist)
+        let ops: Box<dyn RegistryData + 'cfg> = if source_id.is_sparse() {
+            Box::new(http_remote::HttpRegistry::new(source_id, config, &name))
+        } else {
+            Box::new(remote::RemoteRegistry::new(source_id, config, &name))
+        };
+        RegistrySource::new(source_id, config, &name, ops, yanked_whitelist)
     }
 
     pub fn local(
@@ -442,6 +499,7 @@ impl<'cfg> RegistrySource<'cfg> {
             updated: false,
             index: index::RegistryIndex::new(source_id, ops.index_path(), config),
             yanked_whitelist: yanked_whitelist.clone(),
+            namespace_policy: None,
             ops,
         }
     }
@@ -453,6 +511,65 @@ impl<'cfg> RegistrySource<'cfg> {
         self.ops.config()
     }
 
+    /// Returns the namespace policy advertised by this registry.
+    ///
+    /// Registries without a `config.json` (like local registries), or whose
+    /// index hasn't been checked out yet, get the default policy.
+    pub fn namespace_policy(&mut self) -> NamespacePolicy {
+        if let Some(policy) = self.namespace_policy {
+            return policy;
+        }
+        let policy = match self.ops.config() {
+            Ok(Some(config)) => config.namespaces,
+            Ok(None) => NamespacePolicy::default(),
+            Err(e) => {
+                debug!("failed to load registry config for namespaces: {}", e);
+                return NamespacePolicy::default();
+            }
+        };
+        self.namespace_policy = Some(policy);
+        policy
+    }
+
+    /// Finds the newest non-yanked version of every crate in `namespace` by
+    /// walking the local copy of the index, without using the network.
+    ///
+    /// The package cache lock must be held.
+    pub fn namespace_members(&mut self, namespace: &str) -> CargoResult<Vec<Summary>> {
+        let names = self.index.namespace_members(namespace, &mut *self.ops)?;
+        let mut members = Vec::new();
+        for name in names {
+            let newest = self
+                .index
+                .summaries(name, &VersionReq::any(), &mut *self.ops)?
+                .filter(|s| !s.yanked)
+                .map(|s| s.summary.clone())
+                .max_by(|a, b| a.version().cmp(b.version()));
+            members.extend(newest);
+        }
+        Ok(members)
+    }
+
+    /// Checks that a dependency's package name is allowed by this registry's
+    /// namespace policy, so that a disallowed name gets a clear error rather
+    /// than a "no matching package" one.
+    fn validate_dep_name(&mut self, dep: &Dependency) -> CargoResult<()> {
+        let policy = self.namespace_policy();
+        restricted_names::validate_package_name_for_registry(
+            &dep.package_name(),
+            "dependency name",
+            "",
+            &policy,
+        )
+        .chain_err(|| {
+            format!(
+                "dependency `{}` is not allowed by registry `{}`",
+                dep.package_name(),
+                self.source_id.display_registry_name()
+            )
+        })
+    }
+
     /// Unpacks a downloaded package into a location where it's ready to be
     /// compiled.
     ///
@@ -529,6 +646,7 @@ impl<'cfg> RegistrySource<'cfg> {
         self.ops.update_index()?;
         let path = self.ops.index_path();
         self.index = index::RegistryIndex::new(self.source_id, path, self.config);
+        self.namespace_policy = None;
         self.updated = true;
         Ok(())
     }
@@ -565,6 +683,8 @@ impl<'cfg> RegistrySource<'cfg> {
 
 impl<'cfg> Source for RegistrySource<'cfg> {
     fn query(&mut self, dep: &Dependency, f: &mut dyn FnMut(Summary)) -> CargoResult<()> {
+        self.validate_dep_name(dep)?;
+
         // If this is a precise dependency, then it came from a lock file and in
         // theory the registry is known to contain this version. If, however, we
         // come back with no summaries, then our registry may need to be
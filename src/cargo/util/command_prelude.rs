@@ -1,6 +1,8 @@
 use crate::core::compiler::{BuildConfig, MessageFormat};
 use crate::core::Workspace;
-use crate::ops::{CompileFilter, CompileOptions, NewOptions, Packages, VersionControl};
+use crate::ops::{
+    CompileFilter, CompileOptions, NamespaceLayout, NewOptions, Packages, VersionControl,
+};
 use crate::sources::CRATES_IO_REGISTRY;
 use crate::util::important_paths::find_root_manifest_for_wd;
 use crate::util::interning::InternedString;
@@ -172,6 +174,13 @@ pub trait AppExt: Sized {
         self._arg(opt("unit-graph", "Output build graph in JSON (unstable)").hidden(true))
     }
 
+    fn arg_explain_rebuild(self) -> Self {
+        self._arg(opt(
+            "explain-rebuild",
+            "Explain why each unit that isn't fresh is rebuilt (unstable)",
+        ))
+    }
+
     fn arg_new_opts(self) -> Self {
         self._arg(
             opt(
@@ -198,6 +207,17 @@ pub trait AppExt: Sized {
             )
             .value_name("NAME"),
         )
+        ._arg(
+            opt(
+                "namespace",
+                "Create the package in the given namespace, as `<NAMESPACE>/<name>`",
+            )
+            .value_name("NAMESPACE"),
+        )
+        ._arg(opt(
+            "namespaced",
+            "Use the path as a namespaced package name, such as `foo/bar`",
+        ))
     }
 
     fn arg_index(self) -> Self {
@@ -455,6 +475,7 @@ pub trait ArgMatchesExt {
         build_config.requested_profile = self.get_profile_name(config, "dev", profile_checking)?;
         build_config.build_plan = self._is_present("build-plan");
         build_config.unit_graph = self._is_present("unit-graph");
+        build_config.explain_rebuild = self._is_present("explain-rebuild");
         if build_config.build_plan {
             config
                 .cli_unstable()
@@ -465,6 +486,11 @@ pub trait ArgMatchesExt {
                 .cli_unstable()
                 .fail_if_stable_opt("--unit-graph", 8002)?;
         }
+        if build_config.explain_rebuild {
+            config
+                .cli_unstable()
+                .fail_if_stable_opt("--explain-rebuild", None)?;
+        }
 
         let opts = CompileOptions {
             build_config,
@@ -539,6 +565,12 @@ pub trait ArgMatchesExt {
             self._value_of("name").map(|s| s.to_string()),
             self._value_of("edition").map(|s| s.to_string()),
             self.registry(config)?,
+            self._value_of("namespace").map(|s| s.to_string()),
+            self._is_present("namespaced"),
+            self._value_of("namespace-layout")
+                .map(str::parse)
+                .transpose()?
+                .unwrap_or(NamespaceLayout::Nested),
         )
     }
 
@@ -51,7 +51,7 @@
 
 use std::cell::{RefCell, RefMut};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::fmt;
 use std::fs::{self, File};
@@ -61,7 +61,7 @@ use std::mem;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Once;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{anyhow, bail, format_err};
 use curl::easy::Easy;
@@ -72,7 +72,7 @@ use url::Url;
 use self::ConfigValue as CV;
 use crate::core::compiler::rustdoc::RustdocExternMap;
 use crate::core::shell::Verbosity;
-use crate::core::{nightly_features_allowed, CliUnstable, Shell, SourceId, Workspace};
+use crate::core::{nightly_features_allowed, CliUnstable, PackageId, Shell, SourceId, Workspace};
 use crate::ops;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::toml as cargo_toml;
@@ -167,6 +167,12 @@ pub struct Config {
     env: HashMap<String, String>,
     /// Tracks which sources have been updated to avoid multiple updates.
     updated_sources: LazyCell<RefCell<HashSet<SourceId>>>,
+    /// Only consider registry versions published at least this long ago.
+    /// Set from `--max-age` or the `registry.max-age` config value.
+    registry_max_age: LazyCell<Option<Duration>>,
+    /// Registry versions that were skipped because of `registry_max_age`,
+    /// along with when they were published.
+    max_age_skipped: LazyCell<RefCell<BTreeMap<PackageId, SystemTime>>>,
     /// Lock, if held, of the global package cache along with the number of
     /// acquisitions so far.
     package_cache_lock: RefCell<Option<(Option<FileLock>, usize)>>,
@@ -242,6 +248,8 @@ impl Config {
             target_dir: None,
             env,
             updated_sources: LazyCell::new(),
+            registry_max_age: LazyCell::new(),
+            max_age_skipped: LazyCell::new(),
             package_cache_lock: RefCell::new(None),
             http_config: LazyCell::new(),
             net_config: LazyCell::new(),
@@ -389,6 +397,40 @@ impl Config {
             .borrow_mut()
     }
 
+    /// The minimum age a registry version must have to be considered during
+    /// resolution, if any.
+    pub fn registry_max_age(&self) -> CargoResult<Option<Duration>> {
+        let max_age = self.registry_max_age.try_borrow_with(|| {
+            let value = match self.get_string("registry.max-age")? {
+                Some(value) => value,
+                None => return Ok(None),
+            };
+            match humantime::parse_duration(&value.val) {
+                Ok(max_age) => Ok(Some(max_age)),
+                Err(e) => bail!(
+                    "invalid duration `{}` for `registry.max-age` in {}: {}",
+                    value.val,
+                    value.definition,
+                    e
+                ),
+            }
+        })?;
+        Ok(*max_age)
+    }
+
+    /// Overrides `registry.max-age`, used by `cargo update --max-age`.
+    pub fn set_registry_max_age(&mut self, max_age: Duration) {
+        self.registry_max_age.replace(Some(max_age));
+    }
+
+    /// Registry versions skipped because they are newer than
+    /// `registry_max_age` allows, used to explain resolution failures.
+    pub fn max_age_skipped(&self) -> RefMut<'_, BTreeMap<PackageId, SystemTime>> {
+        self.max_age_skipped
+            .borrow_with(|| RefCell::new(BTreeMap::new()))
+            .borrow_mut()
+    }
+
     /// Gets all config values from disk.
     ///
     /// This will lazy-load the values as necessary. Callers are responsible
@@ -1812,6 +1854,18 @@ pub struct CargoBuildConfig {
     pub rustc: Option<PathBuf>,
     pub rustdoc: Option<PathBuf>,
     pub out_dir: Option<ConfigRelativePath>,
+    pub build_script_sandbox: Option<BuildScriptSandbox>,
+    pub max_concurrent_links: Option<u32>,
+    pub min_free_memory: Option<String>,
+}
+
+/// What to do when a build script changes files outside of `OUT_DIR`, with
+/// `-Z build-script-sandbox`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildScriptSandbox {
+    Warn,
+    Deny,
 }
 
 #[derive(Deserialize, Default)]
@@ -171,7 +171,7 @@ fn parse_links_overrides(
                         output.env.push((name.clone(), val.to_string()));
                     }
                 }
-                "warning" | "rerun-if-changed" | "rerun-if-env-changed" => {
+                "warning" | "error" | "rerun-if-changed" | "rerun-if-env-changed" => {
                     anyhow::bail!("`{}` is not supported in build script overrides", key);
                 }
                 _ => {
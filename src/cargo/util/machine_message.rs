@@ -4,6 +4,7 @@ use serde::ser;
 use serde::Serialize;
 use serde_json::{self, json, value::RawValue};
 
+use crate::core::resolver::ConflictReport;
 use crate::core::{compiler::CompileMode, PackageId, Target};
 
 pub trait Message: ser::Serialize {
@@ -91,6 +92,37 @@ impl<'a> Message for TimingInfo<'a> {
     }
 }
 
+#[derive(Serialize)]
+pub struct RebuildReason<'a> {
+    pub package_id: PackageId,
+    pub target: &'a Target,
+    pub mode: CompileMode,
+    pub field: &'a str,
+    pub message: &'a str,
+    pub old: Option<&'a str>,
+    pub new: Option<&'a str>,
+    pub dependency: Option<PackageId>,
+}
+
+impl<'a> Message for RebuildReason<'a> {
+    fn reason(&self) -> &str {
+        "rebuild-reason"
+    }
+}
+
+#[derive(Serialize)]
+pub struct ResolveConflict<'a> {
+    #[serde(flatten)]
+    pub report: &'a ConflictReport,
+    pub rendered: String,
+}
+
+impl<'a> Message for ResolveConflict<'a> {
+    fn reason(&self) -> &str {
+        "resolve-conflict"
+    }
+}
+
 #[derive(Serialize)]
 pub struct BuildFinished {
     pub success: bool,
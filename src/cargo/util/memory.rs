@@ -0,0 +1,49 @@
+//! Helpers for amounts of memory.
+
+/// Returns the memory available for starting new processes without
+/// swapping, in bytes, or `None` if it isn't known on this platform.
+pub fn available() -> Option<u64> {
+    imp::available()
+}
+
+/// Parses a size such as `512MiB`, `10GiB` or a plain number of bytes.
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let multiplier = match unit.trim() {
+        "" | "B" => 1,
+        "KiB" => 1024,
+        "MiB" => 1024 * 1024,
+        "GiB" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::fs;
+
+    pub fn available() -> Option<u64> {
+        let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+        let line = meminfo
+            .lines()
+            .find(|line| line.starts_with("MemAvailable:"))?;
+        let kib = line
+            .trim_start_matches("MemAvailable:")
+            .trim()
+            .trim_end_matches("kB")
+            .trim();
+        kib.parse::<u64>().ok()?.checked_mul(1024)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    pub fn available() -> Option<u64> {
+        None
+    }
+}
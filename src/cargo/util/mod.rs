@@ -21,7 +21,7 @@ pub use self::process_builder::{process, ProcessBuilder};
 pub use self::progress::{Progress, ProgressStyle};
 pub use self::queue::Queue;
 pub use self::read2::read2;
-pub use self::restricted_names::validate_package_name;
+pub use self::restricted_names::{validate_package_name, validate_package_name_for_registry};
 pub use self::rustc::Rustc;
 pub use self::sha256::Sha256;
 pub use self::to_semver::ToSemver;
@@ -50,6 +50,7 @@ pub mod job;
 pub mod lev_distance;
 mod lockserver;
 pub mod machine_message;
+pub mod memory;
 pub mod network;
 pub mod paths;
 pub mod process_builder;
@@ -3,8 +3,74 @@
 use crate::core::manifest::{MAX_SUBCRATE_DEPTH, SUBCRATE_DELIMETER};
 use crate::util::CargoResult;
 use anyhow::bail;
+use serde::Deserialize;
 use std::path::Path;
 
+/// The rules a registry applies to namespaced package names like `foo/bar`.
+///
+/// Registries advertise this with the `namespaces` key of the `config.json`
+/// at the root of their index, for example:
+///
+/// ```json
+/// {"dl": "...", "api": "...", "namespaces": {"allowed": true, "max-depth": 2}}
+/// ```
+///
+/// Registries that don't include the key get the default policy, which
+/// allows up to `MAX_SUBCRATE_DEPTH` levels of namespacing. The delimiter
+/// itself is always `SUBCRATE_DELIMETER`, since it is part of the canonical
+/// package name in manifests, lock files and the index layout.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct NamespacePolicy {
+    /// Whether the registry accepts namespaced package names at all.
+    #[serde(default = "default_namespaces_allowed")]
+    pub allowed: bool,
+    /// The maximum number of namespace levels, or `None` if unlimited.
+    ///
+    /// An explicit `null` in `config.json` means unlimited, while a missing
+    /// key means `MAX_SUBCRATE_DEPTH`.
+    #[serde(default = "default_namespace_max_depth")]
+    pub max_depth: Option<usize>,
+}
+
+fn default_namespaces_allowed() -> bool {
+    true
+}
+
+fn default_namespace_max_depth() -> Option<usize> {
+    MAX_SUBCRATE_DEPTH
+}
+
+impl NamespacePolicy {
+    /// A policy that places no limits on namespacing.
+    ///
+    /// This is used where no particular registry is involved, such as when
+    /// parsing a manifest or a package ID spec.
+    pub const UNRESTRICTED: NamespacePolicy = NamespacePolicy {
+        allowed: true,
+        max_depth: None,
+    };
+
+    /// Returns the maximum namespace depth allowed by this policy, or `None`
+    /// if it is unlimited.
+    pub fn effective_max_depth(&self) -> Option<usize> {
+        if self.allowed {
+            self.max_depth
+        } else {
+            Some(0)
+        }
+    }
+}
+
+impl Default for NamespacePolicy {
+    fn default() -> NamespacePolicy {
+        NamespacePolicy {
+            allowed: default_namespaces_allowed(),
+            max_depth: default_namespace_max_depth(),
+        }
+    }
+}
+
 /// Returns `true` if the name contains non-ASCII characters.
 pub fn is_non_ascii_name(name: &str) -> bool {
     name.chars().any(|ch| ch > '\x7f')
@@ -43,7 +109,22 @@ pub fn is_conflicting_artifact_name(name: &str) -> bool {
 /// level of sanity. Note that package names have other restrictions
 /// elsewhere. `cargo new` has a few restrictions, such as checking for
 /// reserved names. crates.io has even more restrictions.
+///
+/// Namespaced names of any depth are accepted here, since the limits are
+/// up to each registry. Use `validate_package_name_for_registry` when the
+/// registry's `NamespacePolicy` is known.
 pub fn validate_package_name(name: &str, what: &str, help: &str) -> CargoResult<()> {
+    validate_package_name_for_registry(name, what, help, &NamespacePolicy::UNRESTRICTED)
+}
+
+/// Like `validate_package_name`, but also enforces a registry's namespace
+/// policy.
+pub fn validate_package_name_for_registry(
+    name: &str,
+    what: &str,
+    help: &str,
+    policy: &NamespacePolicy,
+) -> CargoResult<()> {
     let mut chars = name.chars();
     if let Some(ch) = chars.next() {
         if ch.is_digit(10) {
@@ -73,21 +154,42 @@ pub fn validate_package_name(name: &str, what: &str, help: &str) -> CargoResult<
     // might actually be a sequence of characters! However, we can
     // strip out the delimeter and see if the characters we're left
     // with are all our other valid characters.
-    let name_without_subcrate_delim = if let Some(max_depth) = MAX_SUBCRATE_DEPTH {
-        let name_without_subcrate_delim = name.replacen(SUBCRATE_DELIMETER, "", max_depth);
-        if name_without_subcrate_delim.contains(SUBCRATE_DELIMETER) {
+    if name.contains(SUBCRATE_DELIMETER)
+        && name.split(SUBCRATE_DELIMETER).any(|part| part.is_empty())
+    {
+        bail!(
+            "the name `{}` cannot be used as a {}, \
+             every part of a namespaced name must be non-empty{}",
+            name,
+            what,
+            help
+        );
+    }
+    let name_without_subcrate_delim = match policy.effective_max_depth() {
+        Some(0) if name.contains(SUBCRATE_DELIMETER) => {
             bail!(
                 "the name `{}` cannot be used as a {}, \
-                 crates can be namespaced at most {} levels deep{}",
+                 the registry does not allow namespaced crates{}",
                 name,
                 what,
-                max_depth,
                 help
             );
         }
-        name_without_subcrate_delim
-    } else {
-        name.replace(SUBCRATE_DELIMETER, "")
+        Some(max_depth) => {
+            let name_without_subcrate_delim = name.replacen(SUBCRATE_DELIMETER, "", max_depth);
+            if name_without_subcrate_delim.contains(SUBCRATE_DELIMETER) {
+                bail!(
+                    "the name `{}` cannot be used as a {}, \
+                     crates can be namespaced at most {} levels deep{}",
+                    name,
+                    what,
+                    max_depth,
+                    help
+                );
+            }
+            name_without_subcrate_delim
+        }
+        None => name.replace(SUBCRATE_DELIMETER, ""),
     };
 
     for ch in name_without_subcrate_delim.chars() {
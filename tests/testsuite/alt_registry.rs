@@ -3,7 +3,7 @@
 use cargo::util::IntoUrl;
 use cargo_test_support::publish::validate_alt_upload;
 use cargo_test_support::registry::{self, Package};
-use cargo_test_support::{basic_manifest, git, paths, project};
+use cargo_test_support::{basic_manifest, git, namespaced_name, paths, project};
 use std::fs;
 
 #[cargo_test]
@@ -1301,3 +1301,103 @@ fn both_index_and_registry() {
             .run();
     }
 }
+
+fn set_alt_namespace_policy(namespaces: &str) {
+    let repo = git2::Repository::open(registry::alt_registry_path()).unwrap();
+    let cfg_path = registry::alt_registry_path().join("config.json");
+    fs::write(
+        cfg_path,
+        format!(
+            r#"{{"dl": "{}", "api": "{}", "namespaces": {}}}"#,
+            registry::alt_dl_url(),
+            registry::alt_api_url(),
+            namespaces
+        ),
+    )
+    .unwrap();
+    git::add(&repo);
+    git::commit(&repo);
+}
+
+#[cargo_test]
+fn namespace_depth_from_registry_config() {
+    let org_team_bar = namespaced_name(&["org", "team", "bar"]);
+    Package::new(&org_team_bar, "0.0.1")
+        .alternative(true)
+        .publish();
+    set_alt_namespace_policy(r#"{"max-depth": 2}"#);
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                [project]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                "{}" = {{ version = "0.0.1", registry = "alternative" }}
+                "#,
+                org_team_bar
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .with_stderr_contains("[COMPILING] org/team/bar v0.0.1 (registry `[ROOT][..]`)")
+        .run();
+
+    // The default policy only allows a single level.
+    set_alt_namespace_policy("{}");
+    p.cargo("update")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[..]dependency `org/team/bar` is not allowed by registry `alternative`
+
+Caused by:
+  the name `org/team/bar` cannot be used as a dependency name, \
+crates can be namespaced at most 1 levels deep
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn publish_namespaced_to_registry_without_namespaces() {
+    registry::init();
+    set_alt_namespace_policy(r#"{"allowed": false}"#);
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                [project]
+                name = "{}"
+                version = "0.0.1"
+                authors = []
+                "#,
+                namespaced_name(&["foo", "bar"])
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("publish --registry alternative --token sekrit")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] `foo/bar` cannot be published to registry `alternative`
+
+Caused by:
+  the name `foo/bar` cannot be used as a package name, \
+the registry does not allow namespaced crates
+",
+        )
+        .run();
+}
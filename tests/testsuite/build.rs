@@ -1091,11 +1091,92 @@ all possible versions conflict with previously selected packages.
     ... which is depended on by `baz v0.1.0`
     ... which is depended on by `foo v0.0.1 ([..])`
 
+this conflict is explained by:
+  `qux v0.1.0` depends on `bad >=1.0.1`
+  `baz v0.1.0` depends on `bad =1.0.0`, which selected `bad v1.0.0`
+  only one semver-compatible version of `bad` can be selected, so `qux v0.1.0` and `baz v0.1.0` conflict
+  `foo v0.0.1 ([..])` depends on both `qux v0.1.0` and `baz v0.1.0`
+
 failed to select a version for `bad` which could resolve this conflict",
         )
         .run();
 }
 
+#[cargo_test]
+fn incompatible_dependencies_json() {
+    Package::new("bad", "1.0.0").publish();
+    Package::new("bad", "1.0.1").publish();
+    Package::new("bar", "0.1.0").dep("bad", "=1.0.0").publish();
+    Package::new("mid", "0.1.0").dep("bar", "0.1.0").publish();
+    Package::new("baz", "0.1.0").dep("bad", ">=1.0.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [project]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                mid = "0.1.0"
+                baz = "0.1.0"
+            "#,
+        )
+        .file("src/main.rs", "fn main(){}")
+        .build();
+
+    p.cargo("build --message-format=json")
+        .with_status(101)
+        .with_json(
+            r#"
+                {
+                    "reason": "resolve-conflict",
+                    "name": "bad",
+                    "requirement": {
+                        "package_id": "bar 0.1.0 (registry+[..])",
+                        "req": "=1.0.0",
+                        "path": [
+                            "mid 0.1.0 (registry+[..])",
+                            "foo 0.0.1 (path+file://[..])"
+                        ]
+                    },
+                    "candidates": ["1.0.0"],
+                    "conflicts": [
+                        {
+                            "package_id": "bad 1.0.1 (registry+[..])",
+                            "reason": {"kind": "semver"},
+                            "requirement": {
+                                "package_id": "bar 0.1.0 (registry+[..])",
+                                "req": "=1.0.0",
+                                "path": [
+                                    "mid 0.1.0 (registry+[..])",
+                                    "foo 0.0.1 (path+file://[..])"
+                                ]
+                            },
+                            "selected_by": {
+                                "package_id": "baz 0.1.0 (registry+[..])",
+                                "req": ">=1.0.1",
+                                "path": ["foo 0.0.1 (path+file://[..])"]
+                            }
+                        }
+                    ],
+                    "rendered": "{...}"
+                }
+            "#,
+        )
+        .with_stderr_contains(
+            "\
+this conflict is explained by:
+  `bar v0.1.0` depends on `bad =1.0.0`
+  `baz v0.1.0` depends on `bad >=1.0.1`, which selected `bad v1.0.1`
+  only one semver-compatible version of `bad` can be selected, so `bar v0.1.0` and `baz v0.1.0` conflict
+  `foo v0.0.1 ([..])` depends on both `bar v0.1.0` (through `mid v0.1.0`) and `baz v0.1.0`
+",
+        )
+        .run();
+}
+
 #[cargo_test]
 fn incompatible_dependencies_with_multi_semver() {
     Package::new("bad", "1.0.0").publish();
@@ -1140,6 +1221,17 @@ all possible versions conflict with previously selected packages.
     ... which is depended on by `bar v0.1.0`
     ... which is depended on by `foo v0.0.1 ([..])`
 
+this conflict is explained by:
+  `foo v0.0.1 ([..])` depends on `bad >=1.0.1, <=2.0.0`
+  `baz v0.1.0` depends on `bad >=2.0.1`, which selected `bad v2.0.1`
+  only one semver-compatible version of `bad` can be selected, so `foo v0.0.1 ([..])` and `baz v0.1.0` conflict
+  `foo v0.0.1 ([..])` depends on `baz v0.1.0`
+
+  `foo v0.0.1 ([..])` depends on `bad >=1.0.1, <=2.0.0`
+  `bar v0.1.0` depends on `bad =1.0.0`, which selected `bad v1.0.0`
+  only one semver-compatible version of `bad` can be selected, so `foo v0.0.1 ([..])` and `bar v0.1.0` conflict
+  `foo v0.0.1 ([..])` depends on `bar v0.1.0`
+
 failed to select a version for `bad` which could resolve this conflict",
         )
         .run();
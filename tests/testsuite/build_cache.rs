@@ -0,0 +1,218 @@
+//! Tests for the `-Z build-cache` shared cache of compiled units.
+
+use cargo_test_support::paths;
+use cargo_test_support::project;
+use cargo_test_support::registry::Package;
+use std::fs;
+
+fn cache_dir() -> std::path::PathBuf {
+    paths::home().join(".cargo/build-cache")
+}
+
+#[cargo_test]
+fn shared_between_workspaces() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}")
+        .publish();
+    let foo = project()
+        .at("foo")
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "0.1"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar(); }")
+        .build();
+    let baz = project()
+        .at("baz")
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "baz"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "0.1"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar(); }")
+        .build();
+
+    foo.cargo("build -Z build-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.1.0 (registry [..])
+[COMPILING] bar v0.1.0
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert_eq!(fs::read_dir(cache_dir()).unwrap().count(), 1);
+
+    baz.cargo("build -Z build-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[RESTORED] bar v0.1.0 from the build cache
+[COMPILING] baz v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    baz.cargo("build -Z build-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+    baz.process(&baz.bin("baz")).run();
+
+    // Different flags mean a different entry.
+    baz.cargo("build -Z build-cache")
+        .env("RUSTFLAGS", "-C debug-assertions")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] bar v0.1.0
+[COMPILING] baz v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert_eq!(fs::read_dir(cache_dir()).unwrap().count(), 2);
+}
+
+#[cargo_test]
+fn build_scripts_not_cached() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}")
+        .file("build.rs", "fn main() {}")
+        .publish();
+    let foo = project()
+        .at("foo")
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "0.1"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar(); }")
+        .build();
+
+    foo.cargo("build -Z build-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] bar v0.1.0")
+        .run();
+    assert!(!cache_dir().exists());
+}
+
+#[cargo_test]
+fn timings_report_hits() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}")
+        .publish();
+    let foo = project()
+        .at("foo")
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "0.1"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar(); }")
+        .build();
+    let baz = project()
+        .at("baz")
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "baz"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "0.1"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar(); }")
+        .build();
+
+    foo.cargo("build -Z build-cache -Z timings=info")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[..]Timing build cache: 0 hits, 1 misses")
+        .run();
+    baz.cargo("build -Z build-cache -Z timings=info")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[..]Timing build cache: 1 hits, 0 misses")
+        .run();
+}
+
+#[cargo_test]
+fn size_limit() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}")
+        .publish();
+    let foo = project()
+        .at("foo")
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "0.1"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar(); }")
+        .build();
+    let cache = paths::root().join("my-cache");
+    foo.change_file(
+        ".cargo/config",
+        r#"
+            [build-cache]
+            dir = "../my-cache"
+            max-size = "1B"
+        "#,
+    );
+
+    foo.cargo("build -Z build-cache")
+        .masquerade_as_nightly_cargo()
+        .run();
+    // The entry was added and then removed to fit in the limit.
+    assert_eq!(fs::read_dir(&cache).unwrap().count(), 0);
+
+    foo.change_file(
+        ".cargo/config",
+        r#"
+            [build-cache]
+            max-size = "lots"
+        "#,
+    );
+    foo.cargo("build -Z build-cache")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] `build-cache.max-size` must be a size such as `10GiB`, found `lots`")
+        .run();
+}
@@ -3142,6 +3142,154 @@ warning: bar
         .run();
 }
 
+#[cargo_test]
+fn warnings_with_location() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.5.0"))
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:warning:native/foo.c:12=unused variable");
+                    println!("cargo:warning:native/foo.h=deprecated header");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.5.0 ([..])
+warning: unused variable
+ --> native/foo.c:12
+warning: deprecated header
+ --> native/foo.h
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn error_fails_build() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.5.0"))
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:warning=checking for libfoo");
+                    println!("cargo:error=libfoo was not found");
+                    println!("cargo:error:config.txt:3=invalid setting");
+                }
+            "#,
+        )
+        .file("config.txt", "")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[COMPILING] foo v0.5.0 ([..])
+The following warnings were emitted during compilation:
+
+warning: checking for libfoo
+
+error: libfoo was not found
+error: invalid setting
+ --> config.txt:3
+error: build script of `foo v0.5.0 ([CWD])` reported 2 errors
+",
+        )
+        .run();
+
+    // The build script runs again, since it failed.
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains("error: libfoo was not found")
+        .run();
+}
+
+#[cargo_test]
+fn diagnostics_as_json() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.5.0"))
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:warning:native/foo.c:12=unused variable");
+                    println!("cargo:error=libfoo was not found");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build --message-format=json")
+        .with_status(101)
+        .with_json_contains_unordered(
+            r#"
+                {
+                    "reason": "compiler-message",
+                    "package_id": "foo 0.5.0 [..]",
+                    "target": {
+                        "kind": ["custom-build"],
+                        "crate_types": ["bin"],
+                        "doc": false,
+                        "doctest": false,
+                        "edition": "2015",
+                        "name": "build-script-build",
+                        "src_path": "[..]/foo/build.rs",
+                        "test": false
+                    },
+                    "message": {
+                        "message": "unused variable",
+                        "code": null,
+                        "level": "warning",
+                        "spans": [{
+                            "file_name": "native/foo.c",
+                            "byte_start": 0,
+                            "byte_end": 0,
+                            "line_start": 12,
+                            "line_end": 12,
+                            "column_start": 1,
+                            "column_end": 1,
+                            "is_primary": true,
+                            "text": [],
+                            "label": null,
+                            "suggested_replacement": null,
+                            "suggestion_applicability": null,
+                            "expansion": null
+                        }],
+                        "children": [],
+                        "rendered": "warning: unused variable\n --> native/foo.c:12\n"
+                    }
+                }
+
+                {
+                    "reason": "compiler-message",
+                    "package_id": "foo 0.5.0 [..]",
+                    "target": "{...}",
+                    "message": {
+                        "message": "libfoo was not found",
+                        "code": null,
+                        "level": "error",
+                        "spans": [],
+                        "children": [],
+                        "rendered": "error: libfoo was not found\n"
+                    }
+                }
+            "#,
+        )
+        .with_stderr_does_not_contain("warning: unused variable")
+        .run();
+}
+
 #[cargo_test]
 fn output_shows_on_vv() {
     let p = project()
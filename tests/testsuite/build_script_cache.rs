@@ -0,0 +1,337 @@
+//! Tests for the `-Z build-script-cache` cache of build script runs.
+
+use cargo_test_support::paths;
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project};
+use std::fs;
+
+#[cargo_test]
+fn restored_after_clean() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file(
+            "src/main.rs",
+            r#"
+                include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+
+                fn main() {
+                    println!("{} {}", GREETING, env!("GREETING_ENV"));
+                }
+            "#,
+        )
+        .file("greeting.txt", "hello")
+        .file(
+            "build.rs",
+            r#"
+                use std::env;
+                use std::fs;
+                use std::path::Path;
+
+                fn main() {
+                    println!("cargo:rerun-if-changed=greeting.txt");
+                    println!("cargo:rerun-if-env-changed=GREETING");
+                    let greeting = fs::read_to_string("greeting.txt").unwrap();
+                    let out_dir = env::var("OUT_DIR").unwrap();
+                    fs::write(
+                        Path::new(&out_dir).join("generated.rs"),
+                        format!("const GREETING: &str = {:?};", greeting),
+                    )
+                    .unwrap();
+                    let suffix = env::var("GREETING").unwrap_or_default();
+                    println!("cargo:rustc-env=GREETING_ENV=world{}", suffix);
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("run -Z build-script-cache -v")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("hello world")
+        .with_stderr_contains("[RUNNING] `[..]build-script-build`")
+        .run();
+
+    p.cargo("clean").run();
+
+    p.cargo("run -Z build-script-cache -v")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("hello world")
+        .with_stderr_contains("[RESTORED] build script output of foo v0.1.0 ([CWD]) from the cache")
+        .with_stderr_does_not_contain("[RUNNING] `[..]build-script-build`")
+        .run();
+}
+
+#[cargo_test]
+fn shared_between_profiles() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                # Compile the build script the same way in both profiles.
+                [profile.dev.build-override]
+                debug = false
+                debug-assertions = false
+                overflow-checks = false
+                incremental = false
+
+                [profile.release.build-override]
+                opt-level = 0
+                debug = false
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"
+                include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+
+                fn main() {
+                    println!("{} {}", GREETING, env!("GREETING_ENV"));
+                }
+            "#,
+        )
+        .file("greeting.txt", "hello")
+        .file(
+            "build.rs",
+            r#"
+                use std::env;
+                use std::fs;
+                use std::path::Path;
+
+                fn main() {
+                    println!("cargo:rerun-if-changed=greeting.txt");
+                    println!("cargo:rerun-if-env-changed=GREETING");
+                    let greeting = fs::read_to_string("greeting.txt").unwrap();
+                    let out_dir = env::var("OUT_DIR").unwrap();
+                    fs::write(
+                        Path::new(&out_dir).join("generated.rs"),
+                        format!("const GREETING: &str = {:?};", greeting),
+                    )
+                    .unwrap();
+                    let suffix = env::var("GREETING").unwrap_or_default();
+                    println!("cargo:rustc-env=GREETING_ENV=world{}", suffix);
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Z build-script-cache")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.cargo("build -Z build-script-cache --release")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+[RESTORED] build script output of foo v0.1.0 ([CWD]) from the cache
+[FINISHED] release [..]
+",
+        )
+        .run();
+    p.process(&p.release_bin("foo"))
+        .with_stdout("hello world")
+        .run();
+}
+
+#[cargo_test]
+fn changed_inputs_rerun() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file(
+            "src/main.rs",
+            r#"
+                include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+
+                fn main() {
+                    println!("{} {}", GREETING, env!("GREETING_ENV"));
+                }
+            "#,
+        )
+        .file("greeting.txt", "hello")
+        .file(
+            "build.rs",
+            r#"
+                use std::env;
+                use std::fs;
+                use std::path::Path;
+
+                fn main() {
+                    println!("cargo:rerun-if-changed=greeting.txt");
+                    println!("cargo:rerun-if-env-changed=GREETING");
+                    let greeting = fs::read_to_string("greeting.txt").unwrap();
+                    let out_dir = env::var("OUT_DIR").unwrap();
+                    fs::write(
+                        Path::new(&out_dir).join("generated.rs"),
+                        format!("const GREETING: &str = {:?};", greeting),
+                    )
+                    .unwrap();
+                    let suffix = env::var("GREETING").unwrap_or_default();
+                    println!("cargo:rustc-env=GREETING_ENV=world{}", suffix);
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("run -Z build-script-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("hello world")
+        .run();
+
+    p.cargo("clean").run();
+    p.change_file("greeting.txt", "goodbye");
+    p.cargo("run -Z build-script-cache -v")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("goodbye world")
+        .with_stderr_contains("[RUNNING] `[..]build-script-build`")
+        .run();
+
+    p.cargo("clean").run();
+    p.cargo("run -Z build-script-cache -v")
+        .masquerade_as_nightly_cargo()
+        .env("GREETING", "!")
+        .with_stdout("goodbye world!")
+        .with_stderr_contains("[RUNNING] `[..]build-script-build`")
+        .run();
+
+    // Going back to an earlier state reuses its run.
+    p.cargo("clean").run();
+    p.cargo("run -Z build-script-cache -v")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("goodbye world")
+        .with_stderr_does_not_contain("[RUNNING] `[..]build-script-build`")
+        .run();
+}
+
+#[cargo_test]
+fn changed_build_script_reruns() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", r#"pub const VERSION: &str = "0.1.0";"#)
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [build-dependencies]
+                bar = "0.1"
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"
+                fn main() {
+                    println!("{}", env!("MESSAGE"));
+                }
+            "#,
+        )
+        .file("greeting.txt", "")
+        .file(
+            "build.rs",
+            r#"
+                mod message;
+
+                fn main() {
+                    println!("cargo:rerun-if-changed=greeting.txt");
+                    println!("cargo:rustc-env=MESSAGE={} {}", message::MESSAGE, bar::VERSION);
+                }
+            "#,
+        )
+        .file("message.rs", r#"pub const MESSAGE: &str = "hello";"#)
+        .build();
+
+    p.cargo("run -Z build-script-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("hello 0.1.0")
+        .run();
+
+    // A module of the build script changed.
+    p.change_file("message.rs", r#"pub const MESSAGE: &str = "goodbye";"#);
+    p.cargo("run -Z build-script-cache -v")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("goodbye 0.1.0")
+        .with_stderr_contains("[RUNNING] `[..]build-script-build`")
+        .with_stderr_does_not_contain("[RESTORED] [..]")
+        .run();
+
+    // A build-dependency changed.
+    Package::new("bar", "0.1.1")
+        .file("src/lib.rs", r#"pub const VERSION: &str = "0.1.1";"#)
+        .publish();
+    p.cargo("update").run();
+    p.cargo("run -Z build-script-cache -v")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("goodbye 0.1.1")
+        .with_stderr_contains("[RUNNING] `[..]build-script-build`")
+        .with_stderr_does_not_contain("[RESTORED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn not_cached_without_rerun_directives() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .file("build.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -Z build-script-cache")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("clean").run();
+    p.cargo("build -Z build-script-cache -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `[..]build-script-build`")
+        .run();
+}
+
+#[cargo_test]
+fn size_limit() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=build.rs");
+                }
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            r#"
+                [build-script-cache]
+                dir = "../my-cache"
+                max-size = "1B"
+            "#,
+        )
+        .build();
+    let cache = paths::root().join("my-cache");
+
+    p.cargo("build -Z build-script-cache")
+        .masquerade_as_nightly_cargo()
+        .run();
+    // The entry was added and then removed to fit in the limit.
+    assert_eq!(fs::read_dir(&cache).unwrap().count(), 0);
+
+    p.change_file(
+        ".cargo/config",
+        r#"
+            [build-script-cache]
+            max-size = "lots"
+        "#,
+    );
+    p.cargo("build -Z build-script-cache")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `build-script-cache.max-size` must be a size such as `10GiB`, found `lots`",
+        )
+        .run();
+}
@@ -0,0 +1,162 @@
+//! Tests for -Zbuild-script-sandbox.
+
+use cargo_test_support::{basic_manifest, project};
+
+const BUILD_RS: &str = r#"
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    fn main() {
+        let out_dir = env::var("OUT_DIR").unwrap();
+        fs::write(Path::new(&out_dir).join("inside.rs"), "").unwrap();
+        fs::write("generated.rs", "").unwrap();
+        fs::write("src/lib.rs", "// regenerated").unwrap();
+        fs::remove_file("stale.txt").unwrap();
+    }
+"#;
+
+#[cargo_test]
+fn warns_about_changed_files() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .file("stale.txt", "")
+        .file("build.rs", BUILD_RS)
+        .build();
+
+    p.cargo("build -Zbuild-script-sandbox")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+warning: build script added `generated.rs` outside of OUT_DIR
+warning: build script modified `src/lib.rs` outside of OUT_DIR
+warning: build script removed `stale.txt` outside of OUT_DIR
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn deny_fails_the_build() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .file("stale.txt", "")
+        .file("build.rs", BUILD_RS)
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build]
+                build-script-sandbox = "deny"
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zbuild-script-sandbox")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+error: build script of `foo v0.1.0 ([CWD])` changed files outside of OUT_DIR:
+  added `generated.rs`
+  modified `src/lib.rs`
+  removed `stale.txt`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn out_dir_only_is_quiet() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    let out_dir = std::env::var("OUT_DIR").unwrap();
+                    std::fs::write(std::path::Path::new(&out_dir).join("gen.rs"), "").unwrap();
+                }
+            "#,
+        )
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build]
+                build-script-sandbox = "deny"
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zbuild-script-sandbox")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn members_are_not_reported_by_the_root() {
+    // The root's build script waits for the member's to write its file, so
+    // that the write happens while both are running.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [workspace]
+                members = ["bar"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    let path = std::path::Path::new("bar/generated.txt");
+                    for _ in 0..300 {
+                        if path.exists() {
+                            break;
+                        }
+                        std::thread::sleep(std::time::Duration::from_millis(100));
+                    }
+                }
+            "#,
+        )
+        .file(".git/HEAD", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .file(
+            "bar/build.rs",
+            r#"
+                fn main() {
+                    std::fs::write("generated.txt", "").unwrap();
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build --workspace -j2 -Zbuild-script-sandbox")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[COMPILING] bar v0.1.0 ([CWD]/bar)
+[COMPILING] foo v0.1.0 ([CWD])
+warning: build script added `generated.txt` outside of OUT_DIR
+[FINISHED] [..]
+",
+        )
+        .run();
+}
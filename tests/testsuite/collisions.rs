@@ -3,8 +3,8 @@
 //! Ideally these should never happen, but I don't think we'll ever be able to
 //! prevent all collisions.
 
-use cargo_test_support::basic_manifest;
 use cargo_test_support::project;
+use cargo_test_support::{basic_manifest, namespaced_name};
 use std::env;
 
 #[cargo_test]
@@ -160,3 +160,129 @@ the same path; see <https://github.com/rust-lang/cargo/issues/6313>.
         )
         .run();
 }
+
+#[cargo_test]
+fn collision_namespaced_extern_name() {
+    // `foo/bar` and `foo_bar` are both `foo_bar` in Rust code.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2018"
+
+                [dependencies]
+                "{}" = {{ path = "foo-bar" }}
+                foo_bar = {{ path = "foo_bar" }}
+                "#,
+                namespaced_name(&["foo", "bar"])
+            ),
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "foo-bar/Cargo.toml",
+            &basic_manifest(&namespaced_name(&["foo", "bar"]), "0.1.0"),
+        )
+        .file("foo-bar/src/lib.rs", "")
+        .file("foo_bar/Cargo.toml", &basic_manifest("foo_bar", "0.1.0"))
+        .file("foo_bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the crate `foo v0.1.0 ([..]/foo)` depends on both `[..]` and `[..]`, \
+which are both named `foo_bar` in Rust code
+Rename one of the dependencies in `Cargo.toml` with the `package` key.
+",
+        )
+        .run();
+
+    // Renaming one of them resolves the ambiguity.
+    p.change_file(
+        "Cargo.toml",
+        &format!(
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            edition = "2018"
+
+            [dependencies]
+            "{}" = {{ path = "foo-bar" }}
+            other = {{ path = "foo_bar", package = "foo_bar" }}
+            "#,
+            namespaced_name(&["foo", "bar"])
+        ),
+    );
+    p.cargo("build").run();
+}
+
+#[cargo_test]
+fn collision_namespaced_uplift() {
+    // Path dependencies normally don't include a metadata hash for dylibs,
+    // but `foo/bar` and `foo_bar` are forced to so that their `deps` outputs
+    // stay apart. Uplifted dylibs can't be distinguished, though.
+    let dylib_manifest = |name: &str, deps: &str| {
+        format!(
+            r#"
+            [package]
+            name = "{}"
+            version = "0.1.0"
+
+            [lib]
+            crate-type = ["dylib"]
+
+            [dependencies]
+            {}
+            "#,
+            name, deps
+        )
+    };
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &dylib_manifest("foo_bar", "baz = { path = \"baz\" }"),
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "baz/Cargo.toml",
+            &format!(
+                r#"
+                [package]
+                name = "baz"
+                version = "0.1.0"
+
+                [dependencies]
+                "{}" = {{ path = "../foo-bar" }}
+                "#,
+                namespaced_name(&["foo", "bar"])
+            ),
+        )
+        .file("baz/src/lib.rs", "")
+        .file(
+            "foo-bar/Cargo.toml",
+            &dylib_manifest(&namespaced_name(&["foo", "bar"]), ""),
+        )
+        .file("foo-bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("build -j=1")
+        .with_status(101)
+        .with_stderr_contains(&format!(
+            "\
+[ERROR] output filename collision between packages `foo[..]bar v0.1.0 ([..])` and `foo[..]bar v0.1.0 ([..])`.
+The lib target `foo[..]bar` in package `[..]` has the same output filename as the lib target `foo[..]bar` in package `[..]`.
+Colliding filename is: [..]/foo/target/debug/{}foo_bar{}
+The package names differ, but both use `foo_bar` in filenames.
+Consider renaming one of the targets or compiling them separately.
+",
+            env::consts::DLL_PREFIX,
+            env::consts::DLL_SUFFIX
+        ))
+        .run();
+}
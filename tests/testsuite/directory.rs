@@ -10,7 +10,7 @@ use cargo_test_support::cargo_process;
 use cargo_test_support::git;
 use cargo_test_support::paths;
 use cargo_test_support::registry::{cksum, Package};
-use cargo_test_support::{basic_manifest, project, t, ProjectBuilder};
+use cargo_test_support::{basic_manifest, namespaced_name, project, t, ProjectBuilder};
 
 fn setup() {
     let root = paths::root();
@@ -771,3 +771,51 @@ Caused by:
         .with_status(101)
         .run();
 }
+
+#[cargo_test]
+fn namespaced_package() {
+    setup();
+
+    // `foo_bar` is left over from an older vendor layout. The package in
+    // `foo~bar`, where `cargo vendor` puts `foo/bar` now, wins.
+    let foo_bar = namespaced_name(&["foo", "bar"]);
+    VendorPackage::new("foo_bar")
+        .file("Cargo.toml", &basic_manifest(&foo_bar, "0.1.0"))
+        .file("src/lib.rs", "pub fn old() {}")
+        .build();
+    VendorPackage::new("foo~bar")
+        .file("Cargo.toml", &basic_manifest(&foo_bar, "0.1.0"))
+        .file("src/lib.rs", "pub fn bar() {}")
+        .build();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                authors = []
+
+                [dependencies]
+                "{}" = "0.1.0"
+                "#,
+                foo_bar
+            ),
+        )
+        .file("src/lib.rs", "pub fn foo() { foo_bar::bar(); }")
+        .build();
+
+    p.cargo("build")
+        .with_stderr(
+            "\
+[WARNING] package `foo/bar v0.1.0[..]` is in both `[..]index/foo_bar` and \
+`[..]index/foo~bar`, using `[..]index/foo~bar`
+[COMPILING] foo/bar v0.1.0
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
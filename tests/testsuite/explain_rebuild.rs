@@ -0,0 +1,196 @@
+//! Tests for `--explain-rebuild`.
+
+use cargo_test_support::{basic_manifest, project, sleep_ms};
+
+#[cargo_test]
+fn not_built_before() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file(
+            "bar/src/lib.rs",
+            r#"pub const FOO: Option<&str> = option_env!("FOO");"#,
+        )
+        .build();
+
+    p.cargo("build --explain-rebuild -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] bar v0.1.0 ([..])
+[COMPILING] foo v0.1.0 ([..])
+[REBUILT] 3 units:
+bar v0.1.0: not built before
+foo v0.1.0: not built before
+foo v0.1.0 bin \"foo\": not built before
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.cargo("build --explain-rebuild -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn dependency_tree() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file(
+            "bar/src/lib.rs",
+            r#"pub const FOO: Option<&str> = option_env!("FOO");"#,
+        )
+        .build();
+    p.cargo("build").run();
+
+    sleep_ms(1000);
+    p.change_file("bar/src/lib.rs", "");
+    p.cargo("build --explain-rebuild -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] bar v0.1.0 ([..])
+[COMPILING] foo v0.1.0 ([..])
+[REBUILT] 3 units:
+bar v0.1.0: modified: src/lib.rs
+├── foo v0.1.0: dependency `bar` is stale
+└── foo v0.1.0 bin \"foo\": dependency `bar` is stale
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn changed_env_var() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file(
+            "bar/src/lib.rs",
+            r#"pub const FOO: Option<&str> = option_env!("FOO");"#,
+        )
+        .build();
+    p.cargo("build").run();
+
+    p.cargo("build --explain-rebuild -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .env("FOO", "1")
+        .with_stderr(
+            "\
+[COMPILING] bar v0.1.0 ([..])
+[COMPILING] foo v0.1.0 ([..])
+[REBUILT] 3 units:
+bar v0.1.0: env var changed: FOO: previously (unset), now 1
+├── foo v0.1.0: dependency `bar` is stale
+└── foo v0.1.0 bin \"foo\": dependency `bar` is stale
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn json_messages() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file(
+            "bar/src/lib.rs",
+            r#"pub const FOO: Option<&str> = option_env!("FOO");"#,
+        )
+        .build();
+    p.cargo("build").run();
+
+    p.cargo("build --explain-rebuild -Z unstable-options --message-format=json")
+        .masquerade_as_nightly_cargo()
+        .env("FOO", "1")
+        .with_json_contains_unordered(
+            r#"
+                {
+                    "reason": "rebuild-reason",
+                    "package_id": "bar 0.1.0 [..]",
+                    "target": "{...}",
+                    "mode": "build",
+                    "field": "fs",
+                    "message": "env var changed: FOO",
+                    "old": "(unset)",
+                    "new": "1",
+                    "dependency": null
+                }
+
+                {
+                    "reason": "rebuild-reason",
+                    "package_id": "foo 0.1.0 [..]",
+                    "target": {
+                        "kind": ["lib"],
+                        "crate_types": ["lib"],
+                        "doc": true,
+                        "doctest": true,
+                        "edition": "2015",
+                        "name": "foo",
+                        "src_path": "[..]/foo/src/lib.rs",
+                        "test": true
+                    },
+                    "mode": "build",
+                    "field": "fs",
+                    "message": "dependency `bar` is stale",
+                    "old": null,
+                    "new": null,
+                    "dependency": "bar 0.1.0 [..]"
+                }
+            "#,
+        )
+        .run();
+}
@@ -2581,3 +2581,74 @@ fn env_build_script_no_rebuild() {
     p.cargo("build").run();
     p.cargo("build").with_stderr("[FINISHED] [..]").run();
 }
+
+#[cargo_test]
+fn checksum_freshness_touched_only() {
+    let p = project()
+        .file("src/lib.rs", "mod a;")
+        .file("src/a.rs", "")
+        .build();
+
+    p.cargo("build -Z checksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .run();
+    if is_coarse_mtime() {
+        sleep_ms(1000);
+    }
+
+    // Only the mtime changes, as with a `git checkout` or a cache restore.
+    let now = FileTime::from_system_time(SystemTime::now());
+    filetime::set_file_times(p.root().join("src/a.rs"), now, now).unwrap();
+    p.cargo("build -Z checksum-freshness -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[TOUCHED] foo v0.0.1 ([CWD]): touched only: src/a.rs
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+
+    // Without the flag the mtime decides.
+    p.cargo("build")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn checksum_freshness_content_changed() {
+    let p = project()
+        .file("src/lib.rs", "mod a;")
+        .file("src/a.rs", "")
+        .build();
+
+    p.cargo("build -Z checksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .run();
+    if is_coarse_mtime() {
+        sleep_ms(1000);
+    }
+
+    p.change_file("src/a.rs", "// changed");
+    p.cargo("build -Z checksum-freshness -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[DIRTY] foo v0.0.1 ([CWD]): content changed: src/a.rs
+[COMPILING] foo v0.0.1 ([CWD])
+[RUNNING] `rustc [..]`
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+    p.cargo("build -Z checksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+}
@@ -0,0 +1,191 @@
+//! Tests for the `cargo index` command.
+
+use cargo_test_support::paths;
+use cargo_test_support::registry::{registry_path, Package};
+use cargo_test_support::{cargo_process, namespaced_name, t};
+use std::fs;
+use std::path::Path;
+
+fn write(path: &Path, contents: &str) {
+    t!(fs::create_dir_all(path.parent().unwrap()));
+    t!(fs::write(path, contents));
+}
+
+fn index_line(name: &str, vers: &str, deps: &str) -> String {
+    format!(
+        r#"{{"name":"{}","vers":"{}","deps":[{}],"cksum":"{}","features":{{}},"yanked":false,"links":null}}"#,
+        name,
+        vers,
+        deps,
+        "0".repeat(64)
+    )
+}
+
+fn index_dep(name: &str) -> String {
+    format!(
+        r#"{{"name":"{}","req":"^0.1","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal","registry":null}}"#,
+        name
+    )
+}
+
+#[cargo_test]
+fn requires_unstable_options() {
+    cargo_process("index verify out")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `cargo index` command is unstable, \
+             pass `-Z unstable-options` to enable it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn build_from_crates() {
+    let foo_bar = namespaced_name(&["foo", "bar"]);
+    Package::new(&foo_bar, "0.1.0").local(true).publish();
+    Package::new(&foo_bar, "0.2.0").local(true).publish();
+    Package::new("baz", "1.0.0")
+        .local(true)
+        .dep(&foo_bar, "0.1")
+        .publish();
+
+    cargo_process("-Z unstable-options index build out --from-crates")
+        .arg(registry_path())
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[INDEXING] baz v1.0.0
+[INDEXING] foo/bar v0.1.0
+[INDEXING] foo/bar v0.2.0
+",
+        )
+        .run();
+
+    let out = paths::root().join("out");
+    let foo_bar_index = t!(fs::read_to_string(out.join("3/f/foo@/bar")));
+    let lines = foo_bar_index.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with(r#"{"name":"foo/bar","vers":"0.1.0","deps":[]"#));
+    assert!(lines[1].starts_with(r#"{"name":"foo/bar","vers":"0.2.0","deps":[]"#));
+
+    let baz_index = t!(fs::read_to_string(out.join("3/b/baz")));
+    assert!(baz_index.contains(
+        r#""deps":[{"name":"foo/bar","req":"^0.1","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal","registry":null}]"#
+    ));
+
+    cargo_process("-Z unstable-options index verify out --crates")
+        .arg(registry_path())
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[VERIFIED] `[..]out`")
+        .run();
+}
+
+#[cargo_test]
+fn build_into_non_empty_directory() {
+    write(&paths::root().join("out/config.json"), "{}");
+    write(
+        &paths::root().join("flat/3/b/baz"),
+        &index_line("baz", "1.0.0", ""),
+    );
+
+    cargo_process("-Z unstable-options index build out --from-index flat")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] the output directory `[..]out` is not empty")
+        .run();
+}
+
+#[cargo_test]
+fn build_from_flat_index() {
+    // An index from before the namespaced layout, which stored `foo/bar`
+    // under its file name form.
+    let flat = paths::root().join("flat");
+    write(
+        &flat.join("config.json"),
+        r#"{"dl": "https://example.com"}"#,
+    );
+    write(&flat.join(".git/HEAD"), "ignored");
+    write(
+        &flat.join("fo/o~/foo~bar"),
+        &(index_line("foo~bar", "0.1.0", "") + "\n"),
+    );
+    write(
+        &flat.join("3/b/baz"),
+        &(index_line("baz", "1.0.0", &index_dep("foo~bar")) + "\n"),
+    );
+
+    cargo_process("-Z unstable-options index build out --from-index flat")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[MOVING] fo/o~/foo~bar to 3/f/foo@/bar")
+        .run();
+
+    let out = paths::root().join("out");
+    assert_eq!(
+        t!(fs::read_to_string(out.join("config.json"))),
+        r#"{"dl": "https://example.com"}"#
+    );
+    assert!(!out.join(".git").exists());
+    assert_eq!(
+        t!(fs::read_to_string(out.join("3/f/foo@/bar"))),
+        index_line("foo/bar", "0.1.0", "") + "\n"
+    );
+    assert_eq!(
+        t!(fs::read_to_string(out.join("3/b/baz"))),
+        index_line("baz", "1.0.0", &index_dep("foo/bar")) + "\n"
+    );
+
+    cargo_process("-Z unstable-options index verify out")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[VERIFIED] `[..]out`")
+        .run();
+}
+
+#[cargo_test]
+fn verify_reports_problems() {
+    let index = paths::root().join("index");
+    write(
+        &index.join("config.json"),
+        r#"{"dl": "https://example.com", "namespaces": {"max-depth": 1}}"#,
+    );
+    write(
+        &index.join("3/b/baz"),
+        &format!(
+            "{}\n{}\n{}\n",
+            index_line("baz", "1.0.0", &index_dep("foo~bar")),
+            index_line("baz", "1.0.0", &index_dep("foo/missing")),
+            index_line("baz", "1.1.0", &index_dep("a/b/c")),
+        ),
+    );
+    write(
+        &index.join("fo/o~/foo~bar"),
+        &(index_line("foo/bar", "0.1.0", "") + "\nnot json\n"),
+    );
+    write(
+        &index.join("3/q/qux"),
+        &(index_line("qux", "0.1.0", "") + "\n"),
+    );
+    write(&paths::root().join("crates/qux-0.1.0.crate"), "not a crate");
+
+    cargo_process("-Z unstable-options index verify index --crates crates")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] 3/b/baz: dependency `foo~bar` of `baz` v1.0.0 should be written `foo/bar`
+[ERROR] 3/b/baz: `baz` v1.0.0 has no `.crate` file at `[..]crates/baz-1.0.0.crate`
+[ERROR] 3/b/baz: `baz` v1.0.0 is listed more than once
+[ERROR] 3/b/baz: `baz` v1.0.0 depends on `foo/missing`, which is not in this index
+[ERROR] 3/b/baz: `baz` v1.0.0 has no `.crate` file at `[..]crates/baz-1.0.0.crate`
+[ERROR] 3/b/baz: dependency of `baz` v1.1.0 is invalid: the name `a/b/c` cannot be used \
+as a dependency name, crates can be namespaced at most 1 levels deep
+[ERROR] 3/b/baz: `baz` v1.1.0 has no `.crate` file at `[..]crates/baz-1.1.0.crate`
+[ERROR] 3/q/qux: `qux` v0.1.0 has cksum `0000[..]`, but `[..]qux-0.1.0.crate` has cksum `[..]`
+[ERROR] fo/o~/foo~bar: line 2 is not a valid index entry: expected ident at line 1 column 2
+[ERROR] fo/o~/foo~bar: `foo/bar` should be at `3/f/foo@/bar` in a namespaced index
+[ERROR] fo/o~/foo~bar: `foo/bar` v0.1.0 has no `.crate` file at `[..]crates/foo~bar-0.1.0.crate`
+[ERROR] found 11 problem(s) in the index at `[..]index`
+",
+        )
+        .run();
+}
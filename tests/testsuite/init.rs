@@ -614,3 +614,20 @@ mod tests {
 "#
     );
 }
+
+#[cargo_test]
+fn namespace_flag() {
+    let path = paths::root().join("bar");
+    fs::create_dir(&path).unwrap();
+    cargo_process("init --lib --namespace foo --vcs none")
+        .cwd(&path)
+        .env("USER", "foo")
+        .with_stderr("[CREATED] library package")
+        .run();
+
+    let toml = fs::read_to_string(path.join("Cargo.toml")).unwrap();
+    assert!(toml.contains(r#"name = "foo/bar""#));
+    assert!(toml.contains("[lib]\nname = \"foo_bar\"\n"));
+
+    cargo_process("build").cwd(&path).run();
+}
@@ -0,0 +1,144 @@
+//! Tests for -Zjob-limits.
+
+use cargo_test_support::project;
+
+#[cargo_test]
+fn max_concurrent_links() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [[bin]]
+                name = "a"
+                path = "src/a.rs"
+
+                [[bin]]
+                name = "b"
+                path = "src/b.rs"
+
+                [[bin]]
+                name = "c"
+                path = "src/c.rs"
+            "#,
+        )
+        .file("src/a.rs", "fn main() {}")
+        .file("src/b.rs", "fn main() {}")
+        .file("src/c.rs", "fn main() {}")
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build]
+                max-concurrent-links = 1
+            "#,
+        )
+        .build();
+
+    p.cargo("build -j4 -Zjob-limits -Ztimings=info")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[..]Timing 2 units waited [..]s for the link limit")
+        .run();
+}
+
+#[cargo_test]
+fn min_free_memory() {
+    if !cfg!(target_os = "linux") {
+        // Free memory is only known on Linux.
+        return;
+    }
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [[bin]]
+                name = "a"
+                path = "src/a.rs"
+
+                [[bin]]
+                name = "b"
+                path = "src/b.rs"
+
+                [[bin]]
+                name = "c"
+                path = "src/c.rs"
+            "#,
+        )
+        .file("src/a.rs", "fn main() {}")
+        .file("src/b.rs", "fn main() {}")
+        .file("src/c.rs", "fn main() {}")
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build]
+                min-free-memory = "1000000GiB"
+            "#,
+        )
+        .build();
+
+    // One unit always runs, so the build finishes.
+    p.cargo("build -j4 -Zjob-limits -Ztimings=info")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[..]Timing 2 units waited [..]s for free memory")
+        .run();
+}
+
+#[cargo_test]
+fn invalid_limits() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [[bin]]
+                name = "a"
+                path = "src/a.rs"
+
+                [[bin]]
+                name = "b"
+                path = "src/b.rs"
+
+                [[bin]]
+                name = "c"
+                path = "src/c.rs"
+            "#,
+        )
+        .file("src/a.rs", "fn main() {}")
+        .file("src/b.rs", "fn main() {}")
+        .file("src/c.rs", "fn main() {}")
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build]
+                max-concurrent-links = 0
+            "#,
+        )
+        .build();
+    p.cargo("build -Zjob-limits")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] `build.max-concurrent-links` must be at least 1")
+        .run();
+
+    p.change_file(
+        ".cargo/config.toml",
+        r#"
+            [build]
+            min-free-memory = "lots"
+        "#,
+    );
+    p.cargo("build -Zjob-limits")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] `build.min-free-memory` must be a size such as `2GiB`, found `lots`")
+        .run();
+}
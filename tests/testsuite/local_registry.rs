@@ -2,7 +2,7 @@
 
 use cargo_test_support::paths::{self, CargoPathExt};
 use cargo_test_support::registry::{registry_path, Package};
-use cargo_test_support::{basic_manifest, project, t};
+use cargo_test_support::{basic_manifest, namespaced_name, project, t};
 use std::fs;
 
 fn setup() {
@@ -488,3 +488,90 @@ fn crates_io_registry_url_is_optional() {
     p.cargo("build").with_stderr("[FINISHED] [..]").run();
     p.cargo("test").run();
 }
+
+#[cargo_test]
+fn namespaced_crate_offline() {
+    setup();
+    let foo_bar = namespaced_name(&["foo", "bar"]);
+    Package::new(&foo_bar, "0.1.0")
+        .local(true)
+        .file("src/lib.rs", "pub fn bar() {}")
+        .publish();
+    // The `.crate` file uses `~` in place of `/`, like `cargo package`.
+    assert!(registry_path().join("foo~bar-0.1.0.crate").is_file());
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [project]
+                    name = "foo"
+                    version = "0.0.1"
+                    authors = []
+
+                    [dependencies]
+                    "{}" = "0.1.0"
+                "#,
+                foo_bar
+            ),
+        )
+        .file("src/lib.rs", "pub fn foo() { foo_bar::bar(); }")
+        .build();
+
+    p.cargo("build --offline")
+        .with_stderr(
+            "\
+[UNPACKING] foo/bar v0.1.0 ([..])
+[COMPILING] foo/bar v0.1.0
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert!(paths::home()
+        .join(".cargo/registry/src")
+        .read_dir()
+        .unwrap()
+        .any(|dir| dir.unwrap().path().join("foo~bar-0.1.0").is_dir()));
+}
+
+#[cargo_test]
+fn namespaced_crate_file_missing() {
+    setup();
+    let foo_bar = namespaced_name(&["foo", "bar"]);
+    Package::new(&foo_bar, "0.1.0").local(true).publish();
+    // A name with `_` in place of `/` isn't found.
+    fs::rename(
+        registry_path().join("foo~bar-0.1.0.crate"),
+        registry_path().join("foo_bar-0.1.0.crate"),
+    )
+    .unwrap();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [project]
+                    name = "foo"
+                    version = "0.0.1"
+                    authors = []
+
+                    [dependencies]
+                    "{}" = "0.1.0"
+                "#,
+                foo_bar
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build --offline")
+        .with_status(101)
+        .with_stderr_contains(
+            "[..]failed to find `foo~bar-0.1.0.crate` for `foo/bar v0.1.0 ([..])` \
+             in the local registry",
+        )
+        .run();
+}
@@ -1,8 +1,9 @@
 //! Tests for supporting older versions of the Cargo.lock file format.
 
 use cargo_test_support::git;
-use cargo_test_support::registry::Package;
+use cargo_test_support::registry::{self, Package};
 use cargo_test_support::{basic_lib_manifest, basic_manifest, lines_match, project};
+use cargo_test_support::{namespaced_name, Project};
 
 #[cargo_test]
 fn oldest_lockfile_still_works() {
@@ -689,6 +690,105 @@ dependencies = [
     assert_lockfiles_eq(&lockfile, &lock);
 }
 
+/// A project depending on the namespaced `ns/bar` from crates.io and on `baz`
+/// from the alternative registry.
+fn namespaced_and_alt_registry_project(lockfile: &str) -> Project {
+    Package::new(&namespaced_name(&["ns", "bar"]), "0.1.0").publish();
+    Package::new("baz", "0.1.0").alternative(true).publish();
+
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [project]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                "ns/bar" = "0.1.0"
+                baz = { version = "0.1.0", registry = "alternative" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("Cargo.lock", lockfile)
+        .build()
+}
+
+fn v4_lockfile() -> String {
+    format!(
+        r#"# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 4
+
+[[package]]
+name = "baz"
+version = "0.1.0"
+source = "registry+{}"
+registry = "alternative"
+checksum = "[..]"
+
+[[package]]
+name = "foo"
+version = "0.0.1"
+dependencies = [
+ "baz",
+ "ns%2Fbar",
+]
+
+[[package]]
+name = "ns/bar"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "[..]"
+"#,
+        registry::alt_registry_url(),
+    )
+}
+
+#[cargo_test]
+fn v4_namespaced_and_alt_registry() {
+    let p = namespaced_and_alt_registry_project("version = 4");
+
+    p.cargo("fetch").run();
+
+    let lock = p.read_lockfile();
+    assert_lockfiles_eq(&v4_lockfile(), &lock);
+
+    // The encoded names and registry keys read back to the same resolve.
+    p.cargo("fetch --locked").run();
+    assert_eq!(lock, p.read_lockfile());
+}
+
+#[cargo_test]
+fn next_lockfile_bump_upgrades_v3() {
+    let p = namespaced_and_alt_registry_project(
+        r#"# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 3
+
+[[package]]
+name = "foo"
+version = "0.0.1"
+dependencies = [
+ "ns/bar",
+]
+
+[[package]]
+name = "ns/bar"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+    );
+
+    p.cargo("fetch -Znext-lockfile-bump")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let lock = p.read_lockfile();
+    assert_lockfiles_eq(&v4_lockfile(), &lock);
+}
+
 #[cargo_test]
 fn lock_from_the_future() {
     let p = project()
@@ -17,10 +17,13 @@ mod bad_config;
 mod bad_manifest_path;
 mod bench;
 mod build;
+mod build_cache;
 mod build_plan;
 mod build_script;
+mod build_script_cache;
 mod build_script_env;
 mod build_script_extra_link_arg;
+mod build_script_sandbox;
 mod build_subcrate;
 mod cache_messages;
 mod cargo_alias_config;
@@ -46,6 +49,7 @@ mod directory;
 mod doc;
 mod edition;
 mod error;
+mod explain_rebuild;
 mod features;
 mod features2;
 mod features_namespaced;
@@ -58,9 +62,11 @@ mod git_auth;
 mod git_gc;
 mod glob_targets;
 mod help;
+mod index;
 mod init;
 mod install;
 mod install_upgrade;
+mod job_limits;
 mod jobserver;
 mod list_availables;
 mod local_registry;
@@ -112,6 +118,7 @@ mod rustdocflags;
 mod rustflags;
 mod search;
 mod shell_quoting;
+mod sparse_registry;
 mod standard_lib;
 mod test;
 mod timings;
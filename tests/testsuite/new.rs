@@ -699,3 +699,180 @@ fn git_default_branch() {
     let head = repo.find_reference("HEAD").unwrap();
     assert_eq!(head.symbolic_target().unwrap(), "refs/heads/hello");
 }
+
+#[cargo_test]
+fn namespace_flag() {
+    cargo_process("new --lib --namespace foo bar --vcs none")
+        .env("USER", "foo")
+        .with_stderr("[CREATED] library `foo/bar` package")
+        .run();
+
+    let toml = fs::read_to_string(paths::root().join("foo/bar/Cargo.toml")).unwrap();
+    assert!(toml.contains(r#"name = "foo/bar""#));
+    assert!(toml.contains("[lib]\nname = \"foo_bar\"\n"));
+    assert!(paths::root().join("foo/bar/src/lib.rs").is_file());
+
+    cargo_process("build")
+        .cwd(&paths::root().join("foo/bar"))
+        .with_stderr(
+            "\
+[COMPILING] foo/bar v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn namespaced_flag_flat_layout() {
+    cargo_process("new foo/bar --namespaced --namespace-layout flat --vcs none")
+        .env("USER", "foo")
+        .with_stderr("[CREATED] binary (application) `foo/bar` package")
+        .run();
+
+    assert!(!paths::root().join("foo").exists());
+    let toml = fs::read_to_string(paths::root().join("foo-bar/Cargo.toml")).unwrap();
+    assert!(toml.contains(r#"name = "foo/bar""#));
+    assert!(!toml.contains("[lib]"));
+    assert!(paths::root().join("foo-bar/src/main.rs").is_file());
+}
+
+#[cargo_test]
+fn namespaced_flag_requires_namespaced_path() {
+    cargo_process("new foo --namespaced")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `--namespaced` requires a namespaced package name like `foo/bar`, found `foo`",
+        )
+        .run();
+
+    cargo_process("new foo/bar --namespaced --namespace baz")
+        .with_status(101)
+        .with_stderr("[ERROR] can't specify both --namespace and --namespaced")
+        .run();
+}
+
+#[cargo_test]
+fn namespaced_path_suggests_flag() {
+    cargo_process("new foo/bar --vcs none")
+        .env("USER", "foo")
+        .with_stderr(
+            "\
+[WARNING] Cargo will use only the directory name as a crate name (bar)
+The path that you provided is also a valid namespaced crate name, so this may not be what you intended.
+Use --namespaced to create the package `foo/bar` instead.
+If you need a crate name to not match the directory name, consider using --name flag.
+[CREATED] binary (application) `bar` package
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn adds_to_workspace_members() {
+    fs::write(
+        paths::root().join("Cargo.toml"),
+        r#"
+            [workspace]
+            members = [
+                "crates/*", # everything in crates
+            ]
+            exclude = ["vendor"]
+        "#,
+    )
+    .unwrap();
+    paths::root().join("crates").mkdir_p();
+
+    // Already covered by the `crates/*` glob, and excluded, respectively.
+    cargo_process("new crates/baz --name baz --vcs none")
+        .env("USER", "foo")
+        .with_stderr("[CREATED] binary (application) `baz` package")
+        .run();
+    cargo_process("new vendor/qux --name qux --vcs none")
+        .env("USER", "foo")
+        .with_stderr("[CREATED] binary (application) `qux` package")
+        .run();
+
+    cargo_process("new --namespace foo bar --vcs none")
+        .env("USER", "foo")
+        .with_stderr(
+            "\
+[ADDING] `foo/bar` as member of workspace at `[ROOT]`
+[CREATED] binary (application) `foo/bar` package
+",
+        )
+        .run();
+
+    assert_eq!(
+        fs::read_to_string(paths::root().join("Cargo.toml")).unwrap(),
+        r#"
+            [workspace]
+            members = [
+                "crates/*", # everything in crates
+                "foo/bar",
+            ]
+            exclude = ["vendor"]
+        "#
+    );
+    cargo_process("build")
+        .cwd(&paths::root().join("foo/bar"))
+        .with_stderr(
+            "\
+[COMPILING] foo/bar v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn workspace_members_untouched_without_namespace() {
+    let manifest = r#"
+            [workspace]
+            members = ["a"]
+        "#;
+    fs::write(paths::root().join("Cargo.toml"), manifest).unwrap();
+
+    cargo_process("new crates/x --vcs none")
+        .env("USER", "foo")
+        .with_stderr_contains("[CREATED] binary (application) `x` package")
+        .with_stderr_does_not_contain("[ADDING] [..]")
+        .run();
+
+    assert_eq!(
+        fs::read_to_string(paths::root().join("Cargo.toml")).unwrap(),
+        manifest
+    );
+}
+
+#[cargo_test]
+fn adds_to_workspace_members_multiline_strings() {
+    fs::write(
+        paths::root().join("Cargo.toml"),
+        r#"
+            [workspace]
+            members = [
+                """a""",
+                '''it's''',
+            ]
+        "#,
+    )
+    .unwrap();
+
+    cargo_process("new --namespace foo bar --vcs none")
+        .env("USER", "foo")
+        .with_stderr_contains("[ADDING] `foo/bar` as member of workspace at `[ROOT]`")
+        .run();
+
+    assert_eq!(
+        fs::read_to_string(paths::root().join("Cargo.toml")).unwrap(),
+        r#"
+            [workspace]
+            members = [
+                """a""",
+                '''it's''',
+                "foo/bar",
+            ]
+        "#
+    );
+}
@@ -2,9 +2,10 @@
 
 use std::fs;
 
+use cargo_test_support::http::{HttpServer, Response};
 use cargo_test_support::paths::CargoPathExt;
-use cargo_test_support::project;
-use cargo_test_support::registry::{self, api_path};
+use cargo_test_support::registry::{self, api_path, dl_url, registry_path};
+use cargo_test_support::{cargo_process, project};
 
 fn setup(name: &str, content: Option<&str>) {
     let dir = api_path().join(format!("api/v1/crates/{}", name));
@@ -14,6 +15,20 @@ fn setup(name: &str, content: Option<&str>) {
     }
 }
 
+/// Points the registry's web API at an `HttpServer` answering with `handler`.
+fn setup_server<F>(handler: F) -> HttpServer
+where
+    F: Fn(&cargo_test_support::http::Request) -> Response + Send + Sync + 'static,
+{
+    registry::init();
+    let server = HttpServer::new(handler);
+    registry::write_index_config(
+        &registry_path(),
+        &format!(r#"{{"dl":"{}","api":"{}"}}"#, dl_url(), server.url()),
+    );
+    server
+}
+
 #[cargo_test]
 fn simple_list() {
     registry::init();
@@ -118,3 +133,119 @@ Caused by:
         )
         .run();
 }
+
+#[cargo_test]
+fn namespace_list() {
+    let server = setup_server(|req| {
+        assert_eq!(req.method, "GET");
+        assert_eq!(req.path, "/api/v1/namespaces/foo/owners");
+        assert_eq!(req.header("authorization"), Some("sekrit"));
+        Response::json(
+            200,
+            r#"{
+                "users": [
+                    {"id": 70, "login": "github:rust-lang:core", "name": "Core"},
+                    {"id": 123, "login": "octocat"}
+                ]
+            }"#,
+        )
+    });
+
+    cargo_process("owner --namespace foo/ -l --token sekrit")
+        .with_stdout(
+            "\
+github:rust-lang:core (Core)
+octocat
+",
+        )
+        .run();
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[cargo_test]
+fn namespace_add_and_remove() {
+    let server = setup_server(|req| {
+        assert_eq!(req.path, "/api/v1/namespaces/foo/owners");
+        assert_eq!(req.body_str(), r#"{"users":["octocat"]}"#);
+        Response::json(
+            200,
+            r#"{"ok": true, "msg": "user octocat has been invited"}"#,
+        )
+    });
+
+    cargo_process("owner --namespace foo -a octocat --token sekrit")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+       Owner user octocat has been invited
+",
+        )
+        .run();
+
+    cargo_process("owner --namespace foo -r octocat --token sekrit")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+       Owner removing [\"octocat\"] from namespace `foo`
+",
+        )
+        .run();
+
+    let methods: Vec<_> = server.requests().into_iter().map(|r| r.method).collect();
+    assert_eq!(methods, ["PUT", "DELETE"]);
+}
+
+#[cargo_test]
+fn namespace_not_owner() {
+    let _server = setup_server(|_req| {
+        Response::json(
+            403,
+            r#"{"errors": [{"detail": "must already be an owner to modify owners"}]}"#,
+        )
+    });
+
+    cargo_process("owner --namespace foo -a octocat --token sekrit")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] failed to invite owners to namespace `foo`
+
+Caused by:
+  you are not an owner of the namespace `foo`
+
+Caused by:
+  api errors (status 403 Forbidden): must already be an owner to modify owners
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn namespace_missing() {
+    let _server = setup_server(|_req| Response::status(404));
+
+    cargo_process("owner --namespace foo -l --token sekrit")
+        .with_status(101)
+        .with_stderr_contains("[ERROR] failed to list owners of namespace `foo`")
+        .with_stderr_contains("  the namespace `foo` does not exist on this registry")
+        .with_stderr_contains("  failed to get a 200 OK response, got 404")
+        .run();
+}
+
+#[cargo_test]
+fn namespace_invalid() {
+    registry::init();
+
+    cargo_process("owner --namespace foo/bar -l --token sekrit")
+        .with_status(101)
+        .with_stderr("[ERROR] invalid namespace `foo/bar`: only top-level namespaces have owners")
+        .run();
+
+    cargo_process("owner foo --namespace foo -l --token sekrit")
+        .with_status(1)
+        .with_stderr_contains(
+            "error: The argument '<crate>' cannot be used with '--namespace <NAMESPACE>'",
+        )
+        .run();
+}
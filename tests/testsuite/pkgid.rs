@@ -1,7 +1,7 @@
 //! Tests for the `cargo pkgid` command.
 
-use cargo_test_support::project;
 use cargo_test_support::registry::Package;
+use cargo_test_support::{namespaced_name, project};
 
 #[cargo_test]
 fn simple() {
@@ -32,3 +32,57 @@ fn simple() {
         .with_stdout("https://github.com/rust-lang/crates.io-index#bar:0.1.0")
         .run();
 }
+
+#[cargo_test]
+fn namespaced() {
+    Package::new(&namespaced_name(&["foo", "bar"]), "0.1.0").publish();
+    Package::new("bar", "0.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2018"
+
+                [dependencies]
+                "{}" = "0.1.0"
+                bar = "0.1.0"
+            "#,
+                namespaced_name(&["foo", "bar"])
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+
+    p.cargo("pkgid foo/bar")
+        .with_stdout("https://github.com/rust-lang/crates.io-index#foo/bar:0.1.0")
+        .run();
+
+    p.cargo("pkgid foo/bar@0.1.0")
+        .with_stdout("https://github.com/rust-lang/crates.io-index#foo/bar:0.1.0")
+        .run();
+
+    p.cargo("pkgid registry+https://github.com/rust-lang/crates.io-index#foo/bar@0.1.0")
+        .with_stdout("https://github.com/rust-lang/crates.io-index#foo/bar:0.1.0")
+        .run();
+
+    p.cargo("pkgid bar")
+        .with_stdout("https://github.com/rust-lang/crates.io-index#bar:0.1.0")
+        .run();
+
+    p.cargo("pkgid foo_bar")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] package ID specification `foo_bar` matched no packages
+
+<tab>Did you mean `foo/bar`?
+",
+        )
+        .run();
+}
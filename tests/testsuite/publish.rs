@@ -1,6 +1,7 @@
 //! Tests for the `cargo publish` command.
 
-use cargo_test_support::paths;
+use cargo_test_support::http::{HttpServer, Response};
+use cargo_test_support::paths::{self, CargoPathExt};
 use cargo_test_support::registry::{self, registry_path, registry_url, Package};
 use cargo_test_support::{basic_manifest, no_such_file_err_msg, project, publish};
 use cargo_test_support::{
@@ -142,6 +143,13 @@ See [..]
 #[cargo_test]
 fn simple_with_a_subcrate() {
     registry::init();
+    let ns = registry::api_path().join("api/v1/namespaces");
+    ns.mkdir_p();
+    fs::write(
+        ns.join("foo"),
+        r#"{"name": "foo", "owners": [{"id": 1, "login": "me"}], "is_owner": true}"#,
+    )
+    .unwrap();
 
     let p = project()
         .file(
@@ -1530,3 +1538,317 @@ Caused by:
         ))
         .run();
 }
+
+/// Points the registry's web API at a server answering `GET
+/// /api/v1/namespaces/foo` with `response`, and fails anything else.
+fn namespace_server(response: Response) -> HttpServer {
+    registry::init();
+    let server = HttpServer::new(move |req| {
+        if req.method == "GET" && req.path == "/api/v1/namespaces/foo" {
+            response.clone()
+        } else {
+            Response::status(500)
+        }
+    });
+    registry::write_index_config(
+        &registry_path(),
+        &format!(
+            r#"{{"dl":"{}","api":"{}"}}"#,
+            registry::dl_url(),
+            server.url()
+        ),
+    );
+    server
+}
+
+#[cargo_test]
+fn namespace_owned_by_someone_else() {
+    let server = namespace_server(Response::json(
+        200,
+        r#"{"name": "foo", "owners": [{"id": 1, "login": "alice"}], "is_owner": false}"#,
+    ));
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [project]
+                    name = "{}"
+                    version = "0.0.1"
+                    authors = []
+                    license = "MIT"
+                    description = "foo bar"
+                "#,
+                namespaced_name(&["foo", "bar"])
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    let expected = "\
+[UPDATING] `[..]` index
+[ERROR] `foo/bar` cannot be published to registry `[..]registry`
+The namespace `foo/` is owned by `alice`, and you are not one of its owners.
+Ask an owner to add you with `cargo owner --namespace foo --add <login>`.
+";
+    p.cargo("publish --no-verify --token sekrit")
+        .with_status(101)
+        .with_stderr(expected)
+        .run();
+
+    // `--dry-run` runs the same check.
+    p.cargo("publish --dry-run --token sekrit")
+        .with_status(101)
+        .with_stderr(expected)
+        .run();
+
+    let reqs = server.requests();
+    assert_eq!(reqs.len(), 2);
+    assert!(reqs
+        .iter()
+        .all(|r| r.header("authorization") == Some("sekrit")));
+}
+
+#[cargo_test]
+fn namespace_owner_checked_with_saved_token() {
+    let server = namespace_server(Response::json(
+        200,
+        r#"{"name": "foo", "owners": [{"id": 1, "login": "alice"}], "is_owner": false}"#,
+    ));
+    fs::write(
+        paths::home().join(".cargo/credentials"),
+        r#"
+            [registry]
+            token = "saved-token"
+        "#,
+    )
+    .unwrap();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [project]
+                    name = "{}"
+                    version = "0.0.1"
+                    authors = []
+                    license = "MIT"
+                    description = "foo bar"
+                "#,
+                namespaced_name(&["foo", "bar"])
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    // The token saved by `cargo login` is used even though a dry run
+    // doesn't need one.
+    p.cargo("publish --dry-run --no-verify")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[WARNING] using `registry.token` config value with source replacement is deprecated
+This may become a hard error in the future; see <https://github.com/rust-lang/cargo/issues/xxx>.
+Use the --token command-line flag to remove this warning.
+[ERROR] `foo/bar` cannot be published to registry `[..]registry`
+The namespace `foo/` is owned by `alice`, and you are not one of its owners.
+Ask an owner to add you with `cargo owner --namespace foo --add <login>`.
+",
+        )
+        .run();
+
+    let reqs = server.requests();
+    assert_eq!(reqs.len(), 1);
+    assert_eq!(reqs[0].header("authorization"), Some("saved-token"));
+}
+
+#[cargo_test]
+fn namespace_unclaimed() {
+    // Nobody has claimed the namespace yet, so the publish can go ahead.
+    let _server = namespace_server(Response::status(404));
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [project]
+                    name = "{}"
+                    version = "0.0.1"
+                    authors = []
+                    license = "MIT"
+                    description = "foo bar"
+                "#,
+                namespaced_name(&["foo", "bar"])
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("publish --dry-run --no-verify --token sekrit")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[WARNING] manifest has no documentation, [..]
+See [..]
+[PACKAGING] foo/bar v0.0.1 ([CWD])
+[UPLOADING] foo/bar v0.0.1 ([CWD])
+[WARNING] aborting upload due to dry run
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn namespace_owner_unknown_without_token() {
+    let server = namespace_server(Response::json(
+        200,
+        r#"{"name": "foo", "owners": [{"id": 1, "login": "alice"}], "is_owner": null}"#,
+    ));
+    // No token is configured at all, so a dry run can only warn.
+    fs::remove_file(paths::home().join(".cargo/credentials")).unwrap();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [project]
+                    name = "{}"
+                    version = "0.0.1"
+                    authors = []
+                    license = "MIT"
+                    description = "foo bar"
+                "#,
+                namespaced_name(&["foo", "bar"])
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("publish --dry-run --no-verify")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[WARNING] could not check whether you own the namespace `foo/` (owned by `alice`) \
+without an API token
+[WARNING] manifest has no documentation, [..]
+See [..]
+[PACKAGING] foo/bar v0.0.1 ([CWD])
+[UPLOADING] foo/bar v0.0.1 ([CWD])
+[WARNING] aborting upload due to dry run
+",
+        )
+        .run();
+
+    let reqs = server.requests();
+    assert_eq!(reqs.len(), 1);
+    assert_eq!(reqs[0].header("authorization"), None);
+}
+
+#[cargo_test]
+fn namespace_owner_not_reported() {
+    // The token is sent, but the registry leaves out `is_owner`.
+    let _server = namespace_server(Response::json(
+        200,
+        r#"{"name": "foo", "owners": [{"id": 1, "login": "alice"}]}"#,
+    ));
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [project]
+                    name = "{}"
+                    version = "0.0.1"
+                    authors = []
+                    license = "MIT"
+                    description = "foo bar"
+                "#,
+                namespaced_name(&["foo", "bar"])
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("publish --dry-run --no-verify --token sekrit")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[WARNING] could not check whether you own the namespace `foo/` (owned by `alice`), \
+registry `[..]registry` did not say whether you are one of its owners
+[WARNING] manifest has no documentation, [..]
+See [..]
+[PACKAGING] foo/bar v0.0.1 ([CWD])
+[UPLOADING] foo/bar v0.0.1 ([CWD])
+[WARNING] aborting upload due to dry run
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn namespace_response_invalid() {
+    // The registry has the endpoint, so a body Cargo can't read is an error.
+    let _server = namespace_server(Response::json(200, r#"{"namespace": "foo"}"#));
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [project]
+                    name = "{}"
+                    version = "0.0.1"
+                    authors = []
+                    license = "MIT"
+                    description = "foo bar"
+                "#,
+                namespaced_name(&["foo", "bar"])
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("publish --dry-run --no-verify --token sekrit")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] failed to check the owners of namespace `foo/`
+
+Caused by:
+  missing field `name` at line 1 column 20
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn namespace_endpoint_unsupported() {
+    // A registry without namespace support doesn't have the endpoint.
+    let _server = namespace_server(Response::status(501));
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [project]
+                    name = "{}"
+                    version = "0.0.1"
+                    authors = []
+                    license = "MIT"
+                    description = "foo bar"
+                "#,
+                namespaced_name(&["foo", "bar"])
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("publish --dry-run --no-verify --token sekrit")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[WARNING] registry `[..]registry` does not support checking the owners of namespace \
+`foo/`, skipping the check
+[WARNING] manifest has no documentation, [..]
+See [..]
+[PACKAGING] foo/bar v0.0.1 ([CWD])
+[UPLOADING] foo/bar v0.0.1 ([CWD])
+[WARNING] aborting upload due to dry run
+",
+        )
+        .run();
+}
@@ -1,9 +1,9 @@
 //! Tests for the `cargo search` command.
 
-use cargo_test_support::cargo_process;
 use cargo_test_support::git::repo;
-use cargo_test_support::paths;
-use cargo_test_support::registry::{api_path, registry_path, registry_url};
+use cargo_test_support::http::{HttpServer, Response};
+use cargo_test_support::registry::{self, api_path, dl_url, registry_path, registry_url, Package};
+use cargo_test_support::{cargo_process, namespaced_name, paths, project};
 use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
@@ -244,3 +244,141 @@ fn multiple_query_params() {
         .with_stdout_contains(SEARCH_RESULTS)
         .run();
 }
+
+/// Serves a namespace `foo` with `count` crates from the registry's web API.
+fn namespace_server(count: usize) -> HttpServer {
+    registry::init();
+    let server = HttpServer::new(move |req| {
+        let query = req.path.splitn(2, '?').nth(1).unwrap_or("");
+        let param = |name: &str| {
+            query
+                .split('&')
+                .find_map(|kv| kv.strip_prefix(name)?.strip_prefix('='))
+                .unwrap()
+        };
+        assert_eq!(param("namespace"), "foo");
+        let per_page: usize = param("per_page").parse().unwrap();
+        let page: usize = param("page").parse().unwrap();
+        let crates = ((page - 1) * per_page..count.min(page * per_page))
+            .map(|i| {
+                format!(
+                    r#"{{"name": "foo/c{:03}", "max_version": "1.0.0", "description": null}}"#,
+                    i
+                )
+            })
+            .collect::<Vec<_>>();
+        Response::json(
+            200,
+            &format!(
+                r#"{{"crates": [{}], "meta": {{"total": {}}}}}"#,
+                crates.join(","),
+                count
+            ),
+        )
+    });
+    registry::write_index_config(
+        &registry_path(),
+        &format!(r#"{{"dl":"{}","api":"{}"}}"#, dl_url(), server.url()),
+    );
+    server
+}
+
+#[cargo_test]
+fn namespace_lists_all_pages() {
+    let server = namespace_server(150);
+
+    let expected = (0..150)
+        .map(|i| format!("foo/c{:03} = \"1.0.0\"\n", i))
+        .collect::<String>();
+    cargo_process("search foo/").with_stdout(expected).run();
+
+    let paths = server
+        .requests()
+        .into_iter()
+        .map(|r| r.path)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        [
+            "/api/v1/crates?namespace=foo&per_page=100&page=1",
+            "/api/v1/crates?namespace=foo&per_page=100&page=2",
+        ]
+    );
+}
+
+#[cargo_test]
+fn namespace_with_limit() {
+    let _server = namespace_server(150);
+
+    cargo_process("search foo/ --limit 2")
+        .with_stdout(
+            "\
+foo/c000 = \"1.0.0\"
+foo/c001 = \"1.0.0\"
+... and 148 crates more (use --limit N to see more)
+",
+        )
+        .run();
+
+    cargo_process("search foo/ --limit 120")
+        .with_stdout_contains("foo/c119 = \"1.0.0\"")
+        .with_stdout_does_not_contain("foo/c120 [..]")
+        .with_stdout_contains("... and 30 crates more (use --limit N to see more)")
+        .run();
+}
+
+#[cargo_test]
+fn namespace_offline() {
+    let name = |parts: &[&str]| namespaced_name(parts);
+    Package::new(&name(&["foo", "bar"]), "0.1.0").publish();
+    Package::new(&name(&["foo", "baz"]), "0.1.0").publish();
+    Package::new(&name(&["foo", "baz"]), "0.2.0").publish();
+    Package::new(&name(&["foo", "gone"]), "1.0.0")
+        .yanked(true)
+        .publish();
+    Package::new(&name(&["foo", "sub", "deep"]), "1.0.0").publish();
+    Package::new(&name(&["other", "bar"]), "0.1.0").publish();
+    Package::new("foo", "0.1.0").publish();
+
+    // Fetch a copy of the index.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "a"
+                    version = "0.0.1"
+
+                    [dependencies]
+                    "{}" = "0.1"
+                "#,
+                name(&["foo", "bar"])
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile").run();
+
+    p.cargo("search foo/ --offline")
+        .with_stdout(
+            "\
+foo/bar = \"0.1.0\"
+foo/baz = \"0.2.0\"
+foo/sub/deep = \"1.0.0\"
+",
+        )
+        .with_stderr("")
+        .run();
+
+    p.cargo("search foo/ --offline --limit 1")
+        .with_stdout(
+            "\
+foo/bar = \"0.1.0\"
+... and 2 crates more (use --limit N to see more)
+",
+        )
+        .run();
+
+    p.cargo("search nothing/ --offline").with_stdout("").run();
+}
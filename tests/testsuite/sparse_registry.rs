@@ -0,0 +1,414 @@
+//! Tests for sparse HTTP registry indexes (`-Z sparse-registry`).
+
+use cargo_test_support::http::{HttpServer, Response};
+use cargo_test_support::registry::{self, Dependency, Package};
+use cargo_test_support::{namespaced_name, project};
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+/// The paths requested from `server` so far.
+fn requested(server: &HttpServer) -> Vec<String> {
+    server.requests().into_iter().map(|req| req.path).collect()
+}
+
+#[cargo_test]
+fn simple() {
+    let server = registry::sparse_index_server();
+    Package::new("bar", "0.0.1").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [project]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                bar = "0.0"
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [source.crates-io]
+                    replace-with = 'sparse'
+
+                    [source.sparse]
+                    registry = 'sparse+{}'
+                "#,
+                server.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `sparse+http://[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.0.1 (registry `sparse+http://[..]`)
+[COMPILING] bar v0.0.1
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    let mut paths = requested(&server);
+    paths.sort();
+    assert_eq!(paths, ["/3/b/bar", "/config.json"]);
+
+    // With a lock file, the cached copy of the index is used as it is.
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[cargo_test]
+fn revalidates_cached_files() {
+    let server = registry::sparse_index_server();
+    Package::new("bar", "0.0.1").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [project]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                bar = "0.0"
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [source.crates-io]
+                    replace-with = 'sparse'
+
+                    [source.sparse]
+                    registry = 'sparse+{}'
+                "#,
+                server.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    // Nothing changed, so the server only confirms the cached copy.
+    p.cargo("update -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[UPDATING] `sparse+http://[..]` index")
+        .run();
+    let requests = server.requests();
+    let bar = requests
+        .iter()
+        .filter(|req| req.path == "/3/b/bar")
+        .collect::<Vec<_>>();
+    assert_eq!(bar.len(), 2);
+    assert_eq!(bar[0].header("if-none-match"), None);
+    assert!(bar[1].header("if-none-match").is_some());
+
+    Package::new("bar", "0.0.2").publish();
+    p.cargo("update -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `sparse+http://[..]` index
+[UPDATING] bar v0.0.1 -> v0.0.2
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn prefetches_dependencies() {
+    let server = registry::sparse_index_server();
+    Package::new("baz", "0.0.1").publish();
+    Package::new("qux", "0.0.1").publish();
+    Package::new("bar", "0.0.1")
+        .dep("baz", "0.0.1")
+        .dev_dep("qux", "0.0.1")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [project]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                bar = "0.0"
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [source.crates-io]
+                    replace-with = 'sparse'
+
+                    [source.sparse]
+                    registry = 'sparse+{}'
+                "#,
+                server.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let mut paths = requested(&server);
+    paths.sort();
+    // Dev-dependencies of registry crates are never built, so they're not
+    // fetched.
+    assert_eq!(paths, ["/3/b/bar", "/3/b/baz", "/config.json"]);
+}
+
+#[cargo_test]
+fn missing_crate() {
+    let server = registry::sparse_index_server();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [project]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                bar = "0.0"
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [source.crates-io]
+                    replace-with = 'sparse'
+
+                    [source.sparse]
+                    registry = 'sparse+{}'
+                "#,
+                server.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no matching package named `bar` found")
+        .run();
+}
+
+#[cargo_test]
+fn offline_uses_cache() {
+    let server = registry::sparse_index_server();
+    Package::new("bar", "0.0.1").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [project]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                bar = "0.0"
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [source.crates-io]
+                    replace-with = 'sparse'
+
+                    [source.sparse]
+                    registry = 'sparse+{}'
+                "#,
+                server.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let requests = server.requests().len();
+    p.cargo("clean").run();
+    fs::remove_file(p.root().join("Cargo.lock")).unwrap();
+
+    p.cargo("build -Zsparse-registry --offline")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] bar v0.0.1
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert_eq!(server.requests().len(), requests);
+}
+
+#[cargo_test]
+fn namespaced_crate() {
+    let server = registry::sparse_index_server();
+    let foo_bar = namespaced_name(&["foo", "bar"]);
+    Package::new(&foo_bar, "0.0.1")
+        .file("src/lib.rs", "pub fn bar() {}")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [project]
+                    name = "foo"
+                    version = "0.0.1"
+                    authors = []
+
+                    [dependencies]
+                    "{}" = "0.0"
+                "#,
+                foo_bar
+            ),
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [source.crates-io]
+                    replace-with = 'sparse'
+
+                    [source.sparse]
+                    registry = 'sparse+{}'
+                "#,
+                server.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `sparse+http://[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] foo/bar v0.0.1 (registry `sparse+http://[..]`)
+[COMPILING] foo/bar v0.0.1
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert!(requested(&server).contains(&"/3/f/foo@/bar".to_string()));
+}
+
+#[cargo_test]
+fn failed_prefetch_of_unused_dependency() {
+    let server = registry::sparse_index_server_with(|req| match req.path.as_str() {
+        "/3/b/baz" => Some(Response::status(403)),
+        // Make sure `baz` is done by the time Cargo waits for `qux`.
+        "/3/q/qux" => {
+            thread::sleep(Duration::from_millis(500));
+            None
+        }
+        _ => None,
+    });
+    Package::new("baz", "0.0.1").publish();
+    Package::new("qux", "0.0.1").publish();
+    Package::new("bar", "0.0.1")
+        .add_dep(Dependency::new("baz", "0.0.1").optional(true))
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [project]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                bar = "0.0"
+                qux = "0.0"
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [source.crates-io]
+                    replace-with = 'sparse'
+
+                    [source.sparse]
+                    registry = 'sparse+{}'
+                "#,
+                server.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // `baz` is prefetched along with `bar`, but isn't needed, so its failure
+    // doesn't matter while waiting for `qux`.
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `sparse+http://[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] [..] v0.0.1 (registry `sparse+http://[..]`)
+[DOWNLOADED] [..] v0.0.1 (registry `sparse+http://[..]`)
+[COMPILING] [..] v0.0.1
+[COMPILING] [..] v0.0.1
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    // Once it's needed, the failure is reported.
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { version = "0.0", features = ["baz"] }
+            qux = "0.0"
+        "#,
+    );
+    p.cargo("update -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]failed to get 200 response from `http://[..]/3/b/baz`, got 403")
+        .run();
+}
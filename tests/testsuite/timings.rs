@@ -1,7 +1,8 @@
 //! Tests for -Ztimings.
 
-use cargo_test_support::project;
 use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project};
+use std::fs;
 
 #[cargo_test]
 fn timings_works() {
@@ -63,3 +64,236 @@ fn timings_works() {
 
     p.cargo("doc -Ztimings").masquerade_as_nightly_cargo().run();
 }
+
+#[cargo_test]
+fn timings_json_report() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -Ztimings=json")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains(r#"{"reason":"timing-info",[..]"#)
+        .with_stderr_contains("      Timing report saved to [..]/foo/cargo-timing-[..].json")
+        .run();
+
+    let report: serde_json::Value =
+        serde_json::from_str(&p.read_file("cargo-timing.json")).unwrap();
+    assert_eq!(report["version"], 1);
+    assert_eq!(report["profile"], "dev");
+    assert_eq!(report["fresh_units"], 0);
+    assert_eq!(report["dirty_units"], 2);
+    assert!(report["error"].is_null());
+    let units = report["units"].as_array().unwrap();
+    assert_eq!(units.len(), 2);
+    let bin = units.iter().find(|u| u["target"] == "bin \"foo\"").unwrap();
+    assert_eq!(bin["name"], "foo");
+    assert_eq!(bin["version"], "0.0.1");
+    assert!(bin["end"].as_f64().unwrap() >= bin["start"].as_f64().unwrap());
+    let lib = units.iter().find(|u| u["target"] == "").unwrap();
+    assert!(lib["rmeta"].is_number());
+    assert!(!report["concurrency"].as_array().unwrap().is_empty());
+}
+
+fn timings_report(units: &[(&str, f64, f64)]) -> String {
+    let units: Vec<_> = units
+        .iter()
+        .map(|(name, start, end)| {
+            serde_json::json!({
+                "name": name,
+                "version": "1.0.0",
+                "target": "",
+                "features": [],
+                "start": start,
+                "rmeta": null,
+                "end": end,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "version": 1,
+        "start": "2021-01-01T00:00:00Z",
+        "targets": ["foo 0.1.0 (lib)"],
+        "profile": "dev",
+        "rustc": "rustc 1.50.0",
+        "jobs": 2,
+        "ncpu": 2,
+        "duration": units.len() as f64,
+        "fresh_units": 0,
+        "dirty_units": units.len(),
+        "error": null,
+        "units": units,
+        "concurrency": [],
+        "cpu_usage": [],
+    })
+    .to_string()
+}
+
+#[cargo_test]
+fn report_timings_compare() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "old.json",
+            &timings_report(&[
+                ("slower", 0.0, 1.0),
+                ("same", 0.0, 1.0),
+                ("faster", 0.0, 3.0),
+            ]),
+        )
+        .file(
+            "new.json",
+            &timings_report(&[
+                ("slower", 0.0, 2.0),
+                ("same", 0.0, 1.05),
+                ("faster", 0.0, 1.0),
+            ]),
+        )
+        .build();
+
+    p.cargo("report timings --compare old.json new.json")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `cargo report` command is unstable, pass `-Z unstable-options` to enable it",
+        )
+        .run();
+
+    p.cargo("report timings --compare old.json new.json -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("      Timing comparison saved to [CWD]/cargo-timing-compare.html")
+        .run();
+
+    let html = p.read_file("cargo-timing-compare.html");
+    assert!(html.contains(
+        "<tr class=\"regression\">\n  <td>slower v1.0.0</td>\n  <td>1.0s</td>\n  <td>2.0s</td>\n  <td>+1.0s (+100%)</td>"
+    ));
+    assert!(html.contains("<tr class=\"\">\n  <td>same v1.0.0</td>"));
+    assert!(html.contains("<tr class=\"improvement\">\n  <td>faster v1.0.0</td>"));
+    assert!(html.contains("<td>Regressions:</td><td colspan=\"2\">1</td>"));
+}
+
+#[cargo_test]
+fn report_timings_compare_wrong_version() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("old.json", &timings_report(&[]))
+        .file("new.json", r#"{"version": 2}"#)
+        .build();
+
+    p.cargo("report timings --compare old.json new.json -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `[CWD]/new.json` is a version 2 timings report, but this version of \
+             Cargo only supports version 1",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn timings_critical_path() {
+    Package::new("dep", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies]
+            dep = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -Ztimings=html")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let html = p.read_file("cargo-timing.html");
+    // The path ends with the binary, which links against both libraries.
+    assert!(html.contains("<td>Critical path:</td><td>"));
+    assert!(html.contains("s (3 units)</td>"));
+    assert!(html.contains("<h2>Critical path</h2>"));
+    assert!(html.contains("<tr class=\"critical\">\n  <td>1.</td>"));
+    assert!(html.contains("\"critical_path\": true"));
+}
+
+#[cargo_test]
+fn durations_prioritize_queue() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                a = { path = "a" }
+                b = { path = "b" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Ztimings=info")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let path = p
+        .target_debug_dir()
+        .parent()
+        .unwrap()
+        .join(".unit-durations.json");
+    let durations: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    let keys: Vec<String> = durations["units"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    assert_eq!(keys.len(), 3);
+
+    // Pretend that one of the dependencies is much slower than the other,
+    // then rebuild both with one job: the slow one has to start first.
+    let check_order = |slow: &str, fast: &str| {
+        let units: serde_json::Map<String, serde_json::Value> = keys
+            .iter()
+            .map(|key| {
+                let secs = if key.starts_with(&format!("{}-", slow)) {
+                    100.0
+                } else {
+                    0.1
+                };
+                (key.clone(), secs.into())
+            })
+            .collect();
+        fs::write(&path, serde_json::json!({ "units": units }).to_string()).unwrap();
+        p.change_file("a/src/lib.rs", &format!("// {}", slow));
+        p.change_file("b/src/lib.rs", &format!("// {}", slow));
+        p.cargo("build -j1")
+            .with_stderr(&format!(
+                "\
+[COMPILING] {} v0.1.0 ([..])
+[COMPILING] {} v0.1.0 ([..])
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+",
+                slow, fast
+            ))
+            .run();
+    };
+    check_order("a", "b");
+    check_order("b", "a");
+}
@@ -1,7 +1,7 @@
 //! Tests for the `cargo update` command.
 
 use cargo_test_support::registry::Package;
-use cargo_test_support::{basic_manifest, project};
+use cargo_test_support::{basic_manifest, namespaced_name, project};
 
 #[cargo_test]
 fn minor_update_two_places() {
@@ -165,6 +165,47 @@ fn conservative() {
         .run();
 }
 
+#[cargo_test]
+fn conservative_namespaced() {
+    let foo_bar = namespaced_name(&["foo", "bar"]);
+    Package::new(&foo_bar, "0.1.0").publish();
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                "{}" = "0.1"
+                bar = "0.1"
+            "#,
+                foo_bar
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+
+    Package::new(&foo_bar, "0.1.1").publish();
+    Package::new("bar", "0.1.1").publish();
+
+    p.cargo("update -p foo/bar")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] foo/bar v0.1.0 -> v0.1.1
+",
+        )
+        .run();
+}
+
 #[cargo_test]
 fn update_via_new_dep() {
     Package::new("log", "0.1.0").publish();
@@ -652,6 +693,120 @@ fn dry_run_update() {
     assert_eq!(old_lockfile, new_lockfile)
 }
 
+#[cargo_test]
+fn dry_run_without_lockfile() {
+    Package::new("log", "0.1.0").publish();
+    Package::new("serde", "0.1.0").dep("log", "0.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                serde = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("update --dry-run")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] log v0.1.0
+[ADDING] serde v0.1.0
+[WARNING] not updating lockfile due to dry run
+",
+        )
+        .run();
+    assert!(!p.root().join("Cargo.lock").exists());
+}
+
+#[cargo_test]
+fn update_json() {
+    Package::new("log", "0.1.0").publish();
+    Package::new("serde", "0.1.0").dep("log", "0.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                serde = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+    let old_lockfile = p.read_lockfile();
+
+    Package::new("itoa", "0.1.0").publish();
+    Package::new("serde", "0.1.1")
+        .dep("log", "0.1")
+        .dep("itoa", "0.1")
+        .publish();
+
+    p.cargo("update --dry-run --message-format=json")
+        .with_json(
+            r#"
+                {
+                    "dry_run": true,
+                    "changes": [
+                        {
+                            "kind": "added",
+                            "name": "itoa",
+                            "old": null,
+                            "new": {
+                                "version": "0.1.0",
+                                "source": "registry+https://github.com/rust-lang/crates.io-index",
+                                "registry": null
+                            }
+                        },
+                        {
+                            "kind": "updated",
+                            "name": "serde",
+                            "old": {
+                                "version": "0.1.0",
+                                "source": "registry+https://github.com/rust-lang/crates.io-index",
+                                "registry": null
+                            },
+                            "new": {
+                                "version": "0.1.1",
+                                "source": "registry+https://github.com/rust-lang/crates.io-index",
+                                "registry": null
+                            }
+                        }
+                    ]
+                }
+            "#,
+        )
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] itoa v0.1.0
+[UPDATING] serde v0.1.0 -> v0.1.1
+[WARNING] not updating lockfile due to dry run
+",
+        )
+        .run();
+    assert_eq!(old_lockfile, p.read_lockfile());
+
+    p.cargo("update -p log --message-format=json")
+        .with_json(r#"{"dry_run": false, "changes": []}"#)
+        .run();
+}
+
 #[cargo_test]
 fn workspace_only() {
     let p = project().file("src/main.rs", "fn main() {}").build();
@@ -676,3 +831,113 @@ fn workspace_only() {
     assert!(!lock1.contains("0.0.2"));
     assert!(!lock2.contains("0.0.1"));
 }
+
+fn days_ago(days: u64) -> String {
+    let t = std::time::SystemTime::now() - std::time::Duration::from_secs(days * 24 * 60 * 60);
+    humantime::format_rfc3339_seconds(t).to_string()
+}
+
+#[cargo_test]
+fn update_max_age() {
+    Package::new("log", "0.1.0").pubtime(&days_ago(30)).publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                log = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+
+    Package::new("log", "0.1.1").pubtime(&days_ago(20)).publish();
+    Package::new("log", "0.1.2").pubtime(&days_ago(2)).publish();
+
+    p.cargo("update --max-age 14d")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] log v0.1.0 -> v0.1.1
+",
+        )
+        .run();
+
+    p.cargo("update --max-age 1day")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] log v0.1.1 -> v0.1.2
+",
+        )
+        .run();
+
+    p.cargo("update --max-age 14x")
+        .with_status(101)
+        .with_stderr("[ERROR] invalid value `14x` for --max-age: [..]")
+        .run();
+}
+
+#[cargo_test]
+fn max_age_config() {
+    Package::new("log", "0.2.0").pubtime(&days_ago(1)).publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                log = "0.2"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // Versions that are already locked stay usable.
+    p.cargo("generate-lockfile").run();
+    p.change_file(
+        ".cargo/config",
+        r#"
+            [registry]
+            max-age = "14d"
+        "#,
+    );
+    p.cargo("build")
+        .with_stderr(
+            "\
+[DOWNLOADING] crates ...
+[DOWNLOADED] log v0.2.0 (registry `[..]`)
+[COMPILING] log v0.2.0
+[COMPILING] bar v0.0.1 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.cargo("update")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] no matching package named `log` found
+location searched: registry `https://github.com/rust-lang/crates.io-index`
+required by package `bar v0.0.1 ([..])`
+the following versions were skipped because they were published less than 14days ago (`registry.max-age`):
+    log v0.2.0 (published [..])
+",
+        )
+        .run();
+}
@@ -8,7 +8,7 @@ use std::fs;
 
 use cargo_test_support::git;
 use cargo_test_support::registry::Package;
-use cargo_test_support::{basic_lib_manifest, paths, project, Project};
+use cargo_test_support::{basic_lib_manifest, namespaced_name, paths, project, Project};
 
 #[cargo_test]
 fn vendor_simple() {
@@ -634,6 +634,81 @@ fn config_instructions_works() {
         .run();
 }
 
+#[cargo_test]
+fn namespaced_packages() {
+    // `foo/bar` is vendored in `foo~bar`, which can't clash with `foo_bar`.
+    let foo_bar = namespaced_name(&["foo", "bar"]);
+    Package::new(&foo_bar, "0.1.0")
+        .file("src/lib.rs", "pub fn old() {}")
+        .publish();
+    Package::new(&foo_bar, "0.2.0")
+        .file("src/lib.rs", "pub fn new() {}")
+        .publish();
+    Package::new("foo_bar", "0.1.0")
+        .file("src/lib.rs", "pub fn other() {}")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                "{0}" = "0.2"
+                baz = {{ path = "baz" }}
+                "#,
+                foo_bar
+            ),
+        )
+        .file("src/lib.rs", "pub fn foo() { foo_bar::new(); }")
+        .file(
+            "baz/Cargo.toml",
+            &format!(
+                r#"
+                [package]
+                name = "baz"
+                version = "0.1.0"
+
+                [dependencies]
+                foo_bar = "0.1"
+                old = {{ package = "{0}", version = "0.1" }}
+                "#,
+                foo_bar
+            ),
+        )
+        .file(
+            "baz/src/lib.rs",
+            "pub fn baz() { foo_bar::other(); old::old(); }",
+        )
+        .build();
+
+    let output = p
+        .cargo("vendor --respect-source-config")
+        .exec_with_output()
+        .unwrap();
+    let output = String::from_utf8(output.stdout).unwrap();
+
+    let manifest = p.read_file("vendor/foo~bar/Cargo.toml");
+    assert!(manifest.contains(r#"name = "foo/bar""#));
+    assert!(manifest.contains(r#"version = "0.2.0""#));
+    let manifest = p.read_file("vendor/foo~bar-0.1.0/Cargo.toml");
+    assert!(manifest.contains(r#"name = "foo/bar""#));
+    assert!(manifest.contains(r#"version = "0.1.0""#));
+    let manifest = p.read_file("vendor/foo_bar/Cargo.toml");
+    assert!(manifest.contains(r#"name = "foo_bar""#));
+    assert!(!p.root().join("vendor/foo").exists());
+
+    p.change_file(".cargo/config", &output);
+    p.cargo("check -v")
+        .with_stderr_contains("[..]foo/vendor/foo~bar/src/lib.rs[..]")
+        .with_stderr_contains("[..]foo/vendor/foo~bar-0.1.0/src/lib.rs[..]")
+        .with_stderr_contains("[..]foo/vendor/foo_bar/src/lib.rs[..]")
+        .run();
+}
+
 #[cargo_test]
 fn git_crlf_preservation() {
     // Check that newlines don't get changed when you vendor